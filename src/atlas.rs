@@ -0,0 +1,239 @@
+/// Region of an atlas texture in texels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRect {
+    /// Texture coordinates as [u_min, v_min, u_max, v_max]
+    pub fn uv(&self, atlas_width: u32, atlas_height: u32) -> [f32; 4] {
+        [
+            self.x as f32 / atlas_width as f32,
+            self.y as f32 / atlas_height as f32,
+            (self.x + self.width) as f32 / atlas_width as f32,
+            (self.y + self.height) as f32 / atlas_height as f32,
+        ]
+    }
+}
+
+/// Row of rectangles, all at most as high as the row
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    // where the next rectangle goes
+    x: u32,
+}
+
+/// Packs rectangles into rows from top to bottom, good for similarly sized items like glyphs
+/// nothing can be freed, clear() starts over
+#[derive(Debug)]
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    // empty texels around every rectangle, so filtering doesn't bleed into neighbours
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Find room for a rectangle, None if the atlas is full
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padded_width = width + self.padding * 2;
+        let padded_height = height + self.padding * 2;
+        if padded_width > self.width {
+            return None;
+        }
+
+        // lowest shelf the rectangle fits into, wastes the least space
+        let atlas_width = self.width;
+        let best = self.shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= padded_height && atlas_width - shelf.x >= padded_width)
+            .min_by_key(|(_, shelf)| shelf.height)
+            .map(|(index, _)| index);
+        let index = match best {
+            Some(index) => index,
+            None => {
+                let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if self.height - y < padded_height {
+                    return None;
+                }
+                self.shelves.push(Shelf { y, height: padded_height, x: 0 });
+                self.shelves.len() - 1
+            },
+        };
+        let shelf = &mut self.shelves[index];
+
+        let rect = AtlasRect {
+            x: shelf.x + self.padding,
+            y: shelf.y + self.padding,
+            width,
+            height,
+        };
+        shelf.x += padded_width;
+        Some(rect)
+    }
+
+    /// Forget every rectangle
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+    }
+}
+
+/// Smallest atlas pack_images tries
+const MIN_IMAGE_ATLAS_SIZE: u32 = 64;
+
+/// Loose images combined into one, e.g. to draw them as sprites from a single texture
+pub struct PackedImages {
+    pub image: image::RgbaImage,
+    /// where each image ended up, by name
+    pub regions: std::collections::HashMap<String, AtlasRect>,
+}
+
+/// Pack named images into the smallest square with a power of two size, up to max_size
+/// None if they don't fit even then
+pub fn pack_images(images: &[(String, image::RgbaImage)], max_size: u32) -> Option<PackedImages> {
+    // tallest first, so the shelves waste less space
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(images[index].1.height()));
+
+    let mut size = MIN_IMAGE_ATLAS_SIZE;
+    while size <= max_size {
+        let mut packer = ShelfPacker::new(size, size, 1);
+        let rects: Option<Vec<(usize, AtlasRect)>> = order
+            .iter()
+            .map(|&index| {
+                let (_, image) = &images[index];
+                packer.pack(image.width(), image.height()).map(|rect| (index, rect))
+            })
+            .collect();
+
+        if let Some(rects) = rects {
+            let mut image = image::RgbaImage::new(size, size);
+            let mut regions = std::collections::HashMap::new();
+            for (index, rect) in rects {
+                let (name, source) = &images[index];
+                image::imageops::replace(&mut image, source, rect.x, rect.y);
+                regions.insert(name.clone(), rect);
+            }
+            return Some(PackedImages { image, regions });
+        }
+        size *= 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &AtlasRect, b: &AtlasRect, padding: u32) -> bool {
+        a.x < b.x + b.width + padding
+            && b.x < a.x + a.width + padding
+            && a.y < b.y + b.height + padding
+            && b.y < a.y + a.height + padding
+    }
+
+    fn filled(width: u32, height: u32, value: u8) -> image::RgbaImage {
+        image::RgbaImage::from_pixel(width, height, image::Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn packed_rects_keep_their_distance() {
+        let padding = 2;
+        let mut packer = ShelfPacker::new(128, 128, padding);
+        let sizes = [(10, 12), (30, 8), (5, 5), (20, 20), (12, 12), (40, 3), (7, 19), (16, 16), (9, 9)];
+        let rects: Vec<_> = sizes
+            .iter()
+            .cycle()
+            .take(30)
+            .map(|&(width, height)| packer.pack(width, height).unwrap())
+            .collect();
+
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.x >= padding && a.y >= padding);
+            assert!(a.x + a.width + padding <= 128 && a.y + a.height + padding <= 128);
+            for b in &rects[i + 1..] {
+                // two paddings between neighbours, one of each
+                assert!(!overlaps(a, b, padding * 2), "{:?} and {:?} are too close", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn smaller_rects_reuse_a_shelf() {
+        let mut packer = ShelfPacker::new(64, 64, 0);
+        assert_eq!(packer.pack(16, 16), Some(AtlasRect { x: 0, y: 0, width: 16, height: 16 }));
+        assert_eq!(packer.pack(16, 8), Some(AtlasRect { x: 16, y: 0, width: 16, height: 8 }));
+        // too high for the first shelf
+        assert_eq!(packer.pack(8, 20), Some(AtlasRect { x: 0, y: 16, width: 8, height: 20 }));
+    }
+
+    #[test]
+    fn none_when_full() {
+        let mut packer = ShelfPacker::new(16, 16, 0);
+        assert_eq!(packer.pack(17, 1), None);
+        assert!(packer.pack(16, 8).is_some());
+        assert!(packer.pack(16, 8).is_some());
+        assert_eq!(packer.pack(1, 1), None);
+
+        packer.clear();
+        assert_eq!(packer.pack(16, 16), Some(AtlasRect { x: 0, y: 0, width: 16, height: 16 }));
+    }
+
+    #[test]
+    fn padding_counts_towards_the_size() {
+        let mut packer = ShelfPacker::new(16, 16, 1);
+        assert_eq!(packer.pack(15, 1), None);
+        assert_eq!(packer.pack(14, 14), Some(AtlasRect { x: 1, y: 1, width: 14, height: 14 }));
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn uv_of_a_rect() {
+        let rect = AtlasRect { x: 16, y: 32, width: 16, height: 32 };
+        assert_eq!(rect.uv(64, 128), [0.25, 0.25, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn pack_images_grows_by_powers_of_two() {
+        let images: Vec<_> = (0..4u8).map(|i| (format!("image{}", i), filled(40, 40, i * 50))).collect();
+        // one image per 64x64 atlas, 128 fits all of them
+        let packed = pack_images(&images, 1024).unwrap();
+        assert_eq!(packed.image.dimensions(), (128, 128));
+        assert_eq!(packed.regions.len(), 4);
+        for (name, image) in &images {
+            let rect = packed.regions[name];
+            assert_eq!((rect.width, rect.height), image.dimensions());
+            assert_eq!(packed.image.get_pixel(rect.x, rect.y), image.get_pixel(0, 0));
+            assert_eq!(packed.image.get_pixel(rect.x + 39, rect.y + 39), image.get_pixel(39, 39));
+        }
+
+        assert!(pack_images(&images, 64).is_none());
+    }
+
+    #[test]
+    fn pack_images_starts_small() {
+        let packed = pack_images(&[("dot".to_string(), filled(1, 1, 255))], 1024).unwrap();
+        assert_eq!(packed.image.dimensions(), (MIN_IMAGE_ATLAS_SIZE, MIN_IMAGE_ATLAS_SIZE));
+        assert_eq!(pack_images(&[], 1024).unwrap().regions.len(), 0);
+    }
+}
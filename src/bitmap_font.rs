@@ -0,0 +1,134 @@
+/// Size of a glyph in font pixels
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Size of a glyph including spacing, what the text advances by
+pub const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+/// Glyphs per row in the atlas
+const COLUMNS: u32 = 16;
+
+/// 5x7 glyphs, one byte per row, the highest of the 5 bits is the leftmost pixel
+/// lowercase letters are drawn as uppercase
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; 7])] = &[
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+];
+
+/// Glyph used for characters the font doesn't have
+const FALLBACK: char = '?';
+
+/// All glyphs rasterized into a single channel atlas, plus one solid cell for backgrounds
+pub struct BitmapFont {
+    pub width: u32,
+    pub height: u32,
+    /// one byte per pixel, 255 where the glyph is set
+    pub pixels: Vec<u8>,
+}
+
+impl BitmapFont {
+    pub fn new() -> Self {
+        // the solid cell comes after the last glyph
+        let cells = GLYPHS.len() as u32 + 1;
+        let rows = cells.div_ceil(COLUMNS);
+        let width = COLUMNS * CELL_WIDTH;
+        let height = rows * CELL_HEIGHT;
+        let mut pixels = vec![0; (width * height) as usize];
+
+        for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+            let (cell_x, cell_y) = Self::cell_origin(index);
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        pixels[((cell_y + y as u32) * width + cell_x + x) as usize] = 255;
+                    }
+                }
+            }
+        }
+
+        let (cell_x, cell_y) = Self::cell_origin(GLYPHS.len());
+        for y in 0..CELL_HEIGHT {
+            for x in 0..CELL_WIDTH {
+                pixels[((cell_y + y) * width + cell_x + x) as usize] = 255;
+            }
+        }
+
+        Self { width, height, pixels }
+    }
+
+    /// Texture coordinates of the whole cell of c, as [u_min, v_min, u_max, v_max]
+    pub fn uv(&self, c: char) -> [f32; 4] {
+        let c = c.to_ascii_uppercase();
+        let index = GLYPHS
+            .iter()
+            .position(|(glyph, _)| *glyph == c)
+            .or_else(|| GLYPHS.iter().position(|(glyph, _)| *glyph == FALLBACK))
+            .unwrap_or(0);
+        self.cell_uv(index)
+    }
+
+    /// Texture coordinates of a fully set cell
+    pub fn solid_uv(&self) -> [f32; 4] {
+        self.cell_uv(GLYPHS.len())
+    }
+
+    fn cell_uv(&self, index: usize) -> [f32; 4] {
+        let (x, y) = Self::cell_origin(index);
+        [
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            (x + CELL_WIDTH) as f32 / self.width as f32,
+            (y + CELL_HEIGHT) as f32 / self.height as f32,
+        ]
+    }
+
+    fn cell_origin(index: usize) -> (u32, u32) {
+        let index = index as u32;
+        ((index % COLUMNS) * CELL_WIDTH, (index / COLUMNS) * CELL_HEIGHT)
+    }
+}
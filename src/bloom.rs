@@ -0,0 +1,291 @@
+use wgpu::util::DeviceExt;
+
+/// Maximum number of levels of the blur pyramid, level 0 has half the screen size
+const MAX_LEVELS: usize = 6;
+
+/// Runtime tweakable parameters of the bloom effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// brightness above which pixels start to glow
+    pub threshold: f32,
+    /// strength of the glow added back to the scene
+    pub intensity: f32,
+    /// size of the upsample filter in texels, bigger => wider glow
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.3,
+            radius: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    radius: f32,
+}
+
+impl From<&BloomSettings> for BloomUniform {
+    fn from(settings: &BloomSettings) -> Self {
+        Self {
+            threshold: settings.threshold,
+            // transition of half the threshold looks smooth enough
+            knee: settings.threshold * 0.5,
+            intensity: settings.intensity,
+            radius: settings.radius,
+        }
+    }
+}
+
+/// Bright pass, downsample/upsample blur pyramid and additive composition
+/// scene -> bright -> level 0 -> down -> level 1 -> ... -> up (+=) -> level 0 -> composite (+=) -> scene
+pub struct Bloom {
+    pub settings: BloomSettings,
+
+    levels: Vec<crate::texture::Texture>,
+    level_bind_groups: Vec<wgpu::BindGroup>,
+
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+
+    bright_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        settings: BloomSettings,
+    ) -> Self {
+        let (levels, level_bind_groups) = Self::create_levels(device, input_bind_group_layout, sc_desc);
+
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Bloom Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[BloomUniform::from(&settings)]),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            }
+        );
+
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("bloom_uniform_bind_group_layout"),
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("bloom_uniform_bind_group"),
+        });
+
+        let layouts = &[input_bind_group_layout, &uniform_bind_group_layout];
+        // add onto whatever is already in the target
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+
+        let bright_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Bloom Bright Pass",
+            include_str!("bloom_bright.wgsl"),
+            layouts,
+            crate::post::HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        let downsample_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Bloom Downsample",
+            include_str!("bloom_downsample.wgsl"),
+            layouts,
+            crate::post::HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        let upsample_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Bloom Upsample",
+            include_str!("bloom_upsample.wgsl"),
+            layouts,
+            crate::post::HDR_FORMAT,
+            additive,
+        );
+        let composite_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Bloom Composite",
+            include_str!("bloom_composite.wgsl"),
+            layouts,
+            crate::post::HDR_FORMAT,
+            additive,
+        );
+
+        Self {
+            settings,
+
+            levels,
+            level_bind_groups,
+
+            uniform_buffer,
+            uniform_bind_group,
+
+            bright_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+        }
+    }
+
+    /// The pyramid always has to match the size of the swap_chain
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) {
+        let (levels, level_bind_groups) = Self::create_levels(device, input_bind_group_layout, sc_desc);
+        self.levels = levels;
+        self.level_bind_groups = level_bind_groups;
+    }
+
+    /// Write the current settings to the uniform buffer
+    pub fn update(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[BloomUniform::from(&self.settings)]));
+    }
+
+    /// Add bloom onto the scene target
+    /// scene_bind_group has to sample the same texture scene_view renders to
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_bind_group: &wgpu::BindGroup,
+        scene_view: &wgpu::TextureView,
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        crate::post::draw_fullscreen(
+            encoder,
+            "Bloom Bright Pass",
+            &self.bright_pipeline,
+            &[scene_bind_group, &self.uniform_bind_group],
+            &self.levels[0].view,
+            clear,
+        );
+
+        // blur by walking down the pyramid...
+        for level in 1..self.levels.len() {
+            crate::post::draw_fullscreen(
+                encoder,
+                "Bloom Downsample",
+                &self.downsample_pipeline,
+                &[&self.level_bind_groups[level - 1], &self.uniform_bind_group],
+                &self.levels[level].view,
+                clear,
+            );
+        }
+
+        // ...and back up again, accumulating every level
+        for level in (0..self.levels.len() - 1).rev() {
+            crate::post::draw_fullscreen(
+                encoder,
+                "Bloom Upsample",
+                &self.upsample_pipeline,
+                &[&self.level_bind_groups[level + 1], &self.uniform_bind_group],
+                &self.levels[level].view,
+                wgpu::LoadOp::Load,
+            );
+        }
+
+        crate::post::draw_fullscreen(
+            encoder,
+            "Bloom Composite",
+            &self.composite_pipeline,
+            &[&self.level_bind_groups[0], &self.uniform_bind_group],
+            scene_view,
+            wgpu::LoadOp::Load,
+        );
+    }
+
+    fn create_levels(
+        device: &wgpu::Device,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> (Vec<crate::texture::Texture>, Vec<wgpu::BindGroup>) {
+        let mut levels = Vec::new();
+        let mut width = sc_desc.width / 2;
+        let mut height = sc_desc.height / 2;
+
+        // stop early for tiny windows, a level should be at least 1x1
+        while levels.len() < MAX_LEVELS && width > 0 && height > 0 {
+            levels.push(crate::texture::Texture::create_render_target(
+                device,
+                width,
+                height,
+                crate::post::HDR_FORMAT,
+                1,
+                "bloom_level",
+            ));
+            width /= 2;
+            height /= 2;
+        }
+
+        // bright pass always needs somewhere to go
+        if levels.is_empty() {
+            levels.push(crate::texture::Texture::create_render_target(
+                device,
+                1,
+                1,
+                crate::post::HDR_FORMAT,
+                1,
+                "bloom_level",
+            ));
+        }
+
+        let bind_groups = levels
+            .iter()
+            .map(|level| crate::post::create_input_bind_group(device, input_bind_group_layout, level))
+            .collect();
+
+        (levels, bind_groups)
+    }
+}
@@ -0,0 +1,30 @@
+// Extract everything brighter than the threshold, at half resolution
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Bloom {
+	threshold: f32;
+	// width of the soft transition around the threshold
+	knee: f32;
+	intensity: f32;
+	radius: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Bloom;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let color = textureSample(t_input, s_input, in.texture_coords).rgb;
+	let brightness = max(color.r, max(color.g, color.b));
+
+	// quadratic curve between threshold - knee and threshold + knee
+	let curve = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+	let soft = curve * curve / (4.0 * params.knee + 0.00001);
+	let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.00001);
+
+	return vec4<f32>(color * contribution, 1.0);
+}
@@ -0,0 +1,22 @@
+// Add the blurred highlights back onto the scene
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Bloom {
+	threshold: f32;
+	knee: f32;
+	intensity: f32;
+	radius: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Bloom;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let bloom = textureSample(t_input, s_input, in.texture_coords).rgb;
+	return vec4<f32>(bloom * params.intensity, 0.0);
+}
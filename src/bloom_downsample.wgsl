@@ -0,0 +1,20 @@
+// Halve the resolution, each bilinear sample averages 4 texels
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(t_input);
+	let texel = vec2<f32>(1.0 / f32(size.x), 1.0 / f32(size.y));
+	let uv = in.texture_coords;
+
+	let sum =
+		textureSample(t_input, s_input, uv + vec2<f32>(-1.0, -1.0) * texel).rgb +
+		textureSample(t_input, s_input, uv + vec2<f32>(1.0, -1.0) * texel).rgb +
+		textureSample(t_input, s_input, uv + vec2<f32>(-1.0, 1.0) * texel).rgb +
+		textureSample(t_input, s_input, uv + vec2<f32>(1.0, 1.0) * texel).rgb;
+
+	return vec4<f32>(sum * 0.25, 1.0);
+}
@@ -0,0 +1,42 @@
+// Upsample with a 3x3 tent filter, gets added onto the next larger level
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Bloom {
+	threshold: f32;
+	knee: f32;
+	intensity: f32;
+	// tent filter size in texels of the smaller level
+	radius: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Bloom;
+
+fn tap(uv: vec2<f32>, offset: vec2<f32>) -> vec3<f32> {
+	return textureSample(t_input, s_input, uv + offset).rgb;
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(t_input);
+	let d = vec2<f32>(params.radius / f32(size.x), params.radius / f32(size.y));
+	let uv = in.texture_coords;
+
+	var sum: vec3<f32> = tap(uv, vec2<f32>(0.0, 0.0)) * 4.0;
+	sum = sum + (
+		tap(uv, vec2<f32>(-d.x, 0.0)) +
+		tap(uv, vec2<f32>(d.x, 0.0)) +
+		tap(uv, vec2<f32>(0.0, -d.y)) +
+		tap(uv, vec2<f32>(0.0, d.y))) * 2.0;
+	sum = sum +
+		tap(uv, vec2<f32>(-d.x, -d.y)) +
+		tap(uv, vec2<f32>(d.x, -d.y)) +
+		tap(uv, vec2<f32>(-d.x, d.y)) +
+		tap(uv, vec2<f32>(d.x, d.y));
+
+	return vec4<f32>(sum / 16.0, 1.0);
+}
@@ -0,0 +1,181 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Transform, Vector3};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// Smallest box containing all points, None if there are no points
+    pub fn from_points<I: IntoIterator<Item = Point3<f32>>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut aabb = Self { min: first, max: first };
+        for point in points {
+            aabb.min = Point3::new(aabb.min.x.min(point.x), aabb.min.y.min(point.y), aabb.min.z.min(point.z));
+            aabb.max = Point3::new(aabb.max.x.max(point.x), aabb.max.y.max(point.y), aabb.max.z.max(point.z));
+        }
+        Some(aabb)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        Point3::new(
+            (self.min.x + self.max.x) * 0.5,
+            (self.min.y + self.max.y) * 0.5,
+            (self.min.z + self.max.z) * 0.5,
+        )
+    }
+
+    /// Half the size along every axis
+    #[allow(dead_code)]
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Box around this box after transforming it, e.g. from model to world space
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        // a box always has corners, so there is always a result
+        Self::from_points(self.corners().iter().map(|corner| matrix.transform_point(*corner))).unwrap()
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, point: Point3<f32>) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z
+    }
+
+    /// Smallest box containing both boxes
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        }
+    }
+}
+
+/// Bounding sphere, cheaper to test than an Aabb but usually less tight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Sphere around the center of the points' Aabb, None if there are no points
+    pub fn from_points<I: IntoIterator<Item = Point3<f32>> + Clone>(points: I) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| (point - center).magnitude())
+            .fold(0.0, f32::max);
+        Some(Self { center, radius })
+    }
+
+    /// Sphere around this sphere after transforming it
+    /// non uniform scale makes the sphere as big as the biggest axis
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = matrix.x.truncate().magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+        Self {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: Point3::new(-1.0, -1.0, -1.0),
+            max: Point3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn assert_close(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn from_points() {
+        assert_eq!(Aabb::from_points(Vec::new()), None);
+        let aabb = Aabb::from_points(vec![
+            Point3::new(1.0, -2.0, 3.0),
+            Point3::new(-1.0, 4.0, 0.0),
+            Point3::new(0.0, 0.0, 5.0),
+        ])
+        .unwrap();
+        assert_eq!(aabb.min, Point3::new(-1.0, -2.0, 0.0));
+        assert_eq!(aabb.max, Point3::new(1.0, 4.0, 5.0));
+        assert_eq!(aabb.center(), Point3::new(0.0, 1.0, 2.5));
+        assert_eq!(aabb.extents(), Vector3::new(1.0, 3.0, 2.5));
+    }
+
+    #[test]
+    fn transformed_by_translation_and_scale() {
+        let matrix = Matrix4::from_translation(Vector3::new(10.0, 0.0, -5.0))
+            * Matrix4::from_nonuniform_scale(2.0, 3.0, 1.0);
+        let aabb = unit_box().transformed(&matrix);
+        assert_close(aabb.min, Point3::new(8.0, -3.0, -6.0));
+        assert_close(aabb.max, Point3::new(12.0, 3.0, -4.0));
+    }
+
+    #[test]
+    fn transformed_by_rotation_grows() {
+        // a cube turned by 45° around y needs a box sqrt(2) times as wide
+        let aabb = unit_box().transformed(&Matrix4::from_angle_y(cgmath::Deg(45.0)));
+        let half = std::f32::consts::SQRT_2;
+        assert_close(aabb.min, Point3::new(-half, -1.0, -half));
+        assert_close(aabb.max, Point3::new(half, 1.0, half));
+    }
+
+    #[test]
+    fn contains_and_union() {
+        let aabb = unit_box();
+        assert!(aabb.contains(Point3::new(1.0, 0.0, -1.0)));
+        assert!(!aabb.contains(Point3::new(1.1, 0.0, 0.0)));
+
+        let other = Aabb {
+            min: Point3::new(0.0, 2.0, 0.0),
+            max: Point3::new(3.0, 3.0, 0.5),
+        };
+        let union = aabb.union(&other);
+        assert_eq!(union.min, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(union.max, Point3::new(3.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn sphere_from_points_and_transformed() {
+        let sphere = BoundingSphere::from_points(unit_box().corners().to_vec()).unwrap();
+        assert_close(sphere.center, Point3::new(0.0, 0.0, 0.0));
+        assert!((sphere.radius - 3.0f32.sqrt()).abs() < 1e-5);
+
+        // non uniform scale takes the biggest axis
+        let matrix = Matrix4::from_translation(Vector3::new(0.0, 1.0, 0.0))
+            * Matrix4::from_nonuniform_scale(1.0, 4.0, 2.0);
+        let transformed = sphere.transformed(&matrix);
+        assert_close(transformed.center, Point3::new(0.0, 1.0, 0.0));
+        assert!((transformed.radius - 4.0 * 3.0f32.sqrt()).abs() < 1e-4);
+    }
+}
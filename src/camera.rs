@@ -6,6 +6,7 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+#[derive(Debug, Clone, Copy)]
 pub struct Camera {
     pub eye: cgmath::Point3<f32>,
     pub target: cgmath::Point3<f32>,
@@ -17,10 +18,98 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Camera at the world transform of a scene node, looking down its -z axis
+    pub fn from_attachment(
+        attachment: &crate::scene::CameraAttachment,
+        world: &cgmath::Matrix4<f32>,
+        aspect: f32,
+    ) -> Self {
+        use cgmath::InnerSpace;
+        let eye = cgmath::Point3::from_homogeneous(world.w);
+        let forward = -world.z.truncate().normalize();
+        Self {
+            eye,
+            target: eye + forward,
+            up: world.y.truncate().normalize(),
+            aspect,
+            fovy: attachment.fovy,
+            znear: attachment.znear,
+            zfar: attachment.zfar,
+        }
+    }
+
+    /// World transform of a node carrying this camera, the inverse of from_attachment
+    pub fn world_transform(&self) -> cgmath::Matrix4<f32> {
+        use cgmath::SquareMatrix;
+        self.build_view_matrix().invert().unwrap_or_else(cgmath::Matrix4::identity)
+    }
+
+    /// Blend the placement towards other, t = 0 is self, t = 1 is other
+    /// projection parameters are taken from other
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            eye: self.eye + (other.eye - self.eye) * t,
+            target: self.target + (other.target - self.target) * t,
+            up: self.up + (other.up - self.up) * t,
+            ..*other
+        }
+    }
+
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
+        self.build_projection_matrix() * self.build_view_matrix()
+    }
+
+    /// World space -> view space
+    pub fn build_view_matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    /// View space -> clip space, with wgpu's depth range of [0, 1]
+    pub fn build_projection_matrix(&self) -> cgmath::Matrix4<f32> {
         let projection =
             cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
-        return OPENGL_TO_WGPU_MATRIX * projection * view;
+        OPENGL_TO_WGPU_MATRIX * projection
+    }
+}
+
+/// 2D camera for sprites, one world unit is one pixel at zoom 1, y points up
+#[derive(Debug, Clone, Copy)]
+pub struct OrthographicCamera {
+    /// world position in the center of the screen
+    pub position: cgmath::Point2<f32>,
+    pub zoom: f32,
+    /// size of the target in pixels
+    pub width: f32,
+    pub height: f32,
+}
+
+impl OrthographicCamera {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            position: cgmath::Point2::new(0.0, 0.0),
+            zoom: 1.0,
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as f32;
+        self.height = height as f32;
+    }
+
+    /// World space -> clip space
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        let half_width = self.width / 2.0 / self.zoom;
+        let half_height = self.height / 2.0 / self.zoom;
+        let projection = cgmath::ortho(
+            self.position.x - half_width,
+            self.position.x + half_width,
+            self.position.y - half_height,
+            self.position.y + half_height,
+            -1.0,
+            1.0,
+        );
+        OPENGL_TO_WGPU_MATRIX * projection
     }
 }
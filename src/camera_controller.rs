@@ -16,7 +16,10 @@ enum ButtonPress {
 }
 
 pub struct CameraController {
-    speed: f32,
+    /// distance moved per update
+    pub speed: f32,
+    /// pixels of mouse movement per unit the target moves
+    pub mouse_slowdown: f32,
     button_press: Option<ButtonPress>,
     old_mouse: PhysicalPosition<f64>,
     mouse_movement: Vector2<f64>,
@@ -26,6 +29,7 @@ impl CameraController {
     pub fn new(speed: f32) -> Self {
         Self {
             speed,
+            mouse_slowdown: MOUSE_SLOWDOWN,
             button_press: None,
             old_mouse: PhysicalPosition::new(0.0, 0.0),
             mouse_movement: Vector2::new(0.0, 0.0),
@@ -68,8 +72,8 @@ impl CameraController {
             },
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse_movement = Vector2 {
-                    x: (*position).x - self.old_mouse.x,
-                    y: (*position).y - self.old_mouse.y,
+                    x: position.x - self.old_mouse.x,
+                    y: position.y - self.old_mouse.y,
                 };
 
                 self.old_mouse = *position;
@@ -78,6 +82,13 @@ impl CameraController {
         }
     }
 
+    /// Forget held buttons and pending mouse movement,
+    /// e.g. when input goes somewhere else and the release would be missed
+    pub fn release(&mut self) {
+        self.button_press = None;
+        self.mouse_movement = Vector2::new(0.0, 0.0);
+    }
+
     /// Update the camera vectors
     /// Vectors are casted from (0, 0, 0) to both the target and the eye
     pub fn update_camera(&mut self, camera: &mut crate::camera::Camera) {
@@ -133,8 +144,8 @@ impl CameraController {
         // mouse movement:
         // target moves
         // eye stays in place
-        camera.target.x += (self.mouse_movement.x as f32) / MOUSE_SLOWDOWN;
-        camera.target.y -= (self.mouse_movement.y as f32) / MOUSE_SLOWDOWN;
+        camera.target.x += (self.mouse_movement.x as f32) / self.mouse_slowdown;
+        camera.target.y -= (self.mouse_movement.y as f32) / self.mouse_slowdown;
         self.mouse_movement = Vector2 { x: 0.0, y: 0.0 };
 
         dbg!(&camera.eye);
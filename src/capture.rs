@@ -0,0 +1,365 @@
+/// Frames, which can be read back at the same time
+/// if all of them are still in flight, a requested capture waits for the next frame
+const CAPTURES_IN_FLIGHT: usize = 3;
+/// Every supported swap chain format has 4 bytes per pixel
+const BYTES_PER_PIXEL: u32 = 4;
+
+/// Why a frame was captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureKind {
+    Screenshot,
+    /// frame number of a recording
+    Recording { frame: u64 },
+    /// handed back to the caller of State::capture_frame
+    Image,
+}
+
+/// A frame, which made it back from the gpu
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub kind: CaptureKind,
+    pub width: u32,
+    pub height: u32,
+    /// tightly packed RGBA8 rows, top row first
+    pub pixels: Vec<u8>,
+}
+
+/// Texture to buffer copies need rows aligned to COPY_BYTES_PER_ROW_ALIGNMENT
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * BYTES_PER_PIXEL;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(alignment) * alignment
+}
+
+/// Drop the row padding and swap blue and red, if the format stores them the other way around
+pub fn to_rgba(data: &[u8], width: u32, height: u32, padded_bytes_per_row: u32, format: wgpu::TextureFormat) -> Vec<u8> {
+    let bgra = matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+    let row_bytes = (width * BYTES_PER_PIXEL) as usize;
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in data.chunks(padded_bytes_per_row as usize).take(height as usize) {
+        let row = &row[..row_bytes];
+        if bgra {
+            for pixel in row.chunks_exact(BYTES_PER_PIXEL as usize) {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+            }
+        } else {
+            pixels.extend_from_slice(row);
+        }
+    }
+    pixels
+}
+
+/// Write a frame to a PNG on another thread, so the render loop keeps going
+pub fn save_png(frame: CapturedFrame, path: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        if let Some(directory) = path.parent() {
+            if let Err(error) = std::fs::create_dir_all(directory) {
+                log::error!("Could not create {:?}: {}", directory, error);
+                return;
+            }
+        }
+        match image::save_buffer(&path, &frame.pixels, frame.width, frame.height, image::ColorType::Rgba8) {
+            Ok(()) => log::info!("Saved {:?}", path),
+            Err(error) => log::error!("Could not save {:?}: {}", path, error),
+        }
+    });
+}
+
+/// File name with the current time, e.g. screenshot-1634567890123.png
+pub fn timestamped_name(prefix: &str) -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    format!("{}-{}.png", prefix, millis)
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    kind: CaptureKind,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    // Some while the gpu hasn't handed the pixels back yet
+    pending: Option<crate::readback::PendingMap>,
+}
+
+/// Copies frames from the gpu, without waiting for them
+/// a captured frame is drawn into an offscreen texture, which is copied to a buffer and
+/// blitted to the swap chain, whose textures can't be copied from
+pub struct FrameCapture {
+    texture: crate::texture::Texture,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    blit_pipeline: wgpu::RenderPipeline,
+
+    readbacks: Vec<Readback>,
+    requests: std::collections::VecDeque<CaptureKind>,
+    // readback used by the frame being recorded
+    current: Option<usize>,
+}
+
+impl FrameCapture {
+    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> Self {
+        let texture = Self::create_texture(device, sc_desc);
+        let bind_group_layout = crate::post::create_input_bind_group_layout(device);
+        let bind_group = crate::post::create_input_bind_group(device, &bind_group_layout, &texture);
+        let blit_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Capture Blit Pipeline",
+            include_str!("post_blit.wgsl"),
+            &[&bind_group_layout],
+            sc_desc.format,
+            wgpu::BlendState::REPLACE,
+        );
+
+        Self {
+            texture,
+            format: sc_desc.format,
+            width: sc_desc.width,
+            height: sc_desc.height,
+            bind_group_layout,
+            bind_group,
+            blit_pipeline,
+
+            readbacks: Vec::new(),
+            requests: std::collections::VecDeque::new(),
+            current: None,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.texture = Self::create_texture(device, sc_desc);
+        self.bind_group = crate::post::create_input_bind_group(device, &self.bind_group_layout, &self.texture);
+        self.width = sc_desc.width;
+        self.height = sc_desc.height;
+    }
+
+    /// Capture one of the next frames
+    pub fn request(&mut self, kind: CaptureKind) {
+        self.requests.push_back(kind);
+    }
+
+    /// Frames requested, but not yet drawn
+    #[allow(dead_code)]
+    pub fn requested(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Whether every readback is in flight, a frame requested now would have to wait
+    pub fn is_full(&self) -> bool {
+        self.readbacks.len() >= CAPTURES_IN_FLIGHT && self.readbacks.iter().all(|readback| readback.pending.is_some())
+    }
+
+    /// Block until every frame in flight made it back
+    pub fn finish(&mut self, device: &wgpu::Device) -> Vec<CapturedFrame> {
+        device.poll(wgpu::Maintain::Wait);
+        self.poll(device)
+    }
+
+    /// Start capturing the frame if one was requested, returns the view to draw it into
+    /// instead of the swap chain, None if this frame isn't captured
+    pub fn begin_frame(&mut self, device: &wgpu::Device) -> Option<wgpu::TextureView> {
+        if self.requests.is_empty() {
+            return None;
+        }
+        let padded_bytes_per_row = padded_bytes_per_row(self.width);
+        let size = (padded_bytes_per_row * self.height) as wgpu::BufferAddress;
+
+        let index = match self.readbacks.iter().position(|readback| readback.pending.is_none()) {
+            Some(index) => index,
+            None if self.readbacks.len() < CAPTURES_IN_FLIGHT => {
+                self.readbacks.push(Readback {
+                    buffer: Self::create_buffer(device, size),
+                    size,
+                    kind: CaptureKind::Screenshot,
+                    width: 0,
+                    height: 0,
+                    padded_bytes_per_row: 0,
+                    pending: None,
+                });
+                self.readbacks.len() - 1
+            },
+            // the gpu is behind, try again next frame
+            None => return None,
+        };
+
+        let readback = &mut self.readbacks[index];
+        // the window was resized since the buffer was used
+        if readback.size != size {
+            readback.buffer = Self::create_buffer(device, size);
+            readback.size = size;
+        }
+        readback.kind = self.requests.pop_front()?;
+        readback.width = self.width;
+        readback.height = self.height;
+        readback.padded_bytes_per_row = padded_bytes_per_row;
+        self.current = Some(index);
+
+        Some(self.texture.texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Copy the captured frame to its readback buffer and show it on output
+    pub fn end_frame(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let readback = match self.current {
+            Some(index) => &self.readbacks[index],
+            None => return,
+        };
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(readback.padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(readback.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: readback.width,
+                height: readback.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        crate::post::draw_fullscreen(
+            encoder,
+            "Capture Blit Pass",
+            &self.blit_pipeline,
+            &[&self.bind_group],
+            output,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        );
+    }
+
+    /// Start reading the frame back, has to be called after the encoder was submitted
+    pub fn after_submit(&mut self) {
+        if let Some(index) = self.current.take() {
+            let readback = &mut self.readbacks[index];
+            readback.pending = Some(crate::readback::PendingMap::new(
+                readback.buffer.slice(..),
+                wgpu::MapMode::Read,
+            ));
+        }
+    }
+
+    /// Frames, which finished reading back since the last call, oldest first, never blocks
+    pub fn poll(&mut self, device: &wgpu::Device) -> Vec<CapturedFrame> {
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut frames = Vec::new();
+        for readback in &mut self.readbacks {
+            let result = match readback.pending.as_mut().and_then(|pending| pending.poll()) {
+                Some(result) => result,
+                None => continue,
+            };
+            readback.pending = None;
+
+            if result.is_err() {
+                log::warn!("Could not read back a captured frame");
+                continue;
+            }
+
+            let pixels = {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                to_rgba(&data, readback.width, readback.height, readback.padded_bytes_per_row, self.format)
+            };
+            readback.buffer.unmap();
+            frames.push(CapturedFrame {
+                kind: readback.kind,
+                width: readback.width,
+                height: readback.height,
+                pixels,
+            });
+        }
+        frames
+    }
+
+    fn create_texture(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> crate::texture::Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: sc_desc.format,
+            // SAMPLED: blitted to the swap chain, COPY_SRC: copied to the readback buffer
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // same size as the output, no filtering needed
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        crate::texture::Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_the_alignment() {
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        assert_eq!(padded_bytes_per_row(1), alignment);
+        assert_eq!(padded_bytes_per_row(64), alignment);
+        assert_eq!(padded_bytes_per_row(65), 2 * alignment);
+        assert_eq!(padded_bytes_per_row(100), 2 * alignment);
+        assert_eq!(padded_bytes_per_row(1000), 16 * alignment);
+    }
+
+    #[test]
+    fn to_rgba_drops_the_padding() {
+        let padded = padded_bytes_per_row(2);
+        let mut data = vec![0xff; (padded * 2) as usize];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[padded as usize..padded as usize + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+
+        let pixels = to_rgba(&data, 2, 2, padded, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(pixels, (1..=16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn to_rgba_swaps_blue_and_red_of_bgra() {
+        let padded = padded_bytes_per_row(2);
+        let mut data = vec![0; padded as usize];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        for format in [wgpu::TextureFormat::Bgra8Unorm, wgpu::TextureFormat::Bgra8UnormSrgb] {
+            let pixels = to_rgba(&data, 2, 1, padded, format);
+            assert_eq!(pixels, vec![3, 2, 1, 4, 7, 6, 5, 8]);
+        }
+    }
+}
@@ -0,0 +1,666 @@
+/// Undo steps kept by default, older ones are dropped
+pub const DEFAULT_HISTORY: usize = 100;
+
+/// Name and merging of a command, independent of what it's applied to
+pub trait Edit {
+    /// Short description for menus and logs
+    fn name(&self) -> String;
+    /// Fold a later edit of the same thing into this one, e.g. every frame of a slider drag
+    /// false if other has to stay a command of its own
+    fn merge(&mut self, _other: &Self) -> bool {
+        false
+    }
+}
+
+/// An edit of T, which can be done and undone
+pub trait Command<T>: Edit {
+    fn apply(&self, target: &mut T);
+    fn revert(&self, target: &mut T);
+}
+
+/// Commands, which are undone and redone together
+#[derive(Debug, Clone)]
+pub struct Transaction<C> {
+    pub name: String,
+    pub commands: Vec<C>,
+}
+
+impl<C> Transaction<C> {
+    fn apply<T>(&self, target: &mut T)
+    where
+        C: Command<T>,
+    {
+        for command in &self.commands {
+            command.apply(target);
+        }
+    }
+
+    fn revert<T>(&self, target: &mut T)
+    where
+        C: Command<T>,
+    {
+        for command in self.commands.iter().rev() {
+            command.revert(target);
+        }
+    }
+}
+
+/// Done and undone transactions, undo walks back through the done ones
+/// commands outside of begin/commit_transaction are transactions of their own
+#[derive(Debug, Clone)]
+pub struct CommandStack<C> {
+    done: Vec<Transaction<C>>,
+    undone: Vec<Transaction<C>>,
+    // collects commands until it's committed
+    open: Option<Transaction<C>>,
+    // begin_transaction calls without a commit, nested ones join the outermost
+    depth: usize,
+    limit: usize,
+}
+
+impl<C> CommandStack<C> {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_HISTORY)
+    }
+
+    /// Keep at most limit undo steps, at least one
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            open: None,
+            depth: 0,
+            limit: limit.max(1),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Change the number of undo steps, drops the oldest ones if there are too many
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1);
+        self.trim();
+    }
+
+    /// Apply a command and remember it, anything undone before can't be redone anymore
+    pub fn execute<T>(&mut self, command: C, target: &mut T)
+    where
+        C: Command<T>,
+    {
+        command.apply(target);
+        self.push(command);
+    }
+
+    /// Remember a command, which was already applied, e.g. step by step during a drag
+    pub fn push(&mut self, command: C)
+    where
+        C: Edit,
+    {
+        self.undone.clear();
+        match &mut self.open {
+            Some(transaction) => {
+                let merged = transaction.commands
+                    .last_mut()
+                    .is_some_and(|last| last.merge(&command));
+                if !merged {
+                    transaction.commands.push(command);
+                }
+            },
+            None => {
+                let name = command.name();
+                self.done.push(Transaction { name, commands: vec![command] });
+                self.trim();
+            },
+        }
+    }
+
+    /// Group the following commands into one undo step, until commit_transaction
+    pub fn begin_transaction(&mut self, name: &str) {
+        self.depth += 1;
+        if self.open.is_none() {
+            self.open = Some(Transaction {
+                name: name.to_string(),
+                commands: Vec::new(),
+            });
+        }
+    }
+
+    /// Close the innermost transaction, the outermost one becomes an undo step unless it's empty
+    pub fn commit_transaction(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth > 0 {
+            return;
+        }
+        if let Some(transaction) = self.open.take() {
+            if !transaction.commands.is_empty() {
+                self.done.push(transaction);
+                self.trim();
+            }
+        }
+    }
+
+    /// Revert everything since the outermost begin_transaction and forget it
+    pub fn cancel_transaction<T>(&mut self, target: &mut T)
+    where
+        C: Command<T>,
+    {
+        self.depth = 0;
+        if let Some(transaction) = self.open.take() {
+            transaction.revert(target);
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Revert the last undo step, false if there is none
+    /// an open transaction is committed first
+    pub fn undo<T>(&mut self, target: &mut T) -> bool
+    where
+        C: Command<T>,
+    {
+        self.finish_transaction();
+        match self.done.pop() {
+            Some(transaction) => {
+                transaction.revert(target);
+                self.undone.push(transaction);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Apply the last undone step again, false if there is none
+    pub fn redo<T>(&mut self, target: &mut T) -> bool
+    where
+        C: Command<T>,
+    {
+        self.finish_transaction();
+        match self.undone.pop() {
+            Some(transaction) => {
+                transaction.apply(target);
+                self.done.push(transaction);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Names of the undo steps, the next one to undo last
+    pub fn undo_names(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.done.iter().map(|transaction| transaction.name.as_str())
+    }
+
+    /// Names of the redo steps, the next one to redo last
+    pub fn redo_names(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.undone.iter().map(|transaction| transaction.name.as_str())
+    }
+
+    /// Forget everything, e.g. after loading another scene
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.open = None;
+        self.depth = 0;
+    }
+
+    fn finish_transaction(&mut self) {
+        if self.open.is_some() {
+            self.depth = 1;
+            self.commit_transaction();
+        }
+    }
+
+    fn trim(&mut self) {
+        if self.done.len() > self.limit {
+            let excess = self.done.len() - self.limit;
+            self.done.drain(..excess);
+        }
+    }
+}
+
+impl<C> Default for CommandStack<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything scene edits can change
+pub struct EditTarget<'a> {
+    pub world: &'a mut hecs::World,
+    pub scene: &'a mut crate::scene::Scene,
+    pub materials: &'a mut [crate::material::Material],
+    pub ambient: &'a mut f32,
+}
+
+/// Change of the scene, with the value from before and after it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneEdit {
+    Transform {
+        entity: hecs::Entity,
+        before: crate::ecs::Transform,
+        after: crate::ecs::Transform,
+    },
+    Light {
+        entity: hecs::Entity,
+        before: crate::ecs::Light,
+        after: crate::ecs::Light,
+    },
+    Material {
+        index: usize,
+        before: crate::material::MaterialParams,
+        after: crate::material::MaterialParams,
+    },
+    Ambient {
+        before: f32,
+        after: f32,
+    },
+    /// the tree was added, e.g. as a copy
+    Spawn(crate::ecs::EntityTree),
+    /// the tree was removed
+    Despawn(crate::ecs::EntityTree),
+}
+
+impl SceneEdit {
+    /// Set the value from after the edit if forward, from before it otherwise
+    fn set(&self, target: &mut EditTarget, forward: bool) {
+        fn choose<T: Copy>(forward: bool, before: &T, after: &T) -> T {
+            if forward { *after } else { *before }
+        }
+        match self {
+            Self::Transform { entity, before, after } => {
+                crate::ecs::set_transform(target.world, *entity, choose(forward, before, after));
+            },
+            Self::Light { entity, before, after } => {
+                if let Ok(mut light) = target.world.get_mut::<crate::ecs::Light>(*entity) {
+                    *light = choose(forward, before, after);
+                }
+            },
+            Self::Material { index, before, after } => {
+                if let Some(material) = target.materials.get_mut(*index) {
+                    material.params = choose(forward, before, after);
+                }
+            },
+            Self::Ambient { before, after } => *target.ambient = choose(forward, before, after),
+            Self::Spawn(tree) | Self::Despawn(tree) => {
+                // undoing a despawn spawns
+                if forward == matches!(self, Self::Spawn(_)) {
+                    if !crate::ecs::restore_tree(target.world, target.scene, tree) {
+                        log::warn!("Could not bring back {}, its parent is gone", tree.name());
+                    }
+                } else {
+                    crate::ecs::despawn(target.world, target.scene, tree.root());
+                }
+            },
+        }
+    }
+}
+
+impl<'a> Command<EditTarget<'a>> for SceneEdit {
+    fn apply(&self, target: &mut EditTarget<'a>) {
+        self.set(target, true);
+    }
+
+    fn revert(&self, target: &mut EditTarget<'a>) {
+        self.set(target, false);
+    }
+}
+
+impl Edit for SceneEdit {
+    fn name(&self) -> String {
+        match self {
+            Self::Transform { .. } => "Transform".to_string(),
+            Self::Light { .. } => "Light".to_string(),
+            Self::Material { index, .. } => format!("Material {}", index),
+            Self::Ambient { .. } => "Ambient".to_string(),
+            Self::Spawn(tree) => format!("Add {}", tree.name()),
+            Self::Despawn(tree) => format!("Remove {}", tree.name()),
+        }
+    }
+
+    fn merge(&mut self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Transform { entity, after, .. }, Self::Transform { entity: other, after: later, .. })
+                if entity == other =>
+            {
+                *after = *later;
+                true
+            },
+            (Self::Light { entity, after, .. }, Self::Light { entity: other, after: later, .. })
+                if entity == other =>
+            {
+                *after = *later;
+                true
+            },
+            (Self::Material { index, after, .. }, Self::Material { index: other, after: later, .. })
+                if index == other =>
+            {
+                *after = *later;
+                true
+            },
+            (Self::Ambient { after, .. }, Self::Ambient { after: later, .. }) => {
+                *after = *later;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Values the debug UI can change, compared before and after it ran to record its edits
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneSnapshot {
+    lights: Vec<(hecs::Entity, crate::ecs::Light)>,
+    materials: Vec<crate::material::MaterialParams>,
+    ambient: f32,
+}
+
+impl SceneSnapshot {
+    pub fn take(world: &hecs::World, materials: &[crate::material::Material], ambient: f32) -> Self {
+        Self {
+            lights: world
+                .query::<&crate::ecs::Light>()
+                .iter()
+                .map(|(entity, light)| (entity, *light))
+                .collect(),
+            materials: materials.iter().map(|material| material.params).collect(),
+            ambient,
+        }
+    }
+
+    /// Edits from the snapshot to the current values, already applied
+    pub fn changes(
+        &self,
+        world: &hecs::World,
+        materials: &[crate::material::Material],
+        ambient: f32,
+    ) -> Vec<SceneEdit> {
+        let mut edits = Vec::new();
+        for (entity, before) in &self.lights {
+            if let Ok(after) = world.get::<crate::ecs::Light>(*entity).map(|light| *light) {
+                if after != *before {
+                    edits.push(SceneEdit::Light { entity: *entity, before: *before, after });
+                }
+            }
+        }
+        for (index, (before, material)) in self.materials.iter().zip(materials).enumerate() {
+            if material.params != *before {
+                edits.push(SceneEdit::Material { index, before: *before, after: material.params });
+            }
+        }
+        if ambient != self.ambient {
+            edits.push(SceneEdit::Ambient { before: self.ambient, after: ambient });
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds a number to the target, merging additions with the same tag
+    #[derive(Debug, Clone, PartialEq)]
+    struct Add {
+        tag: &'static str,
+        amount: i32,
+    }
+
+    fn add(amount: i32) -> Add {
+        Add { tag: "add", amount }
+    }
+
+    impl Edit for Add {
+        fn name(&self) -> String {
+            format!("{} {}", self.tag, self.amount)
+        }
+
+        fn merge(&mut self, other: &Self) -> bool {
+            if self.tag == "merge" && other.tag == "merge" {
+                self.amount += other.amount;
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    impl Command<i32> for Add {
+        fn apply(&self, target: &mut i32) {
+            *target += self.amount;
+        }
+
+        fn revert(&self, target: &mut i32) {
+            *target -= self.amount;
+        }
+    }
+
+    /// Multiplies the target, so the order of reverting matters
+    #[derive(Debug, Clone)]
+    enum Step {
+        Add(i32),
+        Times(i32),
+    }
+
+    impl Edit for Step {
+        fn name(&self) -> String {
+            format!("{:?}", self)
+        }
+    }
+
+    impl Command<i32> for Step {
+        fn apply(&self, target: &mut i32) {
+            match self {
+                Self::Add(amount) => *target += amount,
+                Self::Times(factor) => *target *= factor,
+            }
+        }
+
+        fn revert(&self, target: &mut i32) {
+            match self {
+                Self::Add(amount) => *target -= amount,
+                Self::Times(factor) => *target /= factor,
+            }
+        }
+    }
+
+    #[test]
+    fn execute_undo_redo_round_trip() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.execute(add(1), &mut value);
+        stack.execute(add(2), &mut value);
+        assert_eq!(value, 3);
+
+        assert!(stack.undo(&mut value));
+        assert_eq!(value, 1);
+        assert!(stack.undo(&mut value));
+        assert_eq!(value, 0);
+        assert!(!stack.undo(&mut value));
+        assert_eq!(value, 0);
+
+        assert!(stack.redo(&mut value));
+        assert!(stack.redo(&mut value));
+        assert_eq!(value, 3);
+        assert!(!stack.redo(&mut value));
+        assert_eq!(stack.undo_names().collect::<Vec<_>>(), ["add 1", "add 2"]);
+    }
+
+    #[test]
+    fn new_push_clears_redo() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.execute(add(1), &mut value);
+        stack.execute(add(2), &mut value);
+        stack.undo(&mut value);
+        assert!(stack.can_redo());
+
+        stack.execute(add(5), &mut value);
+        assert!(!stack.can_redo());
+        assert_eq!(stack.redo_names().count(), 0);
+        assert_eq!(value, 6);
+    }
+
+    #[test]
+    fn nested_transactions_join_the_outermost() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.begin_transaction("outer");
+        stack.execute(add(1), &mut value);
+        stack.begin_transaction("inner");
+        stack.execute(add(2), &mut value);
+        stack.commit_transaction();
+        // still open, the inner commit only closes the inner transaction
+        assert!(stack.in_transaction());
+        stack.execute(add(3), &mut value);
+        stack.commit_transaction();
+        assert!(!stack.in_transaction());
+
+        assert_eq!(stack.undo_names().collect::<Vec<_>>(), ["outer"]);
+        assert!(stack.undo(&mut value));
+        assert_eq!(value, 0);
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn empty_transaction_is_dropped() {
+        let mut stack = CommandStack::<Add>::new();
+        stack.begin_transaction("nothing");
+        stack.commit_transaction();
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn cancel_reverts_in_reverse_order() {
+        let mut stack = CommandStack::new();
+        let mut value = 1;
+        stack.begin_transaction("steps");
+        stack.execute(Step::Add(2), &mut value);
+        stack.execute(Step::Times(4), &mut value);
+        assert_eq!(value, 12);
+
+        // forward order would give (12 - 2) / 4 = 2
+        stack.cancel_transaction(&mut value);
+        assert_eq!(value, 1);
+        assert!(!stack.in_transaction());
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn undo_commits_an_open_transaction() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.begin_transaction("open");
+        stack.execute(add(4), &mut value);
+        assert!(stack.undo(&mut value));
+        assert_eq!(value, 0);
+        assert!(!stack.in_transaction());
+        assert!(stack.redo(&mut value));
+        assert_eq!(value, 4);
+    }
+
+    #[test]
+    fn merge_inside_a_transaction() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.begin_transaction("drag");
+        for _ in 0..3 {
+            stack.execute(Add { tag: "merge", amount: 1 }, &mut value);
+        }
+        stack.execute(add(10), &mut value);
+        stack.commit_transaction();
+
+        assert_eq!(stack.done[0].commands, [Add { tag: "merge", amount: 3 }, add(10)]);
+        stack.undo(&mut value);
+        assert_eq!(value, 0);
+    }
+
+    #[test]
+    fn merge_only_within_a_transaction() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.execute(Add { tag: "merge", amount: 1 }, &mut value);
+        stack.execute(Add { tag: "merge", amount: 1 }, &mut value);
+        assert_eq!(stack.undo_names().count(), 2);
+    }
+
+    #[test]
+    fn limit_drops_the_oldest_steps() {
+        let mut stack = CommandStack::with_limit(3);
+        let mut value = 0;
+        for amount in 1..=5 {
+            stack.execute(add(amount), &mut value);
+        }
+        assert_eq!(stack.limit(), 3);
+        assert_eq!(stack.undo_names().collect::<Vec<_>>(), ["add 3", "add 4", "add 5"]);
+
+        stack.set_limit(1);
+        assert_eq!(stack.undo_names().collect::<Vec<_>>(), ["add 5"]);
+        stack.undo(&mut value);
+        assert_eq!(value, 10);
+        assert!(!stack.can_undo());
+
+        // at least one step is always kept
+        assert_eq!(CommandStack::<Add>::with_limit(0).limit(), 1);
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let mut stack = CommandStack::new();
+        let mut value = 0;
+        stack.execute(add(1), &mut value);
+        stack.execute(add(2), &mut value);
+        stack.undo(&mut value);
+        stack.begin_transaction("open");
+        stack.clear();
+        assert!(!stack.can_undo());
+        assert!(!stack.can_redo());
+        assert!(!stack.in_transaction());
+    }
+
+    #[test]
+    fn removed_objects_come_back_on_undo() {
+        use crate::ecs::{Light, MeshRenderer, Transform};
+        let mut world = hecs::World::new();
+        let mut scene = crate::scene::Scene::new();
+        let mut ambient = 0.0;
+        let lamp = crate::ecs::spawn(&mut world, &mut scene, "lamp", Transform::identity(), None);
+        let bulb = crate::ecs::spawn(&mut world, &mut scene, "bulb", Transform::identity(), Some(lamp));
+        world.insert_one(lamp, MeshRenderer { mesh: 0, material: 0, visible: true }).unwrap();
+        world.insert_one(bulb, Light { color: [1.0; 3], intensity: 2.0, radius: 3.0 }).unwrap();
+        let tree = crate::ecs::copy_tree(&world, &scene, lamp).unwrap();
+
+        let mut stack = CommandStack::new();
+        let mut target = EditTarget { world: &mut world, scene: &mut scene, materials: &mut [], ambient: &mut ambient };
+        let moved = Transform::from_translation(cgmath::Vector3::new(0.0, 1.0, 0.0));
+        stack.execute(SceneEdit::Transform { entity: bulb, before: Transform::identity(), after: moved }, &mut target);
+        stack.execute(SceneEdit::Despawn(tree), &mut target);
+        assert_eq!(target.world.len(), 0);
+        assert!(target.scene.is_empty());
+        assert_eq!(stack.undo_names().collect::<Vec<_>>(), ["Transform", "Remove lamp"]);
+
+        assert!(stack.undo(&mut target));
+        assert_eq!(target.world.get::<Light>(bulb).unwrap().intensity, 2.0);
+        assert!(target.world.get::<MeshRenderer>(lamp).unwrap().visible);
+        assert_eq!(target.scene.len(), 2);
+        // the old handles are valid again, so the edits before the removal still work
+        assert!(stack.undo(&mut target));
+        assert_eq!(*target.world.get::<Transform>(bulb).unwrap(), Transform::identity());
+
+        assert!(stack.redo(&mut target));
+        assert_eq!(*target.world.get::<Transform>(bulb).unwrap(), moved);
+        assert!(stack.redo(&mut target));
+        assert!(!target.world.contains(lamp) && !target.world.contains(bulb));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::atlas::AtlasRect;
+use crate::sprite::{Sprite, SpriteBatch, SpriteTexture};
+
+/// Width and height of both images in pixels, odd so there is a center pixel
+const SIZE: u32 = 17;
+/// Radius of the ring in pixels
+const RING_RADIUS: f32 = 7.0;
+/// Color of the ring, while a gizmo handle is under the crosshair
+const HIGHLIGHT: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+
+const WHITE: image::Rgba<u8> = image::Rgba([255, 255, 255, 255]);
+const OUTLINE: image::Rgba<u8> = image::Rgba([0, 0, 0, 160]);
+const CLEAR: image::Rgba<u8> = image::Rgba([0, 0, 0, 0]);
+
+/// Marks the middle of the window, where clicks pick while the cursor is grabbed
+/// the cross and the ring are packed into one sprite texture
+pub struct Crosshair {
+    texture: SpriteTexture,
+    cross: AtlasRect,
+    ring: AtlasRect,
+}
+
+impl Crosshair {
+    pub fn new(batch: &mut SpriteBatch, device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<Self> {
+        let images = [("cross".to_string(), cross_image()), ("ring".to_string(), ring_image())];
+        let packed = crate::atlas::pack_images(&images, 64)
+            .ok_or_else(|| anyhow::anyhow!("the crosshair images don't fit into an atlas"))?;
+        let texture = batch.add_image(device, queue, &image::DynamicImage::ImageRgba8(packed.image), "crosshair")?;
+
+        Ok(Self {
+            texture,
+            cross: packed.regions["cross"],
+            ring: packed.regions["ring"],
+        })
+    }
+
+    /// Draw it in the center of the sprite camera this frame, with the ring if highlighted
+    pub fn draw(&self, batch: &mut SpriteBatch, highlighted: bool) {
+        let position = batch.camera.position;
+        batch.draw(Sprite {
+            region: Some(self.cross),
+            position,
+            ..Sprite::new(self.texture)
+        });
+        if highlighted {
+            batch.draw(Sprite {
+                region: Some(self.ring),
+                position,
+                color: HIGHLIGHT,
+                ..Sprite::new(self.texture)
+            });
+        }
+    }
+}
+
+/// Pixels from the center pixel along x and y
+fn distance_to_center(x: u32, y: u32) -> (i32, i32) {
+    let center = (SIZE / 2) as i32;
+    ((x as i32 - center).abs(), (y as i32 - center).abs())
+}
+
+/// White plus with a dark outline, readable in front of bright and dark scenes
+fn cross_image() -> image::RgbaImage {
+    image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let (dx, dy) = distance_to_center(x, y);
+        match dx.min(dy) {
+            0 => WHITE,
+            1 => OUTLINE,
+            _ => CLEAR,
+        }
+    })
+}
+
+/// White circle, gets its color when it's drawn
+fn ring_image() -> image::RgbaImage {
+    image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let (dx, dy) = distance_to_center(x, y);
+        let distance = ((dx * dx + dy * dy) as f32).sqrt();
+        if (distance - RING_RADIUS).abs() <= 0.5 {
+            WHITE
+        } else {
+            CLEAR
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_is_centered() {
+        let cross = cross_image();
+        let center = SIZE / 2;
+        assert_eq!(*cross.get_pixel(center, 0), WHITE);
+        assert_eq!(*cross.get_pixel(SIZE - 1, center), WHITE);
+        assert_eq!(*cross.get_pixel(center + 1, 0), OUTLINE);
+        assert_eq!(*cross.get_pixel(0, 0), CLEAR);
+    }
+
+    #[test]
+    fn ring_leaves_the_center_clear() {
+        let ring = ring_image();
+        let center = SIZE / 2;
+        assert_eq!(*ring.get_pixel(center, center), CLEAR);
+        assert_eq!(*ring.get_pixel(center + RING_RADIUS as u32, center), WHITE);
+        assert_eq!(*ring.get_pixel(center, center - RING_RADIUS as u32), WHITE);
+        assert_eq!(*ring.get_pixel(0, 0), CLEAR);
+    }
+
+    #[test]
+    fn both_images_fit_into_the_smallest_atlas() {
+        let images = [("cross".to_string(), cross_image()), ("ring".to_string(), ring_image())];
+        let packed = crate::atlas::pack_images(&images, 64).unwrap();
+        assert_eq!(packed.image.dimensions(), (64, 64));
+        assert_eq!(packed.regions.len(), 2);
+    }
+}
@@ -0,0 +1,367 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+/// Line segments per circle
+const CIRCLE_SEGMENTS: usize = 24;
+/// Length of an arrow head relative to the arrow
+const ARROW_HEAD: f32 = 0.2;
+/// Lines the vertex buffer has room for at first, it grows if there are more
+const INITIAL_CAPACITY: usize = 1024;
+
+pub const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+pub const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+pub const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+/// Corner pairs of Aabb::corners, which share an edge
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Line segments in world space, collected during a frame
+#[derive(Debug, Default)]
+pub struct DebugLines {
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugLines {
+    pub fn line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 4]) {
+        self.vertices.push(LineVertex { position: from.into(), color });
+        self.vertices.push(LineVertex { position: to.into(), color });
+    }
+
+    /// Axis aligned box
+    pub fn aabb(&mut self, aabb: &crate::bounds::Aabb, color: [f32; 4]) {
+        self.corners(&aabb.corners(), color);
+    }
+
+    /// Box in model space, transformed to world space, e.g. the bounds of a mesh instance
+    pub fn oriented_box(&mut self, aabb: &crate::bounds::Aabb, model: &cgmath::Matrix4<f32>, color: [f32; 4]) {
+        use cgmath::Transform;
+        let mut corners = aabb.corners();
+        for corner in corners.iter_mut() {
+            *corner = model.transform_point(*corner);
+        }
+        self.corners(&corners, color);
+    }
+
+    /// Circle around center in the plane spanned by the two unit axes
+    pub fn circle(
+        &mut self,
+        center: Point3<f32>,
+        axis_u: Vector3<f32>,
+        axis_v: Vector3<f32>,
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        let point = |segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (axis_u * angle.cos() + axis_v * angle.sin()) * radius
+        };
+        for segment in 0..CIRCLE_SEGMENTS {
+            self.line(point(segment), point(segment + 1), color);
+        }
+    }
+
+    /// One circle per axis plane
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 4]) {
+        self.circle(center, Vector3::unit_x(), Vector3::unit_y(), radius, color);
+        self.circle(center, Vector3::unit_y(), Vector3::unit_z(), radius, color);
+        self.circle(center, Vector3::unit_z(), Vector3::unit_x(), radius, color);
+    }
+
+    /// Line with a head of four lines at to
+    pub fn arrow(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 4]) {
+        self.line(from, to, color);
+        let direction = to - from;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = direction / length;
+        // any vector, which isn't parallel to the arrow
+        let helper = if direction.y.abs() < 0.99 { Vector3::unit_y() } else { Vector3::unit_x() };
+        let side = direction.cross(helper).normalize();
+        let up = side.cross(direction);
+        let head = length * ARROW_HEAD;
+        let base = to - direction * head;
+        for offset in [side, -side, up, -up].iter() {
+            self.line(to, base + *offset * head * 0.5, color);
+        }
+    }
+
+    /// Square grid in the xz plane, size is the length of a side
+    pub fn grid(&mut self, center: Point3<f32>, size: f32, divisions: u32, color: [f32; 4]) {
+        let divisions = divisions.max(1);
+        let half = size / 2.0;
+        for line in 0..=divisions {
+            let offset = line as f32 / divisions as f32 * size - half;
+            self.line(
+                center + Vector3::new(offset, 0.0, -half),
+                center + Vector3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vector3::new(-half, 0.0, offset),
+                center + Vector3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// x, y and z axis of a transform in red, green and blue
+    pub fn axes(&mut self, transform: &cgmath::Matrix4<f32>, length: f32) {
+        let origin = Point3::from_homogeneous(transform.w);
+        self.arrow(origin, origin + transform.x.truncate().normalize() * length, RED);
+        self.arrow(origin, origin + transform.y.truncate().normalize() * length, GREEN);
+        self.arrow(origin, origin + transform.z.truncate().normalize() * length, BLUE);
+    }
+
+    /// Edges of the volume a view projection matrix sees
+    pub fn frustum(&mut self, view_projection: &cgmath::Matrix4<f32>, color: [f32; 4]) {
+        use cgmath::{SquareMatrix, Transform};
+        let inverse = match view_projection.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        // clip space box, wgpu's depth goes from 0 to 1
+        let clip = crate::bounds::Aabb {
+            min: Point3::new(-1.0, -1.0, 0.0),
+            max: Point3::new(1.0, 1.0, 1.0),
+        };
+        let mut corners = clip.corners();
+        for corner in corners.iter_mut() {
+            *corner = inverse.transform_point(*corner);
+        }
+        self.corners(&corners, color);
+    }
+
+    /// Vertex normals of a mesh instance
+    pub fn normals(&mut self, mesh: &crate::mesh::Mesh, model: &cgmath::Matrix4<f32>, length: f32, color: [f32; 4]) {
+        use cgmath::Transform;
+        for vertex in &mesh.vertices {
+            let position = model.transform_point(vertex.position());
+            let normal = model.transform_vector(vertex.normal());
+            if normal.magnitude2() > 0.0 {
+                self.line(position, position + normal.normalize() * length, color);
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Edges between corners ordered like Aabb::corners
+    fn corners(&mut self, corners: &[Point3<f32>; 8], color: [f32; 4]) {
+        for (a, b) in BOX_EDGES.iter() {
+            self.line(corners[*a], corners[*b], color);
+        }
+    }
+}
+
+/// Immediate mode line drawing for debugging, everything has to be drawn again every frame
+/// lines are drawn into the HDR scene, so they go through post-processing like the scene
+pub struct DebugDraw {
+    /// hidden behind the scene like any other geometry
+    pub depth_tested: DebugLines,
+    /// always visible
+    pub on_top: DebugLines,
+
+    depth_tested_pipeline: wgpu::RenderPipeline,
+    on_top_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    // in vertices
+    capacity: usize,
+    // vertices of depth_tested and on_top in the buffer
+    depth_tested_count: u32,
+    on_top_count: u32,
+}
+
+impl DebugDraw {
+    /// sample_count has to match the scene target and depth texture it's drawn into
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Debug Draw Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("debug_draw.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Debug Draw Pipeline Layout"),
+                bind_group_layouts: &[uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let create_pipeline = |label, depth_compare| device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[LineVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format: crate::post::HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::texture::Texture::DEPTH_FORMAT,
+                    // lines never hide each other or the scene
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        );
+        let depth_tested_pipeline = create_pipeline("Debug Draw Pipeline", wgpu::CompareFunction::LessEqual);
+        let on_top_pipeline = create_pipeline("Debug Draw On Top Pipeline", wgpu::CompareFunction::Always);
+
+        let capacity = INITIAL_CAPACITY * 2;
+        let vertex_buffer = Self::create_vertex_buffer(device, capacity);
+
+        Self {
+            depth_tested: DebugLines::default(),
+            on_top: DebugLines::default(),
+
+            depth_tested_pipeline,
+            on_top_pipeline,
+            vertex_buffer,
+            capacity,
+            depth_tested_count: 0,
+            on_top_count: 0,
+        }
+    }
+
+    /// Upload the lines of this frame and start collecting the next one
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.depth_tested_count = self.depth_tested.vertices.len() as u32;
+        self.on_top_count = self.on_top.vertices.len() as u32;
+
+        let needed = self.depth_tested.vertices.len() + self.on_top.vertices.len();
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.depth_tested.vertices));
+        let offset = self.depth_tested.vertices.len() * std::mem::size_of::<LineVertex>();
+        queue.write_buffer(&self.vertex_buffer, offset as wgpu::BufferAddress, bytemuck::cast_slice(&self.on_top.vertices));
+
+        self.depth_tested.clear();
+        self.on_top.clear();
+    }
+
+    /// Draw the prepared lines into the scene, with the depth buffer the scene was drawn with
+    /// resolve_target is needed if target is multisampled
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+        uniform_bind_group: &wgpu::BindGroup,
+    ) {
+        if self.depth_tested_count + self.on_top_count == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug Draw Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_bind_group(0, uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        let end = self.depth_tested_count + self.on_top_count;
+        if self.depth_tested_count > 0 {
+            render_pass.set_pipeline(&self.depth_tested_pipeline);
+            render_pass.draw(0..self.depth_tested_count, 0..1);
+        }
+        if self.on_top_count > 0 {
+            render_pass.set_pipeline(&self.on_top_pipeline);
+            render_pass.draw(self.depth_tested_count..end, 0..1);
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Draw Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
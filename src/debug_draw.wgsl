@@ -0,0 +1,32 @@
+// Vertex shader
+// only the start of the camera uniform is needed
+[[block]]
+struct Uniform {
+	view_projection: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> uniform: Uniform;
+
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn main(in: VertexInput) -> VertexOutput {
+	var out: VertexOutput;
+	out.clip_coordinate = uniform.view_projection * vec4<f32>(in.position, 1.0);
+	out.color = in.color;
+	return out;
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return in.color;
+}
@@ -0,0 +1,254 @@
+use wgpu::util::DeviceExt;
+
+/// Replaces the shaded scene with a visualization of its geometry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    /// the normal, shaded scene
+    #[default]
+    Lit,
+    Wireframe,
+    /// world space normals as colors
+    Normals,
+    UvChecker,
+    /// distance to the camera, white is close
+    Depth,
+    /// how often every pixel is drawn, brighter is more
+    Overdraw,
+}
+
+impl DebugView {
+    pub const ALL: [Self; 6] = [
+        Self::Lit,
+        Self::Wireframe,
+        Self::Normals,
+        Self::UvChecker,
+        Self::Depth,
+        Self::Overdraw,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lit => "Lit",
+            Self::Wireframe => "Wireframe",
+            Self::Normals => "Normals",
+            Self::UvChecker => "UV Checker",
+            Self::Depth => "Depth",
+            Self::Overdraw => "Overdraw",
+        }
+    }
+
+    /// The view after this one, back to Lit after the last
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|view| view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Pipelines of the debug views, drawn with the same attachments as the scene
+pub struct DebugViews {
+    wireframe: wgpu::RenderPipeline,
+    // false if the adapter can't rasterize lines, the wireframe is drawn
+    // from expanded_meshes with barycentric coordinates then
+    line_mode: bool,
+    normals: wgpu::RenderPipeline,
+    uv_checker: wgpu::RenderPipeline,
+    depth: wgpu::RenderPipeline,
+    overdraw: wgpu::RenderPipeline,
+
+    // copies of the meshes without index buffer, every 3 vertices are a triangle
+    expanded_meshes: Vec<(wgpu::Buffer, u32)>,
+}
+
+impl DebugViews {
+    /// Features for the wireframe with real lines, request these if the adapter has them
+    /// wgpu 0.9 calls PolygonMode::Line support NON_FILL_POLYGON_MODE
+    pub const FEATURES: wgpu::Features = wgpu::Features::NON_FILL_POLYGON_MODE;
+
+    /// sample_count has to match the scene target and depth texture it's drawn into
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+        meshes: &[crate::mesh::Mesh],
+    ) -> Self {
+        let line_mode = device.features().contains(Self::FEATURES);
+        if !line_mode {
+            log::info!("Wireframe without line rasterization, drawing it with barycentric coordinates");
+        }
+
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Debug View Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("debug_view.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Debug View Pipeline Layout"),
+                bind_group_layouts: &[uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let create_pipeline = |label, entry_point, polygon_mode, cull_mode, blend, depth_compare| {
+            device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "main",
+                        buffers: &[crate::vertex::Vertex::desc(), crate::instance::InstanceRaw::desc()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point,
+                        targets: &[wgpu::ColorTargetState {
+                            format: crate::post::HDR_FORMAT,
+                            blend: Some(blend),
+                            write_mask: wgpu::ColorWrite::ALL,
+                        }],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode,
+                        polygon_mode,
+                        clamp_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: crate::texture::Texture::DEPTH_FORMAT,
+                        depth_write_enabled: depth_compare != wgpu::CompareFunction::Always,
+                        depth_compare,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    }
+                }
+            )
+        };
+
+        let replace = wgpu::BlendState::REPLACE;
+        let less = wgpu::CompareFunction::Less;
+        let back = Some(wgpu::Face::Back);
+        let wireframe = if line_mode {
+            create_pipeline("Wireframe Pipeline", "wireframe", wgpu::PolygonMode::Line, None, replace, less)
+        } else {
+            create_pipeline("Wireframe Pipeline", "wireframe_barycentric", wgpu::PolygonMode::Fill, None, replace, less)
+        };
+        let normals = create_pipeline("Normals Pipeline", "normals", wgpu::PolygonMode::Fill, back, replace, less);
+        let uv_checker = create_pipeline("UV Checker Pipeline", "uv_checker", wgpu::PolygonMode::Fill, back, replace, less);
+        let depth = create_pipeline("Depth View Pipeline", "depth", wgpu::PolygonMode::Fill, back, replace, less);
+        // every fragment counts, hidden or not
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+        let overdraw = create_pipeline(
+            "Overdraw Pipeline",
+            "overdraw",
+            wgpu::PolygonMode::Fill,
+            None,
+            additive,
+            wgpu::CompareFunction::Always,
+        );
+
+        let expanded_meshes = if line_mode {
+            Vec::new()
+        } else {
+            meshes.iter().map(|mesh| Self::expand(device, mesh)).collect()
+        };
+
+        Self {
+            wireframe,
+            line_mode,
+            normals,
+            uv_checker,
+            depth,
+            overdraw,
+
+            expanded_meshes,
+        }
+    }
+
+    /// Clear the attachments and set up the pipeline of view, None for DebugView::Lit
+    /// the caller has to bind the camera (0) and draw the scene
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        target: &'a wgpu::TextureView,
+        resolve_target: Option<&'a wgpu::TextureView>,
+        depth: &'a wgpu::TextureView,
+        view: DebugView,
+    ) -> Option<wgpu::RenderPass<'a>> {
+        let pipeline = match view {
+            DebugView::Lit => return None,
+            DebugView::Wireframe => &self.wireframe,
+            DebugView::Normals => &self.normals,
+            DebugView::UvChecker => &self.uv_checker,
+            DebugView::Depth => &self.depth,
+            DebugView::Overdraw => &self.overdraw,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug View Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(pipeline);
+        Some(render_pass)
+    }
+
+    /// True if view has to be drawn from expanded_mesh() instead of the indexed meshes
+    pub fn uses_expanded_meshes(&self, view: DebugView) -> bool {
+        view == DebugView::Wireframe && !self.line_mode
+    }
+
+    /// Vertex buffer without index buffer and its vertex count
+    pub fn expanded_mesh(&self, mesh: usize) -> Option<(&wgpu::Buffer, u32)> {
+        self.expanded_meshes.get(mesh).map(|(buffer, count)| (buffer, *count))
+    }
+
+    fn expand(device: &wgpu::Device, mesh: &crate::mesh::Mesh) -> (wgpu::Buffer, u32) {
+        let vertices: Vec<crate::vertex::Vertex> = mesh.indices
+            .iter()
+            .filter_map(|index| mesh.vertices.get(*index as usize).copied())
+            .collect();
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Expanded Vertex Buffer", mesh.name)),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsage::VERTEX,
+            }
+        );
+        (buffer, vertices.len() as u32)
+    }
+}
@@ -0,0 +1,103 @@
+// Vertex shader
+[[block]]
+struct Uniform {
+	view_projection: mat4x4<f32>;
+	view: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> uniform: Uniform;
+
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+	[[location(5)]] model_0: vec4<f32>;
+	[[location(6)]] model_1: vec4<f32>;
+	[[location(7)]] model_2: vec4<f32>;
+	[[location(8)]] model_3: vec4<f32>;
+	[[location(9)]] normal_0: vec3<f32>;
+	[[location(10)]] normal_1: vec3<f32>;
+	[[location(11)]] normal_2: vec3<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] normal: vec3<f32>;
+	[[location(2)]] view_distance: f32;
+	// only meaningful for meshes without index buffer, every 3 vertices are a triangle
+	[[location(3)]] barycentric: vec3<f32>;
+};
+
+[[stage(vertex)]]
+fn main(
+	model: VertexInput,
+	instance: InstanceInput,
+	[[builtin(vertex_index)]] vertex_index: u32
+) -> VertexOutput {
+	let model_matrix = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	let normal_matrix = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+	let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+	let corner = vertex_index % 3u;
+
+	var out: VertexOutput;
+	out.clip_coordinate = uniform.view_projection * world_position;
+	out.texture_coords = model.texture_coords;
+	out.normal = normal_matrix * model.normal;
+	// the camera looks down -z
+	out.view_distance = -(uniform.view * world_position).z;
+	out.barycentric = vec3<f32>(
+		select(0.0, 1.0, corner == 0u),
+		select(0.0, 1.0, corner == 1u),
+		select(0.0, 1.0, corner == 2u)
+	);
+	return out;
+}
+
+// Fragment shaders, one per debug view
+
+[[stage(fragment)]]
+fn wireframe(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(0.1, 1.0, 0.4, 1.0);
+}
+
+// without line rasterization, only keep the pixels close to an edge of the triangle
+[[stage(fragment)]]
+fn wireframe_barycentric(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let width = fwidth(in.barycentric) * 1.5;
+	let edge = smoothStep(vec3<f32>(0.0, 0.0, 0.0), width, in.barycentric);
+	let coverage = 1.0 - min(min(edge.x, edge.y), edge.z);
+	if (coverage < 0.01) {
+		discard;
+	}
+	return vec4<f32>(vec3<f32>(0.1, 1.0, 0.4) * coverage, 1.0);
+}
+
+[[stage(fragment)]]
+fn normals(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(normalize(in.normal) * 0.5 + 0.5, 1.0);
+}
+
+// 8x8 checker board, tinted by the coordinates
+[[stage(fragment)]]
+fn uv_checker(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let cells = floor(in.texture_coords * 8.0);
+	let brightness = select(0.25, 1.0, fract((cells.x + cells.y) * 0.5) < 0.25);
+	return vec4<f32>(vec3<f32>(in.texture_coords, 1.0) * brightness, 1.0);
+}
+
+// white close to the camera, fading to black with the distance
+[[stage(fragment)]]
+fn depth(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let brightness = exp(-in.view_distance * 0.15);
+	return vec4<f32>(brightness, brightness, brightness, 1.0);
+}
+
+// added up for every fragment, the tone mapping turns it into a heat map
+[[stage(fragment)]]
+fn overdraw(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(0.2, 0.08, 0.02, 1.0);
+}
@@ -0,0 +1,296 @@
+/// Surface attributes, which get written by the geometry pass
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+// specular, shininess, emissive, so values above 1 have to fit
+const MATERIAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Which way the scene gets lit, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderPath {
+    /// shade every fragment while drawing it, supports MSAA
+    Forward,
+    /// write a G-buffer and shade every pixel once, cheaper with many lights
+    Deferred,
+}
+
+impl RenderPath {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "forward" => Some(Self::Forward),
+            "deferred" => Some(Self::Deferred),
+            _ => None,
+        }
+    }
+}
+
+pub struct GBuffer {
+    pub albedo: crate::texture::Texture,
+    pub normal: crate::texture::Texture,
+    pub material: crate::texture::Texture,
+    pub depth: crate::texture::Texture,
+}
+
+impl GBuffer {
+    fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> Self {
+        let target = |format, label| crate::texture::Texture::create_render_target(
+            device, sc_desc.width, sc_desc.height, format, 1, label,
+        );
+
+        Self {
+            albedo: target(ALBEDO_FORMAT, "gbuffer_albedo"),
+            normal: target(NORMAL_FORMAT, "gbuffer_normal"),
+            material: target(MATERIAL_FORMAT, "gbuffer_material"),
+            // the G-buffer is never multisampled
+            depth: crate::texture::Texture::create_depth_texture(device, sc_desc, 1, "gbuffer_depth"),
+        }
+    }
+}
+
+/// G-buffer pass followed by a fullscreen lighting pass into the scene target
+/// uses the same material, camera, ambient occlusion and light bind groups as the forward path
+pub struct DeferredRenderer {
+    gbuffer: GBuffer,
+    geometry_pipeline: wgpu::RenderPipeline,
+
+    gbuffer_bind_group_layout: wgpu::BindGroupLayout,
+    gbuffer_bind_group: wgpu::BindGroup,
+    lighting_pipeline: wgpu::RenderPipeline,
+}
+
+impl DeferredRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        material_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        ao_layout: &wgpu::BindGroupLayout,
+        light_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let gbuffer = GBuffer::new(device, sc_desc);
+        let geometry_pipeline = Self::create_geometry_pipeline(device, material_layout, camera_layout);
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+        let gbuffer_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("gbuffer_bind_group_layout"),
+        });
+        let gbuffer_bind_group = Self::create_gbuffer_bind_group(device, &gbuffer_bind_group_layout, &gbuffer);
+
+        // lighting is shared with the forward shader
+        let lighting_source = format!(
+            "{}\n{}",
+            include_str!("lights.wgsl"),
+            include_str!("deferred_lighting.wgsl"),
+        );
+        let lighting_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "Deferred Lighting",
+            &lighting_source,
+            &[&gbuffer_bind_group_layout, camera_layout, ao_layout, light_layout],
+            crate::post::HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+
+        Self {
+            gbuffer,
+            geometry_pipeline,
+
+            gbuffer_bind_group_layout,
+            gbuffer_bind_group,
+            lighting_pipeline,
+        }
+    }
+
+    /// Depth of the geometry pass, e.g. to draw more things into the scene target afterwards
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        &self.gbuffer.depth.view
+    }
+
+    /// The G-buffer always has to match the size of the swap_chain
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.gbuffer = GBuffer::new(device, sc_desc);
+        self.gbuffer_bind_group = Self::create_gbuffer_bind_group(device, &self.gbuffer_bind_group_layout, &self.gbuffer);
+    }
+
+    /// Start the geometry pass, the caller has to bind material (0), camera (1) and draw
+    pub fn begin_geometry_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        let clear = wgpu::Operations {
+            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            store: true,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("G-Buffer Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: &self.gbuffer.albedo.view,
+                    resolve_target: None,
+                    ops: clear,
+                },
+                wgpu::RenderPassColorAttachment {
+                    view: &self.gbuffer.normal.view,
+                    resolve_target: None,
+                    ops: clear,
+                },
+                wgpu::RenderPassColorAttachment {
+                    view: &self.gbuffer.material.view,
+                    resolve_target: None,
+                    ops: clear,
+                },
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.gbuffer.depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_pipeline(&self.geometry_pipeline);
+        render_pass
+    }
+
+    /// Shade the G-buffer into target, pixels without geometry get the clear color
+    pub fn render_lighting(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        clear_color: wgpu::Color,
+        camera_bind_group: &wgpu::BindGroup,
+        ao_bind_group: &wgpu::BindGroup,
+        light_bind_group: &wgpu::BindGroup,
+    ) {
+        crate::post::draw_fullscreen(
+            encoder,
+            "Deferred Lighting",
+            &self.lighting_pipeline,
+            &[&self.gbuffer_bind_group, camera_bind_group, ao_bind_group, light_bind_group],
+            target,
+            wgpu::LoadOp::Clear(clear_color),
+        );
+    }
+
+    fn create_gbuffer_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        gbuffer: &GBuffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&gbuffer.albedo.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&gbuffer.normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&gbuffer.material.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&gbuffer.depth.view),
+                },
+            ],
+            label: Some("gbuffer_bind_group"),
+        })
+    }
+
+    fn create_geometry_pipeline(
+        device: &wgpu::Device,
+        material_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("G-Buffer Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("gbuffer.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("G-Buffer Pipeline Layout"),
+                bind_group_layouts: &[material_layout, camera_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let target = |format| wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrite::ALL,
+        };
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("G-Buffer Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[crate::vertex::Vertex::desc(), crate::instance::InstanceRaw::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[
+                        target(ALBEDO_FORMAT),
+                        target(NORMAL_FORMAT),
+                        target(MATERIAL_FORMAT),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        )
+    }
+}
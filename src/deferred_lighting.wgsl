@@ -0,0 +1,60 @@
+// Lighting pass of the deferred path, shades every pixel of the G-buffer once
+[[block]]
+struct Uniform {
+view_projection: mat4x4<f32>;
+view: mat4x4<f32>;
+inverse_view_projection: mat4x4<f32>;
+eye: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var t_albedo: texture_2d<f32>;
+[[group(0), binding(1)]]
+var t_normal: texture_2d<f32>;
+[[group(0), binding(2)]]
+var t_material: texture_2d<f32>;
+[[group(0), binding(3)]]
+var t_depth: texture_depth_2d;
+
+[[group(1), binding(0)]]
+var<uniform> uniform: Uniform;
+
+[[group(2), binding(0)]]
+var t_ao: texture_2d<f32>;
+[[group(2), binding(1)]]
+var s_ao: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let coords = vec2<i32>(i32(in.clip_coordinate.x), i32(in.clip_coordinate.y));
+	let depth = textureLoad(t_depth, coords, 0);
+
+	// sampled before the discard, after it the control flow isn't uniform anymore
+	let uv = in.texture_coords;
+	let ao = textureSample(t_ao, s_ao, uv).r;
+
+	// keep the clear color, where nothing was drawn
+	if (depth >= 1.0) {
+		discard;
+	}
+
+	// world position from depth, y points up in NDC, but down in texture coordinates
+	let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+	let world = uniform.inverse_view_projection * ndc;
+	let position = world.xyz / world.w;
+
+	let albedo = textureLoad(t_albedo, coords, 0);
+	let normal = normalize(textureLoad(t_normal, coords, 0).xyz);
+	let material = textureLoad(t_material, coords, 0);
+
+	let lit = shade(
+		albedo.rgb,
+		position,
+		normal,
+		uniform.eye.xyz,
+		material.x,
+		material.y,
+		ao
+	);
+	return vec4<f32>(lit + albedo.rgb * material.z, albedo.a);
+}
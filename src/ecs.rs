@@ -0,0 +1,529 @@
+// Game logic lives in a hecs World, the renderer only ever sees the scene graph.
+// Entities with a SceneNode mirror one node each, the hierarchy itself stays in the scene.
+// Fixed update: store previous -> camera (and other game logic)
+// Every frame: input (per event) -> transform propagation -> render extraction,
+// the last two blend between the previous and current fixed update
+
+pub use crate::scene::Transform;
+pub use crate::scene::LightAttachment as Light;
+pub use crate::camera::Camera;
+pub use crate::camera_controller::CameraController;
+
+/// Links an entity to its node in the scene graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneNode(pub crate::scene::NodeId);
+
+/// World transform, written by transform_propagation_system
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub cgmath::Matrix4<f32>);
+
+/// Draw a mesh at the entity's transform, indices into the renderer's meshes and materials
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshRenderer {
+    pub mesh: usize,
+    pub material: usize,
+    pub visible: bool,
+}
+
+/// State at the end of the previous fixed update, for interpolation
+/// entities without it are rendered at their current state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Previous<T>(pub T);
+
+/// Marks the camera to render with, only one entity should have it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveCamera;
+
+/// Name of the entity, same as the name of its scene node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name(pub String);
+
+/// Create an entity for every node of the scene, with components for its attachments
+/// cameras need the aspect ratio of the window, the first one becomes active if none is yet
+pub fn spawn_scene(
+    world: &mut hecs::World,
+    scene: &crate::scene::Scene,
+    aspect: f32,
+) -> Vec<hecs::Entity> {
+    let mut entities = Vec::new();
+    let mut has_active_camera = active_camera(world).is_some();
+    scene.traverse(|id, node, _| {
+        let mut builder = hecs::EntityBuilder::new();
+        builder
+            .add(Name(node.name.clone()))
+            .add(SceneNode(id))
+            .add(*node.local_transform())
+            .add(Previous(*node.local_transform()))
+            .add(GlobalTransform(*node.world_transform()));
+        if let Some(mesh) = node.mesh {
+            builder.add(MeshRenderer {
+                mesh: mesh.mesh,
+                material: mesh.material,
+                visible: true,
+            });
+        }
+        if let Some(light) = node.light {
+            builder.add(light);
+        }
+        if let Some(camera) = node.camera {
+            let camera = Camera::from_attachment(&camera, node.world_transform(), aspect);
+            builder.add(camera).add(Previous(camera));
+            if !has_active_camera {
+                builder.add(ActiveCamera);
+                has_active_camera = true;
+            }
+        }
+        entities.push(world.spawn(builder.build()));
+    });
+    entities
+}
+
+/// The camera to render with, if there is one
+pub fn active_camera(world: &hecs::World) -> Option<hecs::Entity> {
+    world
+        .query::<&Camera>()
+        .with::<ActiveCamera>()
+        .iter()
+        .next()
+        .map(|(entity, _)| entity)
+}
+
+/// Render with the camera of entity from now on, false if it has no camera
+#[allow(dead_code)]
+pub fn set_active_camera(world: &mut hecs::World, entity: hecs::Entity) -> bool {
+    if world.get::<Camera>(entity).is_err() {
+        return false;
+    }
+    let previous: Vec<hecs::Entity> = world.query::<()>().with::<ActiveCamera>().iter().map(|(other, _)| other).collect();
+    for other in previous {
+        let _ = world.remove_one::<ActiveCamera>(other);
+    }
+    world.insert_one(entity, ActiveCamera).is_ok()
+}
+
+/// Spawn an entity with a new scene node below the node of parent, or as a root
+/// further components like MeshRenderer or Light can be added with World::insert
+pub fn spawn(
+    world: &mut hecs::World,
+    scene: &mut crate::scene::Scene,
+    name: &str,
+    transform: Transform,
+    parent: Option<hecs::Entity>,
+) -> hecs::Entity {
+    let parent_node = parent.and_then(|parent| world.get::<SceneNode>(parent).ok().map(|node| node.0));
+    let id = match parent_node.and_then(|parent| scene.add_child(parent, name, transform).ok()) {
+        Some(id) => id,
+        None => scene.add_node(name, transform),
+    };
+
+    world.spawn((
+        Name(name.to_string()),
+        SceneNode(id),
+        transform,
+        Previous(transform),
+        GlobalTransform(cgmath::SquareMatrix::identity()),
+    ))
+}
+
+/// Remove an entity together with its scene node, children of the node are removed as well
+pub fn despawn(world: &mut hecs::World, scene: &mut crate::scene::Scene, entity: hecs::Entity) {
+    if let Ok(node) = world.get::<SceneNode>(entity).map(|node| *node) {
+        // entities of the removed children would point at nothing
+        let mut removed = vec![node.0];
+        scene.traverse(|id, _, _| {
+            if scene.ancestors(id).any(|ancestor| ancestor == node.0) {
+                removed.push(id);
+            }
+        });
+        let orphans: Vec<hecs::Entity> = world
+            .query::<&SceneNode>()
+            .iter()
+            .filter(|(other, node)| *other != entity && removed.contains(&node.0))
+            .map(|(other, _)| other)
+            .collect();
+        for orphan in orphans {
+            let _ = world.despawn(orphan);
+        }
+        let _ = scene.remove(node.0);
+    }
+    let _ = world.despawn(entity);
+}
+
+/// Entity mirroring a scene node, None if there is none
+pub fn entity_of(world: &hecs::World, node: crate::scene::NodeId) -> Option<hecs::Entity> {
+    world
+        .query::<&SceneNode>()
+        .iter()
+        .find(|(_, scene_node)| scene_node.0 == node)
+        .map(|(entity, _)| entity)
+}
+
+/// Move an entity right away, without blending from where it was
+pub fn set_transform(world: &mut hecs::World, entity: hecs::Entity, transform: Transform) {
+    if let Ok(mut current) = world.get_mut::<Transform>(entity) {
+        *current = transform;
+    }
+    if let Ok(mut previous) = world.get_mut::<Previous<Transform>>(entity) {
+        previous.0 = transform;
+    }
+}
+
+/// Components of one entity in an EntityTree
+#[derive(Debug, Clone, PartialEq)]
+struct EntityCopy {
+    entity: hecs::Entity,
+    name: Name,
+    node: SceneNode,
+    transform: Transform,
+    mesh: Option<MeshRenderer>,
+    light: Option<Light>,
+}
+
+/// Copy of an entity and the entities of all nodes below it, together with the nodes
+/// restore_tree brings them back with the same handles after they were despawned
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityTree {
+    nodes: crate::scene::Subtree,
+    entities: Vec<EntityCopy>,
+}
+
+impl EntityTree {
+    /// The entity the tree was copied from
+    pub fn root(&self) -> hecs::Entity {
+        self.entities[0].entity
+    }
+
+    pub fn name(&self) -> &str {
+        &self.entities[0].name.0
+    }
+}
+
+/// Copy entity and everything below it, None if it has no scene node
+/// or there's a camera in the tree, which is flown by a controller that can't be copied
+pub fn copy_tree(world: &hecs::World, scene: &crate::scene::Scene, entity: hecs::Entity) -> Option<EntityTree> {
+    let root = world.get::<SceneNode>(entity).ok()?.0;
+    let nodes = scene.subtree(root)?;
+    let mut entities = Vec::new();
+    for id in nodes.ids() {
+        let entity = match entity_of(world, id) {
+            Some(entity) => entity,
+            None => continue,
+        };
+        if world.get::<Camera>(entity).is_ok() {
+            return None;
+        }
+        let mut query = world.query_one::<(&Name, &Transform, Option<&MeshRenderer>, Option<&Light>)>(entity).ok()?;
+        let (name, transform, mesh, light) = query.get()?;
+        entities.push(EntityCopy {
+            entity,
+            name: name.clone(),
+            node: SceneNode(id),
+            transform: *transform,
+            mesh: mesh.copied(),
+            light: light.copied(),
+        });
+    }
+    // the root entity comes first
+    if entities.first().map(|copy| copy.entity) != Some(entity) {
+        return None;
+    }
+    Some(EntityTree { nodes, entities })
+}
+
+/// Spawn the entities of a tree again, after despawn, with their old handles and scene nodes
+/// false if the nodes can't be put back, e.g. because the parent is gone
+pub fn restore_tree(world: &mut hecs::World, scene: &mut crate::scene::Scene, tree: &EntityTree) -> bool {
+    if scene.restore(&tree.nodes).is_err() {
+        return false;
+    }
+    scene.update_world_transforms();
+
+    for copy in &tree.entities {
+        let global = scene
+            .node(copy.node.0)
+            .map_or_else(cgmath::SquareMatrix::identity, |node| *node.world_transform());
+        let mut builder = hecs::EntityBuilder::new();
+        builder
+            .add(copy.name.clone())
+            .add(copy.node)
+            .add(copy.transform)
+            .add(Previous(copy.transform))
+            .add(GlobalTransform(global));
+        if let Some(mesh) = copy.mesh {
+            builder.add(mesh);
+        }
+        if let Some(light) = copy.light {
+            builder.add(light);
+        }
+        world.spawn_at(copy.entity, builder.build());
+    }
+    true
+}
+
+/// Forward a window event to every camera controller
+pub fn input_system(world: &mut hecs::World, event: &winit::event::WindowEvent) {
+    for (_, controller) in world.query_mut::<&mut CameraController>() {
+        controller.input(event);
+    }
+}
+
+/// Remember the current state, has to run before everything else in a fixed update
+pub fn store_previous_system(world: &mut hecs::World) {
+    for (_, (transform, previous)) in world.query_mut::<(&Transform, &mut Previous<Transform>)>() {
+        previous.0 = *transform;
+    }
+    for (_, (camera, previous)) in world.query_mut::<(&Camera, &mut Previous<Camera>)>() {
+        previous.0 = *camera;
+    }
+}
+
+/// Move every controlled camera
+pub fn camera_system(world: &mut hecs::World) {
+    for (_, (camera, controller)) in world.query_mut::<(&mut Camera, &mut CameraController)>() {
+        controller.update_camera(camera);
+    }
+}
+
+/// Push changed transforms, blended by alpha, into the scene, let it propagate them
+/// down the hierarchy and copy the resulting world transforms back
+pub fn transform_propagation_system(world: &mut hecs::World, scene: &mut crate::scene::Scene, alpha: f32) {
+    for (_, (transform, previous, node)) in world.query_mut::<(&Transform, Option<&Previous<Transform>>, &SceneNode)>() {
+        let transform = match previous {
+            Some(previous) if previous.0 != *transform => previous.0.interpolate(transform, alpha),
+            _ => *transform,
+        };
+        // only touch nodes that actually changed, so the dirty flags stay meaningful
+        if scene.node(node.0).map(|node| *node.local_transform()) != Some(transform) {
+            let _ = scene.set_local_transform(node.0, transform);
+        }
+    }
+
+    scene.update_world_transforms();
+
+    for (_, (global, node)) in world.query_mut::<(&mut GlobalTransform, &SceneNode)>() {
+        if let Some(node) = scene.node(node.0) {
+            global.0 = *node.world_transform();
+        }
+    }
+}
+
+/// Move the nodes of the cameras to where the cameras are now, e.g. before the scene is saved
+/// the cameras themselves are moved by their controllers, not by their transforms
+pub fn camera_write_back_system(world: &mut hecs::World, scene: &mut crate::scene::Scene) {
+    use cgmath::SquareMatrix;
+    for (_, (camera, transform, node)) in world.query_mut::<(&Camera, &mut Transform, &SceneNode)>() {
+        let parent = scene
+            .node(node.0)
+            .and_then(|node| node.parent())
+            .and_then(|parent| scene.node(parent))
+            .map(|parent| *parent.world_transform())
+            .unwrap_or_else(cgmath::Matrix4::identity);
+        let local = parent.invert().unwrap_or_else(cgmath::Matrix4::identity) * camera.world_transform();
+        *transform = Transform::from_matrix(&local);
+
+        let _ = scene.set_local_transform(node.0, *transform);
+        if let Some(attachment) = scene.node_mut(node.0).and_then(|node| node.camera.as_mut()) {
+            attachment.fovy = camera.fovy;
+            attachment.znear = camera.znear;
+            attachment.zfar = camera.zfar;
+        }
+    }
+
+    scene.update_world_transforms();
+}
+
+/// Copy what the renderer needs into the scene attachments
+/// returns the active camera to render with, blended by alpha
+pub fn render_extraction_system(world: &hecs::World, scene: &mut crate::scene::Scene, alpha: f32) -> Option<Camera> {
+    for (_, (node, mesh, light)) in world
+        .query::<(&SceneNode, Option<&MeshRenderer>, Option<&Light>)>()
+        .iter()
+    {
+        if let Some(node) = scene.node_mut(node.0) {
+            node.mesh = mesh
+                .filter(|mesh| mesh.visible)
+                .map(|mesh| crate::scene::MeshAttachment {
+                    mesh: mesh.mesh,
+                    material: mesh.material,
+                });
+            node.light = light.copied();
+        }
+    }
+
+    world
+        .query::<(&Camera, Option<&Previous<Camera>>)>()
+        .with::<ActiveCamera>()
+        .iter()
+        .next()
+        .map(|(_, (camera, previous))| match previous {
+            Some(previous) => previous.0.interpolate(camera, alpha),
+            None => *camera,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{CameraAttachment, MeshAttachment, Scene};
+    use cgmath::{InnerSpace, Matrix4, Vector3};
+
+    struct Fixture {
+        world: hecs::World,
+        scene: Scene,
+        root: hecs::Entity,
+        child: hecs::Entity,
+    }
+
+    /// A root with a mesh, a light below it and a camera next to it
+    fn fixture() -> Fixture {
+        let mut scene = Scene::new();
+        let root = scene.add_node("root", Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)));
+        let child = scene.add_child(root, "light", Transform::from_translation(Vector3::new(0.0, 2.0, 0.0))).unwrap();
+        let camera = scene.add_node("camera", Transform::from_translation(Vector3::new(0.0, 0.0, 5.0)));
+        scene.node_mut(root).unwrap().mesh = Some(MeshAttachment { mesh: 0, material: 1 });
+        scene.node_mut(child).unwrap().light = Some(Light { color: [1.0, 1.0, 1.0], intensity: 1.0, radius: 4.0 });
+        scene.node_mut(camera).unwrap().camera = Some(CameraAttachment { fovy: 45.0, znear: 0.1, zfar: 100.0 });
+        scene.update_world_transforms();
+
+        let mut world = hecs::World::new();
+        let entities = spawn_scene(&mut world, &scene, 1.0);
+        let find = |world: &hecs::World, name: &str| {
+            entities.iter().copied().find(|entity| world.get::<Name>(*entity).unwrap().0 == name).unwrap()
+        };
+        let (root, child) = (find(&world, "root"), find(&world, "light"));
+        Fixture { world, scene, root, child }
+    }
+
+    fn translation(world: &hecs::World, entity: hecs::Entity) -> Vector3<f32> {
+        world.get::<GlobalTransform>(entity).unwrap().0.w.truncate()
+    }
+
+    #[test]
+    fn spawn_scene_adds_components_for_attachments() {
+        let Fixture { world, scene, root, child } = fixture();
+        assert_eq!(world.len() as usize, scene.len());
+        assert_eq!(
+            *world.get::<MeshRenderer>(root).unwrap(),
+            MeshRenderer { mesh: 0, material: 1, visible: true },
+        );
+        assert!(world.get::<Light>(child).is_ok());
+        assert!(world.get::<Light>(root).is_err());
+        assert_eq!(world.query::<&Camera>().iter().count(), 1);
+        assert_eq!(translation(&world, child), Vector3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn propagation_moves_the_children_along() {
+        let Fixture { mut world, mut scene, root, child } = fixture();
+        *world.get_mut::<Transform>(root).unwrap() = Transform::from_translation(Vector3::new(-3.0, 0.0, 0.0));
+        transform_propagation_system(&mut world, &mut scene, 1.0);
+
+        assert_eq!(translation(&world, root), Vector3::new(-3.0, 0.0, 0.0));
+        assert_eq!(translation(&world, child), Vector3::new(-3.0, 2.0, 0.0));
+        let node = world.get::<SceneNode>(child).unwrap().0;
+        assert_eq!(*scene.node(node).unwrap().world_transform(), Matrix4::from_translation(Vector3::new(-3.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn propagation_blends_with_the_previous_state() {
+        let Fixture { mut world, mut scene, root, child } = fixture();
+        store_previous_system(&mut world);
+        *world.get_mut::<Transform>(root).unwrap() = Transform::from_translation(Vector3::new(-3.0, 0.0, 0.0));
+        transform_propagation_system(&mut world, &mut scene, 0.5);
+        assert_eq!(translation(&world, child), Vector3::new(-1.0, 2.0, 0.0));
+
+        store_previous_system(&mut world);
+        transform_propagation_system(&mut world, &mut scene, 0.5);
+        assert_eq!(translation(&world, child), Vector3::new(-3.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn extraction_copies_attachments_and_the_camera() {
+        let Fixture { world, mut scene, root, child } = fixture();
+        world.get_mut::<MeshRenderer>(root).unwrap().visible = false;
+        world.get_mut::<Light>(child).unwrap().intensity = 3.0;
+        let camera = render_extraction_system(&world, &mut scene, 1.0);
+
+        let node = |world: &hecs::World, entity| world.get::<SceneNode>(entity).unwrap().0;
+        assert_eq!(scene.node(node(&world, root)).unwrap().mesh, None);
+        assert_eq!(scene.node(node(&world, child)).unwrap().light.unwrap().intensity, 3.0);
+        assert_eq!(camera.unwrap().eye, cgmath::Point3::new(0.0, 0.0, 5.0));
+    }
+
+    #[test]
+    fn extraction_uses_the_active_camera() {
+        let Fixture { mut world, mut scene, root, .. } = fixture();
+        let first = active_camera(&world).unwrap();
+        let camera = Camera { fovy: 90.0, ..*world.get::<Camera>(first).unwrap() };
+        let second = world.spawn((camera,));
+        assert_eq!(render_extraction_system(&world, &mut scene, 1.0).unwrap().fovy, 45.0);
+
+        assert!(!set_active_camera(&mut world, root));
+        assert!(set_active_camera(&mut world, second));
+        assert_eq!(active_camera(&world), Some(second));
+        assert_eq!(world.query::<&ActiveCamera>().iter().count(), 1);
+        assert_eq!(render_extraction_system(&world, &mut scene, 1.0).unwrap().fovy, 90.0);
+    }
+
+    #[test]
+    fn cameras_are_written_back_to_their_nodes() {
+        let Fixture { mut world, mut scene, root, .. } = fixture();
+        let node = scene.find("camera").unwrap();
+        let parent = world.get::<SceneNode>(root).unwrap().0;
+        scene.set_parent(node, Some(parent)).unwrap();
+        scene.update_world_transforms();
+        for (_, camera) in world.query_mut::<&mut Camera>() {
+            camera.eye = cgmath::Point3::new(4.0, 1.0, 0.0);
+            camera.target = cgmath::Point3::new(4.0, 1.0, -1.0);
+            camera.fovy = 60.0;
+        }
+        camera_write_back_system(&mut world, &mut scene);
+
+        // the root moved it by 1 along x
+        let camera = scene.node(node).unwrap();
+        let translation = camera.local_transform().translation;
+        assert!((translation - Vector3::new(3.0, 1.0, 0.0)).magnitude() < 1e-5);
+        assert_eq!(camera.camera.unwrap().fovy, 60.0);
+
+        let attachment = camera.camera.unwrap();
+        let moved = Camera::from_attachment(&attachment, camera.world_transform(), 1.0);
+        assert!((moved.eye - cgmath::Point3::new(4.0, 1.0, 0.0)).magnitude() < 1e-5);
+        assert!((moved.target - cgmath::Point3::new(4.0, 1.0, -1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn restored_trees_look_like_before() {
+        let Fixture { mut world, mut scene, root, child } = fixture();
+        let tree = copy_tree(&world, &scene, root).unwrap();
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.name(), "root");
+
+        despawn(&mut world, &mut scene, root);
+        assert!(restore_tree(&mut world, &mut scene, &tree));
+        assert_eq!(*world.get::<MeshRenderer>(root).unwrap(), MeshRenderer { mesh: 0, material: 1, visible: true });
+        assert!(world.get::<Light>(child).is_ok());
+        assert_eq!(translation(&world, child), Vector3::new(1.0, 2.0, 0.0));
+        assert_eq!(copy_tree(&world, &scene, root), Some(tree.clone()));
+
+        // the nodes are there already
+        assert!(!restore_tree(&mut world, &mut scene, &tree));
+    }
+
+    #[test]
+    fn cameras_are_not_copied() {
+        let Fixture { world, scene, .. } = fixture();
+        assert_eq!(copy_tree(&world, &scene, active_camera(&world).unwrap()), None);
+    }
+
+    #[test]
+    fn despawn_takes_the_children_along() {
+        let Fixture { mut world, mut scene, root, child } = fixture();
+        let extra = spawn(&mut world, &mut scene, "extra", Transform::identity(), Some(child));
+        assert_eq!(scene.len(), 4);
+
+        despawn(&mut world, &mut scene, root);
+        assert!(!world.contains(root) && !world.contains(child) && !world.contains(extra));
+        assert_eq!(world.len(), 1);
+        assert_eq!(scene.len(), 1);
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+/// Frames in a row, which may time out or be outdated before the swap chain is recreated anyway
+const MAX_RETRIES: u32 = 8;
+/// Wait before the first retry, doubled with every further one
+const INITIAL_BACKOFF: Duration = Duration::from_millis(1);
+const MAX_BACKOFF: Duration = Duration::from_millis(250);
+/// Swap chains in a row, which may be lost right after being recreated, before the device is assumed lost
+const MAX_LOST: u32 = 3;
+
+/// Why the renderer could not start, or could not recover
+#[derive(Debug)]
+pub enum RendererError {
+    /// no adapter supports both the window and the features we need
+    NoAdapter,
+    /// software rendering was asked for, but no adapter runs on the cpu
+    NoSoftwareAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// the adapter can't present to the window
+    IncompatibleSurface,
+    /// not a sample count the renderer knows how to handle
+    InvalidSampleCount(u32),
+    /// the adapter can't render to the multisampled formats
+    UnsupportedSampleCount { count: u32, adapter: String },
+    /// a texture every scene needs could not be loaded
+    Texture { name: String, error: anyhow::Error },
+}
+
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAdapter => write!(
+                f,
+                "no compatible graphics adapter found, a GPU (or software renderer) with Vulkan, Metal or DX12 is needed",
+            ),
+            Self::NoSoftwareAdapter => write!(
+                f,
+                "no software graphics adapter found, a CPU renderer like lavapipe or WARP is needed",
+            ),
+            Self::RequestDevice(error) => write!(f, "could not open the graphics device: {}", error),
+            Self::IncompatibleSurface => write!(f, "the graphics adapter can't draw to the window"),
+            Self::InvalidSampleCount(count) => write!(
+                f,
+                "{} samples per pixel are not supported, expected one of {:?}",
+                count, crate::settings::SUPPORTED_SAMPLE_COUNTS,
+            ),
+            Self::UnsupportedSampleCount { count, adapter } => write!(
+                f,
+                "{} can't render with {} samples per pixel, SAMPLE_COUNT=1 disables multisampling",
+                adapter, count,
+            ),
+            Self::Texture { name, error } => write!(f, "could not load texture {:?}: {}", name, error),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+impl From<wgpu::RequestDeviceError> for RendererError {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(error)
+    }
+}
+
+/// What to do about a frame, which could not be rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// try the next frame again, but not before the delay
+    Retry(Duration),
+    /// create the swap chain again, then continue after the delay
+    RecreateSwapChain(Duration),
+    /// the device is gone, every gpu resource has to be created again
+    RebuildDevice,
+    /// nothing left to try
+    Exit,
+}
+
+/// Decides how to recover from swap chain errors, based on how many failed in a row
+#[derive(Debug, Clone, Default)]
+pub struct FrameErrors {
+    // failed frames in a row
+    failed: u32,
+    // Lost errors in a row, the swap chain was recreated after each of them
+    lost: u32,
+}
+
+impl FrameErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A frame was rendered, everything is fine again
+    pub fn succeeded(&mut self) {
+        if self.failed > 0 {
+            log::info!("Rendering again after {} failed frames", self.failed);
+        }
+        self.failed = 0;
+        self.lost = 0;
+    }
+
+    /// A frame failed, returns what to do about it
+    pub fn failed(&mut self, error: &wgpu::SwapChainError) -> Recovery {
+        self.failed += 1;
+        let backoff = self.backoff();
+        match error {
+            // the gpu is busy, e.g. the window is being moved or is hidden
+            wgpu::SwapChainError::Timeout if self.failed < MAX_RETRIES => {
+                log::debug!("Swap chain timed out, retrying in {:?}", backoff);
+                Recovery::Retry(backoff)
+            },
+            wgpu::SwapChainError::Timeout => {
+                log::warn!("Swap chain timed out {} times in a row, recreating it", self.failed);
+                Recovery::RecreateSwapChain(backoff)
+            },
+            // the surface changed, e.g. during a resize, the next resize event might not be here yet
+            wgpu::SwapChainError::Outdated => {
+                log::debug!("Swap chain is outdated, recreating it in {:?}", backoff);
+                Recovery::RecreateSwapChain(backoff)
+            },
+            wgpu::SwapChainError::Lost => {
+                self.lost += 1;
+                if self.lost > MAX_LOST {
+                    log::error!("Swap chain was lost {} times in a row, rebuilding the device", self.lost);
+                    self.lost = 0;
+                    Recovery::RebuildDevice
+                } else {
+                    log::warn!("Swap chain was lost, recreating it");
+                    Recovery::RecreateSwapChain(Duration::from_secs(0))
+                }
+            },
+            wgpu::SwapChainError::OutOfMemory => {
+                log::error!("Out of graphics memory");
+                Recovery::Exit
+            },
+        }
+    }
+
+    fn backoff(&self) -> Duration {
+        // 1, 2, 4, ... ms, the shift can't overflow with this cap
+        let doublings = self.failed.saturating_sub(1).min(16);
+        (INITIAL_BACKOFF * (1 << doublings)).min(MAX_BACKOFF)
+    }
+}
+
+/// Whether an error reported to the device's error handler comes from losing the device
+/// wgpu 0.9 has no error type of its own for it, only the message of the innermost cause
+pub fn is_device_lost(error: &wgpu::Error) -> bool {
+    let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(current) = cause {
+        if current.to_string().contains("device is lost") {
+            return true;
+        }
+        cause = current.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn timeouts_back_off_then_recreate() {
+        let mut errors = FrameErrors::new();
+        let retries: Vec<_> = (1..MAX_RETRIES)
+            .map(|_| errors.failed(&wgpu::SwapChainError::Timeout))
+            .collect();
+        let expected: Vec<_> = [1, 2, 4, 8, 16, 32, 64].iter().map(|&millis| Recovery::Retry(ms(millis))).collect();
+        assert_eq!(retries, expected);
+        assert_eq!(errors.failed(&wgpu::SwapChainError::Timeout), Recovery::RecreateSwapChain(ms(128)));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let mut errors = FrameErrors::new();
+        for _ in 0..40 {
+            errors.failed(&wgpu::SwapChainError::Outdated);
+        }
+        assert_eq!(errors.failed(&wgpu::SwapChainError::Outdated), Recovery::RecreateSwapChain(MAX_BACKOFF));
+    }
+
+    #[test]
+    fn success_starts_over() {
+        let mut errors = FrameErrors::new();
+        for _ in 0..MAX_RETRIES {
+            errors.failed(&wgpu::SwapChainError::Timeout);
+        }
+        errors.failed(&wgpu::SwapChainError::Lost);
+        errors.succeeded();
+        assert_eq!(errors.failed(&wgpu::SwapChainError::Timeout), Recovery::Retry(INITIAL_BACKOFF));
+        // the earlier lost swap chain doesn't count anymore
+        for _ in 0..MAX_LOST {
+            assert_eq!(errors.failed(&wgpu::SwapChainError::Lost), Recovery::RecreateSwapChain(ms(0)));
+        }
+    }
+
+    #[test]
+    fn lost_too_often_rebuilds_the_device() {
+        let mut errors = FrameErrors::new();
+        for _ in 0..MAX_LOST {
+            assert_eq!(errors.failed(&wgpu::SwapChainError::Lost), Recovery::RecreateSwapChain(ms(0)));
+        }
+        assert_eq!(errors.failed(&wgpu::SwapChainError::Lost), Recovery::RebuildDevice);
+        // the new device gets the same chances
+        assert_eq!(errors.failed(&wgpu::SwapChainError::Lost), Recovery::RecreateSwapChain(ms(0)));
+    }
+
+    #[test]
+    fn lost_device_is_found_in_the_causes() {
+        #[derive(Debug)]
+        struct Cause(&'static str);
+        impl std::fmt::Display for Cause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+        impl std::error::Error for Cause {}
+
+        let validation = |message| wgpu::Error::ValidationError {
+            source: Box::new(Cause(message)),
+            description: "Validation Error".to_string(),
+        };
+        assert!(is_device_lost(&validation("parent device is lost")));
+        assert!(!is_device_lost(&validation("buffer is too small")));
+    }
+
+    #[test]
+    fn out_of_memory_exits() {
+        let mut errors = FrameErrors::new();
+        assert_eq!(errors.failed(&wgpu::SwapChainError::OutOfMemory), Recovery::Exit);
+    }
+}
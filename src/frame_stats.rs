@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of frames the rolling statistics are computed over
+pub const DEFAULT_HISTORY: usize = 120;
+
+/// Rolling CPU frame times and the CPU time of every pass of the last frame
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    history: usize,
+    // passes of the frame currently being recorded
+    current_passes: Vec<(&'static str, Duration)>,
+    // passes of the last complete frame
+    passes: Vec<(&'static str, Duration)>,
+}
+
+impl FrameStats {
+    pub fn new(history: usize) -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(history),
+            history: history.max(1),
+            current_passes: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Start a new frame, frame_time is the time since the previous one
+    pub fn record_frame(&mut self, frame_time: Duration) {
+        // the very first frame has nothing to measure against
+        if frame_time > Duration::from_secs(0) {
+            if self.frame_times.len() == self.history {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(frame_time);
+        }
+        self.passes = std::mem::take(&mut self.current_passes);
+    }
+
+    /// CPU time spent in a pass of the current frame, passes with the same name add up
+    pub fn record_pass(&mut self, name: &'static str, duration: Duration) {
+        match self.current_passes.iter_mut().find(|(pass, _)| *pass == name) {
+            Some((_, total)) => *total += duration,
+            None => self.current_passes.push((name, duration)),
+        }
+    }
+
+    /// Passes of the last complete frame, in recording order
+    #[allow(dead_code)]
+    pub fn pass_times(&self) -> &[(&'static str, Duration)] {
+        &self.passes
+    }
+
+    #[allow(dead_code)]
+    pub fn frame_time(&self) -> Duration {
+        self.frame_times.back().copied().unwrap_or_default()
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::from_secs(0);
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    /// Frame time, which percentile of all frames are faster than, e.g. 0.99 for the slowest 1%
+    pub fn percentile_frame_time(&self, percentile: f32) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::from_secs(0);
+        }
+        let mut sorted: Vec<Duration> = self.frame_times.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() - 1) as f32 * percentile.clamp(0.0, 1.0)).round() as usize;
+        sorted[index]
+    }
+
+    pub fn average_fps(&self) -> f32 {
+        fps(self.average_frame_time())
+    }
+
+    /// Frames per second at the given frame time percentile, 0.99 gives the 1% low
+    pub fn percentile_fps(&self, percentile: f32) -> f32 {
+        fps(self.percentile_frame_time(percentile))
+    }
+
+    /// Everything in a few lines of text, for the overlay or the log
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "FPS {:.1} (1% LOW {:.1})\nFRAME {:.2} MS\n",
+            self.average_fps(),
+            self.percentile_fps(0.99),
+            milliseconds(self.average_frame_time()),
+        );
+        for (name, duration) in &self.passes {
+            summary += &format!("{} {:.2} MS\n", name, milliseconds(*duration));
+        }
+        summary
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY)
+    }
+}
+
+fn fps(frame_time: Duration) -> f32 {
+    let seconds = frame_time.as_secs_f32();
+    if seconds > 0.0 {
+        1.0 / seconds
+    } else {
+        0.0
+    }
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+/// Measures consecutive passes, every lap() ends one and starts the next
+pub struct PassTimer {
+    start: Instant,
+}
+
+impl PassTimer {
+    pub fn start() -> Self {
+        Self { start: Instant::now() }
+    }
+
+    pub fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now - self.start;
+        self.start = now;
+        elapsed
+    }
+}
@@ -0,0 +1,179 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+
+use crate::bounds::{Aabb, BoundingSphere};
+
+/// Plane in hessian normal form, points on the positive side are "inside"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Plane from the coefficients a*x + b*y + c*z + d = 0, normalized
+    pub fn from_coefficients(coefficients: Vector4<f32>) -> Self {
+        let normal = coefficients.truncate();
+        let length = normal.magnitude();
+        Self {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    /// Positive in front of the plane, negative behind it
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(Vector3::new(point.x, point.y, point.z)) + self.distance
+    }
+}
+
+/// The six planes of a view frustum, all normals point inwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// left, right, bottom, top, near, far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a view projection matrix (Gribb/Hartmann)
+    /// expects wgpu's clip space, which means depth is in [0, 1]
+    /// e.g. `Frustum::from_matrix(&camera.build_view_projection_matrix())`
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let row = |i| matrix.row(i);
+        Self {
+            planes: [
+                Plane::from_coefficients(row(3) + row(0)),
+                Plane::from_coefficients(row(3) - row(0)),
+                Plane::from_coefficients(row(3) + row(1)),
+                Plane::from_coefficients(row(3) - row(1)),
+                // 0 <= z, not -w <= z as in OpenGL
+                Plane::from_coefficients(row(2)),
+                Plane::from_coefficients(row(3) - row(2)),
+            ],
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// Conservative: might return true for spheres just outside a frustum corner
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    /// Conservative: might return true for boxes just outside a frustum corner
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // corner furthest along the normal, if it is behind the plane all others are too
+            let positive = Point3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
+/// How many objects survived culling in the last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CullingStats {
+    pub total: usize,
+    pub visible: usize,
+}
+
+impl CullingStats {
+    #[allow(dead_code)]
+    pub fn culled(&self) -> usize {
+        self.total - self.visible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    /// 90° field of view, square, near 1 and far 10, looking down -z from the origin
+    fn frustum() -> Frustum {
+        let projection = cgmath::perspective(cgmath::Deg(90.0), 1.0, 1.0, 10.0);
+        Frustum::from_matrix(&(crate::camera::OPENGL_TO_WGPU_MATRIX * projection))
+    }
+
+    fn assert_plane(plane: &Plane, normal: Vector3<f32>, distance: f32) {
+        assert!((plane.normal - normal).magnitude() < EPSILON, "{:?} != {:?}", plane.normal, normal);
+        assert!((plane.distance - distance).abs() < EPSILON, "{} != {}", plane.distance, distance);
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, radius: f32) -> BoundingSphere {
+        BoundingSphere { center: Point3::new(x, y, z), radius }
+    }
+
+    fn aabb(min: (f32, f32, f32), max: (f32, f32, f32)) -> Aabb {
+        Aabb {
+            min: Point3::new(min.0, min.1, min.2),
+            max: Point3::new(max.0, max.1, max.2),
+        }
+    }
+
+    #[test]
+    fn planes_of_a_perspective_matrix() {
+        let [left, right, bottom, top, near, far] = frustum().planes;
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert_plane(&left, Vector3::new(diagonal, 0.0, -diagonal), 0.0);
+        assert_plane(&right, Vector3::new(-diagonal, 0.0, -diagonal), 0.0);
+        assert_plane(&bottom, Vector3::new(0.0, diagonal, -diagonal), 0.0);
+        assert_plane(&top, Vector3::new(0.0, -diagonal, -diagonal), 0.0);
+        // depth in [0, 1] puts the near plane at z = -1, not at the eye
+        assert_plane(&near, Vector3::new(0.0, 0.0, -1.0), -1.0);
+        assert_plane(&far, Vector3::new(0.0, 0.0, 1.0), 10.0);
+    }
+
+    #[test]
+    fn contains_point() {
+        let frustum = frustum();
+        assert!(frustum.contains_point(Point3::new(0.0, 0.0, -5.0)));
+        assert!(frustum.contains_point(Point3::new(4.9, -4.9, -5.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -11.0)));
+        assert!(!frustum.contains_point(Point3::new(5.1, 0.0, -5.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, 5.0)));
+    }
+
+    #[test]
+    fn spheres_inside_outside_and_straddling() {
+        let frustum = frustum();
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -5.0, 1.0)));
+        // behind the camera, beyond the far plane and off to the side
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 2.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -12.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(8.0, 0.0, -5.0, 1.0)));
+        // the center is outside, but the sphere reaches in
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -10.5, 1.0)));
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -0.5, 1.0)));
+        assert!(frustum.intersects_sphere(&sphere(5.5, 0.0, -5.0, 1.0)));
+    }
+
+    #[test]
+    fn boxes_inside_outside_and_straddling() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -6.0), (1.0, 1.0, -4.0))));
+        assert!(!frustum.intersects_aabb(&aabb((-1.0, -1.0, 1.0), (1.0, 1.0, 3.0))));
+        assert!(!frustum.intersects_aabb(&aabb((-1.0, -1.0, -13.0), (1.0, 1.0, -11.0))));
+        assert!(!frustum.intersects_aabb(&aabb((7.0, -1.0, -6.0), (9.0, 1.0, -4.0))));
+        // crosses the far, near and right plane
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -11.0), (1.0, 1.0, -9.0))));
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -2.0), (1.0, 1.0, 0.0))));
+        assert!(frustum.intersects_aabb(&aabb((4.0, -1.0, -6.0), (6.0, 1.0, -4.0))));
+        // bigger than the whole frustum
+        assert!(frustum.intersects_aabb(&aabb((-20.0, -20.0, -20.0), (20.0, 20.0, 20.0))));
+    }
+
+    #[test]
+    fn culling_stats() {
+        let stats = CullingStats { total: 10, visible: 3 };
+        assert_eq!(stats.culled(), 7);
+    }
+}
@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+/// Source of time for the game loop, so it can be driven by something else than the wall clock
+pub trait Clock {
+    /// Time since some fixed point, has to be monotonic
+    fn now(&self) -> Duration;
+}
+
+/// Wall clock, starting at creation
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Clock which only moves when told to, for tests and offline rendering
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManualClock {
+    now: Duration,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
+
+/// Turns variable frame times into a whole number of fixed simulation steps
+/// time, which is left over, is carried into the next frame
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    timestep: Duration,
+    /// more steps per frame are dropped, so a slow frame can't cause an ever slower next frame
+    max_steps: u32,
+    accumulator: Duration,
+    previous: Option<Duration>,
+}
+
+impl FixedTimestep {
+    pub fn new(timestep: Duration, max_steps: u32) -> Self {
+        assert!(timestep > Duration::from_secs(0), "timestep has to be positive");
+        Self {
+            timestep,
+            max_steps,
+            accumulator: Duration::from_secs(0),
+            previous: None,
+        }
+    }
+
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Add the time since the last call, returns how many steps to simulate
+    /// the first call only starts the clock
+    pub fn advance(&mut self, now: Duration) -> u32 {
+        let elapsed = match self.previous {
+            Some(previous) => now.saturating_sub(previous),
+            None => Duration::from_secs(0),
+        };
+        self.previous = Some(now);
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < self.max_steps {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        // spiral of death, give up on catching up and only keep the partial step
+        if self.accumulator >= self.timestep {
+            let skipped = self.accumulator.as_nanos() / self.timestep.as_nanos();
+            log::warn!("Simulation is falling behind, skipping {} steps", skipped);
+            self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % self.timestep.as_nanos()) as u64);
+        }
+
+        steps
+    }
+
+    /// How far the simulation is into the next step, in [0, 1)
+    /// rendering interpolates between the previous and the current step by this
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+}
+
+/// Keeps frames at least min_frame_time apart, does nothing without a limit
+#[derive(Debug, Clone)]
+pub struct FrameLimiter {
+    min_frame_time: Option<Duration>,
+    last_frame: Option<Duration>,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<u32>) -> Self {
+        Self {
+            min_frame_time: max_fps
+                .filter(|fps| *fps > 0)
+                .map(|fps| Duration::from_secs_f64(1.0 / fps as f64)),
+            last_frame: None,
+        }
+    }
+
+    /// Remember the start of a frame, returns the time since the previous one
+    pub fn frame_started(&mut self, now: Duration) -> Duration {
+        let frame_time = self.last_frame.map_or(Duration::from_secs(0), |last| now.saturating_sub(last));
+        self.last_frame = Some(now);
+        frame_time
+    }
+
+    /// How long to wait before starting the next frame, None if it is due
+    pub fn time_until_next_frame(&self, now: Duration) -> Option<Duration> {
+        let next = self.last_frame? + self.min_frame_time?;
+        if next > now {
+            Some(next - now)
+        } else {
+            None
+        }
+    }
+}
+
+/// What to do this frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    /// number of fixed updates to run
+    pub steps: u32,
+    /// duration of every fixed update
+    pub timestep: Duration,
+    /// interpolation factor between the previous and current simulation state
+    pub alpha: f32,
+    /// time since the previous frame
+    pub frame_time: Duration,
+}
+
+/// Fixed timestep simulation with variable rate rendering
+/// call tick() once per frame, run tick.steps fixed updates and render with tick.alpha
+pub struct GameLoop<C: Clock> {
+    clock: C,
+    timestep: FixedTimestep,
+    limiter: FrameLimiter,
+}
+
+impl<C: Clock> GameLoop<C> {
+    pub fn new(clock: C, timestep: Duration, max_steps: u32, max_fps: Option<u32>) -> Self {
+        Self {
+            clock,
+            timestep: FixedTimestep::new(timestep, max_steps),
+            limiter: FrameLimiter::new(max_fps),
+        }
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    pub fn tick(&mut self) -> Tick {
+        let now = self.clock.now();
+        let frame_time = self.limiter.frame_started(now);
+        let steps = self.timestep.advance(now);
+
+        Tick {
+            steps,
+            timestep: self.timestep.timestep(),
+            alpha: self.timestep.alpha(),
+            frame_time,
+        }
+    }
+
+    /// How long to wait until the next frame should start, None if it can start right away
+    pub fn time_until_next_frame(&self) -> Option<Duration> {
+        self.limiter.time_until_next_frame(self.clock.now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn first_advance_only_starts_the_clock() {
+        let mut timestep = FixedTimestep::new(ms(10), 5);
+        assert_eq!(timestep.advance(ms(1000)), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn whole_steps_and_alpha_of_the_partial_step() {
+        let mut timestep = FixedTimestep::new(ms(10), 5);
+        timestep.advance(ms(0));
+        assert_eq!(timestep.advance(ms(25)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+
+        // the leftover half step is carried into the next frame
+        assert_eq!(timestep.advance(ms(30)), 1);
+        assert!(timestep.alpha().abs() < 1e-4);
+        assert_eq!(timestep.advance(ms(34)), 0);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn max_steps_drops_the_backlog() {
+        let mut timestep = FixedTimestep::new(ms(10), 3);
+        timestep.advance(ms(0));
+        // a 1s hitch would be 100 steps, only 3 are run and the rest is skipped
+        assert_eq!(timestep.advance(ms(1005)), 3);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        // nothing of the skipped time is left for the next frame
+        assert_eq!(timestep.advance(ms(1010)), 1);
+    }
+
+    #[test]
+    fn game_loop_with_manual_clock() {
+        let mut game_loop = GameLoop::new(ManualClock::new(), ms(10), 5, None);
+        let tick = game_loop.tick();
+        assert_eq!(tick.steps, 0);
+        assert_eq!(tick.frame_time, ms(0));
+
+        game_loop.clock_mut().advance(ms(16));
+        let tick = game_loop.tick();
+        assert_eq!(tick.steps, 1);
+        assert_eq!(tick.timestep, ms(10));
+        assert_eq!(tick.frame_time, ms(16));
+        assert!((tick.alpha - 0.6).abs() < 1e-4);
+
+        game_loop.clock_mut().advance(ms(4));
+        let tick = game_loop.tick();
+        assert_eq!(tick.steps, 1);
+        assert_eq!(tick.frame_time, ms(4));
+        // without a limit the next frame is always due
+        assert_eq!(game_loop.time_until_next_frame(), None);
+    }
+
+    #[test]
+    fn frame_limiter_waits_for_the_next_frame() {
+        let mut limiter = FrameLimiter::new(Some(50));
+        assert_eq!(limiter.time_until_next_frame(ms(0)), None);
+
+        assert_eq!(limiter.frame_started(ms(100)), ms(0));
+        assert_eq!(limiter.time_until_next_frame(ms(105)), Some(ms(15)));
+        assert_eq!(limiter.time_until_next_frame(ms(120)), None);
+        assert_eq!(limiter.time_until_next_frame(ms(130)), None);
+        assert_eq!(limiter.frame_started(ms(130)), ms(30));
+    }
+
+    #[test]
+    fn frame_limiter_without_limit() {
+        for max_fps in [None, Some(0)] {
+            let mut limiter = FrameLimiter::new(max_fps);
+            limiter.frame_started(ms(100));
+            assert_eq!(limiter.time_until_next_frame(ms(100)), None);
+        }
+    }
+}
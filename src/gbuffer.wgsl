@@ -0,0 +1,77 @@
+// Geometry pass of the deferred path, writes surface attributes instead of colors
+[[block]]
+struct Uniform {
+view_projection: mat4x4<f32>;
+view: mat4x4<f32>;
+inverse_view_projection: mat4x4<f32>;
+eye: vec4<f32>;
+};
+
+[[group(1), binding(0)]]
+var<uniform> uniform: Uniform;
+
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+	[[location(5)]] model_0: vec4<f32>;
+	[[location(6)]] model_1: vec4<f32>;
+	[[location(7)]] model_2: vec4<f32>;
+	[[location(8)]] model_3: vec4<f32>;
+	[[location(9)]] normal_0: vec3<f32>;
+	[[location(10)]] normal_1: vec3<f32>;
+	[[location(11)]] normal_2: vec3<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] normal: vec3<f32>;
+};
+
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let model_matrix = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	let normal_matrix = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+	let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+
+	var out: VertexOutput;
+	out.clip_coordinate = uniform.view_projection * world_position;
+	out.texture_coords = model.texture_coords;
+	out.normal = normal_matrix * model.normal;
+	return out;
+}
+
+// Fragment shader
+[[block]]
+struct Material {
+	specular: f32;
+	shininess: f32;
+	emissive: f32;
+	pad0: f32;
+};
+
+[[group(0), binding(0)]]
+var t_diffuse: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_diffuse: sampler;
+[[group(0), binding(2)]]
+var<uniform> material: Material;
+
+struct GBufferOutput {
+	[[location(0)]] albedo: vec4<f32>;
+	[[location(1)]] normal: vec4<f32>;
+	[[location(2)]] material: vec4<f32>;
+};
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> GBufferOutput {
+	var out: GBufferOutput;
+	out.albedo = textureSample(t_diffuse, s_diffuse, in.texture_coords);
+	out.normal = vec4<f32>(normalize(in.normal), 0.0);
+	out.material = vec4<f32>(material.specular, material.shininess, material.emissive, 1.0);
+	return out;
+}
@@ -0,0 +1,366 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Quaternion, Rad, Rotation3, Transform, Vector3};
+
+/// Size of the gizmo relative to its distance to the camera, keeps it the same size on screen
+const SCREEN_SCALE: f32 = 0.15;
+/// How close the ray has to get to a handle, relative to the gizmo size
+const HANDLE_TOLERANCE: f32 = 0.08;
+/// Plane handles are squares between two axes, from and to this far along both
+const PLANE_HANDLE: (f32, f32) = (0.25, 0.45);
+/// Colors of the x, y and z handles
+const AXIS_COLORS: [[f32; 4]; 3] = [crate::debug_draw::RED, crate::debug_draw::GREEN, crate::debug_draw::BLUE];
+/// Color of the handle under the cursor or being dragged
+const ACTIVE_COLOR: [f32; 4] = crate::debug_draw::YELLOW;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [Self; 3] = [Self::Translate, Self::Rotate, Self::Scale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Translate => "Translate",
+            Self::Rotate => "Rotate",
+            Self::Scale => "Scale",
+        }
+    }
+}
+
+/// Orientation of the handles, scaling always happens along the object's own axes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoSpace {
+    World,
+    Local,
+}
+
+impl GizmoSpace {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::World => "World",
+            Self::Local => "Local",
+        }
+    }
+
+    pub fn toggled(&self) -> Self {
+        match self {
+            Self::World => Self::Local,
+            Self::Local => Self::World,
+        }
+    }
+}
+
+/// Grabbed part of the gizmo, axes are 0 = x, 1 = y and 2 = z
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// move along, rotate around or scale along one axis
+    Axis(usize),
+    /// move or scale in the plane with this normal axis
+    Plane(usize),
+}
+
+/// Increments edits are rounded to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapping {
+    pub enabled: bool,
+    /// world units
+    pub translation: f32,
+    /// degrees
+    pub rotation: f32,
+    /// scale factor
+    pub scale: f32,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            translation: 0.25,
+            rotation: 15.0,
+            scale: 0.1,
+        }
+    }
+}
+
+/// State at the start of a drag, every drag update starts over from here
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    constraint: Constraint,
+    start: crate::scene::Transform,
+    // world space origin and handle axes
+    origin: Point3<f32>,
+    axes: [Vector3<f32>; 3],
+    // world space changes have to be applied in the parent's space
+    parent_inverse: Matrix4<f32>,
+    // where the ray hit the handle, relative to origin
+    grab: Vector3<f32>,
+}
+
+/// Translate, rotate and scale handles for one object, dragged with rays from the cursor
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snapping: Snapping,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snapping: Snapping::default(),
+            drag: None,
+        }
+    }
+
+    /// Length of the handles for a gizmo at origin, seen from eye
+    pub fn size(eye: Point3<f32>, origin: Point3<f32>) -> f32 {
+        (origin - eye).magnitude() * SCREEN_SCALE
+    }
+
+    /// World space handle axes of an object with this world transform
+    pub fn axes(&self, world: &Matrix4<f32>) -> [Vector3<f32>; 3] {
+        if self.space == GizmoSpace::World && self.mode != GizmoMode::Scale {
+            return [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+        }
+        let axis = |column: Vector3<f32>, fallback: Vector3<f32>| {
+            if column.magnitude2() > f32::EPSILON { column.normalize() } else { fallback }
+        };
+        [
+            axis(world.x.truncate(), Vector3::unit_x()),
+            axis(world.y.truncate(), Vector3::unit_y()),
+            axis(world.z.truncate(), Vector3::unit_z()),
+        ]
+    }
+
+    /// Handle closest to the camera, which the ray touches
+    pub fn hit(&self, ray: &crate::picking::Ray, world: &Matrix4<f32>, size: f32) -> Option<Constraint> {
+        let origin = Point3::from_homogeneous(world.w);
+        let axes = self.axes(world);
+        let tolerance = size * HANDLE_TOLERANCE;
+        let mut closest: Option<(f32, Constraint)> = None;
+        let mut candidate = |distance: f32, constraint: Constraint| {
+            if closest.is_none_or(|(closest, _)| distance < closest) {
+                closest = Some((distance, constraint));
+            }
+        };
+
+        for (index, &axis) in axes.iter().enumerate() {
+            let (u, v) = (axes[(index + 1) % 3], axes[(index + 2) % 3]);
+            match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    if let Some((distance, along)) = closest_parameters(ray, origin, axis) {
+                        let along = along.max(0.0).min(size);
+                        let gap = (ray.at(distance) - (origin + axis * along)).magnitude();
+                        if distance >= 0.0 && gap < tolerance {
+                            candidate(distance, Constraint::Axis(index));
+                        }
+                    }
+                    if let Some(distance) = ray.intersect_plane(origin, axis) {
+                        let offset = ray.at(distance) - origin;
+                        let inside = |value: f32| value >= PLANE_HANDLE.0 * size && value <= PLANE_HANDLE.1 * size;
+                        if inside(offset.dot(u)) && inside(offset.dot(v)) {
+                            candidate(distance, Constraint::Plane(index));
+                        }
+                    }
+                },
+                GizmoMode::Rotate => {
+                    if let Some(distance) = ray.intersect_plane(origin, axis) {
+                        let radius = (ray.at(distance) - origin).magnitude();
+                        if (radius - size).abs() < tolerance {
+                            candidate(distance, Constraint::Axis(index));
+                        }
+                    }
+                },
+            }
+        }
+
+        closest.map(|(_, constraint)| constraint)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Start dragging a handle of the object with local transform and world matrix
+    /// parent is the world matrix of the object's parent, false if the ray misses the handle's plane or line
+    pub fn begin_drag(
+        &mut self,
+        ray: &crate::picking::Ray,
+        constraint: Constraint,
+        local: crate::scene::Transform,
+        world: &Matrix4<f32>,
+        parent: &Matrix4<f32>,
+    ) -> bool {
+        use cgmath::SquareMatrix;
+        let mut drag = Drag {
+            constraint,
+            start: local,
+            origin: Point3::from_homogeneous(world.w),
+            axes: self.axes(world),
+            parent_inverse: parent.invert().unwrap_or_else(Matrix4::identity),
+            grab: Vector3::new(0.0, 0.0, 0.0),
+        };
+        match self.grab_point(&drag, ray) {
+            Some(grab) => {
+                drag.grab = grab;
+                self.drag = Some(drag);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Local transform for the current ray, None if there's no drag or the ray misses the handle
+    pub fn drag(&self, ray: &crate::picking::Ray) -> Option<crate::scene::Transform> {
+        let drag = self.drag.as_ref()?;
+        let point = self.grab_point(drag, ray)?;
+        let mut transform = drag.start;
+
+        match (self.mode, drag.constraint) {
+            (GizmoMode::Translate, Constraint::Axis(index)) => {
+                let axis = drag.axes[index];
+                let distance = self.snap((point - drag.grab).dot(axis), self.snapping.translation);
+                transform.translation += drag.parent_inverse.transform_vector(axis * distance);
+            },
+            (GizmoMode::Translate, Constraint::Plane(index)) => {
+                let (u, v) = (drag.axes[(index + 1) % 3], drag.axes[(index + 2) % 3]);
+                let offset = point - drag.grab;
+                let world = u * self.snap(offset.dot(u), self.snapping.translation)
+                    + v * self.snap(offset.dot(v), self.snapping.translation);
+                transform.translation += drag.parent_inverse.transform_vector(world);
+            },
+            (GizmoMode::Rotate, Constraint::Axis(index)) | (GizmoMode::Rotate, Constraint::Plane(index)) => {
+                let axis = drag.axes[index];
+                let angle = axis.dot(drag.grab.cross(point)).atan2(drag.grab.dot(point));
+                let angle = self.snap(angle.to_degrees(), self.snapping.rotation).to_radians();
+                let local_axis = drag.parent_inverse.transform_vector(axis);
+                if local_axis.magnitude2() <= f32::EPSILON {
+                    return None;
+                }
+                transform.rotation = Quaternion::from_axis_angle(local_axis.normalize(), Rad(angle)) * drag.start.rotation;
+            },
+            (GizmoMode::Scale, Constraint::Axis(index)) => {
+                let axis = drag.axes[index];
+                let start = drag.grab.dot(axis);
+                if start.abs() <= f32::EPSILON {
+                    return None;
+                }
+                let factor = self.snap(point.dot(axis) / start, self.snapping.scale);
+                transform.scale[index] = drag.start.scale[index] * factor;
+            },
+            (GizmoMode::Scale, Constraint::Plane(index)) => {
+                let start = drag.grab.magnitude();
+                if start <= f32::EPSILON {
+                    return None;
+                }
+                let factor = self.snap(point.magnitude() / start, self.snapping.scale);
+                for other in [(index + 1) % 3, (index + 2) % 3].iter() {
+                    transform.scale[*other] = drag.start.scale[*other] * factor;
+                }
+            },
+        }
+
+        Some(transform)
+    }
+
+    /// Stop dragging, returns the local transform from before the drag
+    pub fn end_drag(&mut self) -> Option<crate::scene::Transform> {
+        self.drag.take().map(|drag| drag.start)
+    }
+
+    /// Handles of the object with world transform, hovered is highlighted, as is the dragged one
+    pub fn draw(
+        &self,
+        lines: &mut crate::debug_draw::DebugLines,
+        world: &Matrix4<f32>,
+        size: f32,
+        hovered: Option<Constraint>,
+    ) {
+        let origin = Point3::from_homogeneous(world.w);
+        // axes are fixed while dragging, so the handles don't move away from the cursor
+        let axes = self.drag.map_or_else(|| self.axes(world), |drag| drag.axes);
+        let active = self.drag.map(|drag| drag.constraint).or(hovered);
+        let color = |constraint: Constraint, index: usize| {
+            if active == Some(constraint) { ACTIVE_COLOR } else { AXIS_COLORS[index] }
+        };
+
+        for (index, &axis) in axes.iter().enumerate() {
+            let (u, v) = (axes[(index + 1) % 3], axes[(index + 2) % 3]);
+            let axis_color = color(Constraint::Axis(index), index);
+            match self.mode {
+                GizmoMode::Translate => lines.arrow(origin, origin + axis * size, axis_color),
+                GizmoMode::Rotate => lines.circle(origin, u, v, size, axis_color),
+                GizmoMode::Scale => {
+                    let end = origin + axis * size;
+                    let half = Vector3::new(1.0, 1.0, 1.0) * size * 0.04;
+                    lines.line(origin, end, axis_color);
+                    lines.aabb(&crate::bounds::Aabb { min: end - half, max: end + half }, axis_color);
+                },
+            }
+            if self.mode != GizmoMode::Rotate {
+                let plane_color = color(Constraint::Plane(index), index);
+                let (near, far) = (PLANE_HANDLE.0 * size, PLANE_HANDLE.1 * size);
+                let corners = [
+                    origin + u * near + v * near,
+                    origin + u * far + v * near,
+                    origin + u * far + v * far,
+                    origin + u * near + v * far,
+                ];
+                for corner in 0..4 {
+                    lines.line(corners[corner], corners[(corner + 1) % 4], plane_color);
+                }
+            }
+        }
+    }
+
+    /// Where the ray meets the line or plane of the dragged handle, relative to the origin
+    fn grab_point(&self, drag: &Drag, ray: &crate::picking::Ray) -> Option<Vector3<f32>> {
+        match (self.mode, drag.constraint) {
+            (GizmoMode::Translate, Constraint::Axis(index)) | (GizmoMode::Scale, Constraint::Axis(index)) => {
+                let axis = drag.axes[index];
+                let (_, along) = closest_parameters(ray, drag.origin, axis)?;
+                Some(axis * along)
+            },
+            (_, Constraint::Axis(index)) | (_, Constraint::Plane(index)) => {
+                let distance = ray.intersect_plane(drag.origin, drag.axes[index])?;
+                Some(ray.at(distance) - drag.origin)
+            },
+        }
+    }
+
+    fn snap(&self, value: f32, step: f32) -> f32 {
+        if self.snapping.enabled && step > 0.0 {
+            (value / step).round() * step
+        } else {
+            value
+        }
+    }
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Distance along the ray and along the axis line through origin of the closest points of both,
+/// None if they are parallel
+fn closest_parameters(ray: &crate::picking::Ray, origin: Point3<f32>, axis: Vector3<f32>) -> Option<(f32, f32)> {
+    let offset = ray.origin - origin;
+    let a = ray.direction.dot(ray.direction);
+    let b = ray.direction.dot(axis);
+    let c = axis.dot(axis);
+    let d = ray.direction.dot(offset);
+    let e = axis.dot(offset);
+    let denominator = a * c - b * b;
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    Some(((b * e - c * d) / denominator, (a * e - b * d) / denominator))
+}
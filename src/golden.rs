@@ -0,0 +1,367 @@
+/// Size of every golden image
+pub const GOLDEN_WIDTH: u32 = 320;
+pub const GOLDEN_HEIGHT: u32 = 240;
+/// Frames rendered before the one compared, so the simulation and temporal effects settle
+const WARMUP_FRAMES: u32 = 3;
+/// Where the references live, relative to the repository
+const GOLDEN_DIR: &str = "golden";
+/// Where actual and diff images of failed cases are written
+const FAILURE_DIR: &str = "golden/failures";
+
+/// How far an image may be off from its reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// largest difference of a color channel, which still counts as the same pixel
+    pub per_channel: u8,
+    /// share of pixels in [0, 1], which may differ by more than per_channel
+    /// rasterization rules aren't exact, so a few edge pixels can be off between adapters
+    pub max_failing: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_failing: 0.001,
+        }
+    }
+}
+
+/// Options of a golden run, from the command line
+#[derive(Debug, Clone, Default)]
+pub struct GoldenOptions {
+    pub tolerance: Tolerance,
+    /// overwrite the references with the current output instead of comparing
+    pub update: bool,
+    /// only run the cases with these names, every case if empty
+    pub filter: Vec<String>,
+}
+
+impl GoldenOptions {
+    /// Parse everything after "golden": --update, --tolerance <0-255>, --max-failing <0-1> and case names
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--update" => options.update = true,
+                "--tolerance" => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("--tolerance needs a value"))?;
+                    options.tolerance.per_channel = value.parse()?;
+                },
+                "--max-failing" => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("--max-failing needs a value"))?;
+                    options.tolerance.max_failing = value.parse()?;
+                },
+                _ if arg.starts_with("--") => anyhow::bail!("Unknown option {}", arg),
+                _ => options.filter.push(arg),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// A scene rendered to a golden image
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenCase {
+    pub name: String,
+    /// None for the built-in scene
+    pub scene: Option<std::path::PathBuf>,
+}
+
+impl GoldenCase {
+    /// The built-in scene and every scene in the scenes directory
+    pub fn all() -> Vec<Self> {
+        let mut cases = vec![Self {
+            name: "default".to_string(),
+            scene: None,
+        }];
+        let mut scenes: Vec<_> = std::fs::read_dir("scenes")
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+            .collect();
+        scenes.sort();
+        cases.extend(scenes.into_iter().filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some(Self { name, scene: Some(path) })
+        }));
+        cases
+    }
+
+    pub fn reference_path(&self) -> std::path::PathBuf {
+        std::path::Path::new(GOLDEN_DIR).join(format!("{}.png", self.name))
+    }
+}
+
+/// Result of comparing an image with its reference
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// pixels differing by more than the tolerance
+    pub failing: usize,
+    pub total: usize,
+    /// largest difference of any channel
+    pub max_difference: u8,
+    /// failing pixels in red, on a faded copy of the actual image
+    pub diff: image::RgbaImage,
+}
+
+impl Comparison {
+    pub fn passed(&self, tolerance: &Tolerance) -> bool {
+        self.failing as f32 <= tolerance.max_failing * self.total as f32
+    }
+}
+
+/// Compare two images of the same size pixel by pixel, None if the sizes differ
+pub fn compare(reference: &image::RgbaImage, actual: &image::RgbaImage, tolerance: &Tolerance) -> Option<Comparison> {
+    if reference.dimensions() != actual.dimensions() {
+        return None;
+    }
+
+    let mut diff = image::RgbaImage::new(actual.width(), actual.height());
+    let mut failing = 0;
+    let mut max_difference = 0;
+    for ((expected, pixel), out) in reference.pixels().zip(actual.pixels()).zip(diff.pixels_mut()) {
+        let difference = expected.0
+            .iter()
+            .zip(pixel.0.iter())
+            .map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() as u8)
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        *out = if difference > tolerance.per_channel {
+            failing += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            // faded gray, so the failures stand out but the picture is still recognizable
+            let [r, g, b, _] = pixel.0;
+            let luma = (r as u32 * 3 + g as u32 * 6 + b as u32) / 10;
+            let faded = (luma / 3) as u8;
+            image::Rgba([faded, faded, faded, 255])
+        };
+    }
+
+    Some(Comparison {
+        failing,
+        total: (actual.width() * actual.height()) as usize,
+        max_difference,
+        diff,
+    })
+}
+
+/// Settings of every case, no environment, the output may only depend on the scene
+fn settings(scene: Option<std::path::PathBuf>) -> crate::settings::Settings {
+    crate::settings::Settings {
+        scene,
+        software_adapter: true,
+        // every driver resolves MSAA a little differently, edges would never match
+        sample_count: 1,
+        ..Default::default()
+    }
+}
+
+/// Render one case offscreen, after the warmup frames
+fn render_case(case: &GoldenCase) -> anyhow::Result<image::RgbaImage> {
+    let settings = settings(case.scene.clone());
+    let size = winit::dpi::PhysicalSize::new(GOLDEN_WIDTH, GOLDEN_HEIGHT);
+    let mut state = pollster::block_on(crate::state::State::offscreen(size, &settings))?;
+
+    // time only moves by whole fixed updates, the same on every machine
+    let mut game_loop = crate::game_loop::GameLoop::new(
+        crate::game_loop::ManualClock::new(),
+        settings.timestep(),
+        settings.max_simulation_steps,
+        None,
+    );
+    for _ in 0..WARMUP_FRAMES {
+        game_loop.clock_mut().advance(settings.timestep());
+        let tick = game_loop.tick();
+        for _ in 0..tick.steps {
+            state.fixed_update();
+        }
+        state.update_frame(&tick);
+        state.render().map_err(|error| anyhow::anyhow!("Could not render: {:?}", error))?;
+    }
+    let frame = state
+        .capture_frame()
+        .map_err(|error| anyhow::anyhow!("Could not render: {:?}", error))?;
+
+    image::RgbaImage::from_raw(frame.width, frame.height, frame.pixels)
+        .ok_or_else(|| anyhow::anyhow!("Captured frame has the wrong size"))
+}
+
+/// Check one case against its reference, writes the actual and diff images if it fails
+/// returns whether it passed
+fn check_case(actual: &image::RgbaImage, case: &GoldenCase, options: &GoldenOptions) -> anyhow::Result<bool> {
+    let reference_path = case.reference_path();
+    if options.update {
+        std::fs::create_dir_all(GOLDEN_DIR)?;
+        actual.save(&reference_path)?;
+        println!("{}: updated {:?}", case.name, reference_path);
+        return Ok(true);
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(error) => {
+            println!("{}: FAILED, no reference {:?} ({}), create it with --update", case.name, reference_path, error);
+            return Ok(false);
+        },
+    };
+
+    let failure_dir = std::path::Path::new(FAILURE_DIR);
+    match compare(&reference, actual, &options.tolerance) {
+        Some(comparison) if comparison.passed(&options.tolerance) => {
+            println!("{}: ok ({} pixels off, at most by {})", case.name, comparison.failing, comparison.max_difference);
+            Ok(true)
+        },
+        Some(comparison) => {
+            std::fs::create_dir_all(failure_dir)?;
+            actual.save(failure_dir.join(format!("{}-actual.png", case.name)))?;
+            comparison.diff.save(failure_dir.join(format!("{}-diff.png", case.name)))?;
+            println!(
+                "{}: FAILED, {} of {} pixels off, at most by {}, see {:?}",
+                case.name, comparison.failing, comparison.total, comparison.max_difference, failure_dir,
+            );
+            Ok(false)
+        },
+        None => {
+            std::fs::create_dir_all(failure_dir)?;
+            actual.save(failure_dir.join(format!("{}-actual.png", case.name)))?;
+            println!(
+                "{}: FAILED, rendered {}x{} but the reference is {}x{}",
+                case.name, actual.width(), actual.height(), reference.width(), reference.height(),
+            );
+            Ok(false)
+        },
+    }
+}
+
+/// Render every case and compare it with its reference, returns whether all of them passed
+pub fn run(options: &GoldenOptions) -> bool {
+    let cases: Vec<_> = GoldenCase::all()
+        .into_iter()
+        .filter(|case| options.filter.is_empty() || options.filter.contains(&case.name))
+        .collect();
+    if cases.is_empty() {
+        eprintln!("No golden cases match {:?}", options.filter);
+        return false;
+    }
+
+    let mut passed = 0;
+    for case in &cases {
+        let result = render_case(case).and_then(|actual| check_case(&actual, case, options));
+        match result {
+            Ok(true) => passed += 1,
+            Ok(false) => {},
+            Err(error) => println!("{}: FAILED, {}", case.name, error),
+        }
+    }
+    println!("{} of {} golden images passed", passed, cases.len());
+    passed == cases.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(width: u32, height: u32, value: u8) -> image::RgbaImage {
+        image::RgbaImage::from_pixel(width, height, image::Rgba([value, value, value, 255]))
+    }
+
+    fn args(args: &[&str]) -> anyhow::Result<GoldenOptions> {
+        GoldenOptions::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn identical_images_pass() {
+        let image = gray(8, 8, 100);
+        let comparison = compare(&image, &image, &Tolerance::default()).unwrap();
+        assert_eq!(comparison.failing, 0);
+        assert_eq!(comparison.total, 64);
+        assert_eq!(comparison.max_difference, 0);
+        assert!(comparison.passed(&Tolerance::default()));
+    }
+
+    #[test]
+    fn different_sizes_cant_be_compared() {
+        assert!(compare(&gray(8, 8, 0), &gray(8, 9, 0), &Tolerance::default()).is_none());
+    }
+
+    #[test]
+    fn per_channel_tolerance() {
+        let tolerance = Tolerance { per_channel: 2, max_failing: 0.0 };
+        let reference = gray(4, 4, 100);
+
+        let mut close = reference.clone();
+        close.put_pixel(1, 1, image::Rgba([102, 98, 100, 255]));
+        let comparison = compare(&reference, &close, &tolerance).unwrap();
+        assert_eq!((comparison.failing, comparison.max_difference), (0, 2));
+        assert!(comparison.passed(&tolerance));
+
+        // alpha counts like every other channel
+        let mut off = reference.clone();
+        off.put_pixel(1, 1, image::Rgba([100, 100, 100, 252]));
+        let comparison = compare(&reference, &off, &tolerance).unwrap();
+        assert_eq!((comparison.failing, comparison.max_difference), (1, 3));
+        assert!(!comparison.passed(&tolerance));
+    }
+
+    #[test]
+    fn share_of_failing_pixels() {
+        let reference = gray(10, 10, 0);
+        let mut actual = reference.clone();
+        actual.put_pixel(3, 4, image::Rgba([255, 255, 255, 255]));
+        let comparison = compare(&reference, &actual, &Tolerance::default()).unwrap();
+        assert_eq!(comparison.failing, 1);
+        assert_eq!(comparison.max_difference, 255);
+
+        assert!(comparison.passed(&Tolerance { per_channel: 2, max_failing: 0.01 }));
+        assert!(!comparison.passed(&Tolerance { per_channel: 2, max_failing: 0.005 }));
+        assert!(!comparison.passed(&Tolerance::default()));
+    }
+
+    #[test]
+    fn diff_marks_failing_pixels() {
+        let reference = gray(2, 1, 90);
+        let mut actual = reference.clone();
+        actual.put_pixel(1, 0, image::Rgba([0, 0, 0, 255]));
+        let diff = compare(&reference, &actual, &Tolerance::default()).unwrap().diff;
+        assert_eq!(diff.get_pixel(0, 0), &image::Rgba([30, 30, 30, 255]));
+        assert_eq!(diff.get_pixel(1, 0), &image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn options_from_args() {
+        let options = args(&["--update", "--tolerance", "5", "default", "--max-failing", "0.5", "hierarchy"]).unwrap();
+        assert!(options.update);
+        assert_eq!(options.tolerance, Tolerance { per_channel: 5, max_failing: 0.5 });
+        assert_eq!(options.filter, vec!["default", "hierarchy"]);
+
+        let options = args(&[]).unwrap();
+        assert!(!options.update);
+        assert_eq!(options.tolerance, Tolerance::default());
+        assert!(options.filter.is_empty());
+
+        assert!(args(&["--tolerance"]).is_err());
+        assert!(args(&["--tolerance", "256"]).is_err());
+        assert!(args(&["--max-failing", "a lot"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn cases_and_references() {
+        let cases = GoldenCase::all();
+        assert_eq!(cases[0], GoldenCase { name: "default".to_string(), scene: None });
+        let hierarchy = cases.iter().find(|case| case.name == "hierarchy").unwrap();
+        assert_eq!(hierarchy.scene, Some(std::path::PathBuf::from("scenes/hierarchy.ron")));
+        assert_eq!(hierarchy.reference_path(), std::path::Path::new("golden/hierarchy.png"));
+    }
+
+    /// Every case against its reference, like `golden` on the command line
+    #[test]
+    #[ignore = "needs a software graphics adapter, run it with `cargo test -- --ignored golden`"]
+    fn golden_images() {
+        assert!(run(&GoldenOptions::default()), "golden images differ, see {}", FAILURE_DIR);
+    }
+}
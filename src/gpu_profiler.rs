@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+/// Scopes per frame, later ones are not measured
+const MAX_SCOPES: u32 = 16;
+/// Frames, which can wait for their readback at the same time
+/// if all of them are still in flight, a frame is simply not profiled
+const FRAMES_IN_FLIGHT: usize = 3;
+/// Start and end timestamp
+const QUERIES_PER_SCOPE: u32 = 2;
+
+struct ProfilerFrame {
+    // resolved timestamps, copied to the readback buffer
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    scopes: Vec<&'static str>,
+    // Some while the gpu hasn't handed the timestamps back yet
+    pending: Option<crate::readback::PendingMap>,
+}
+
+/// GPU time of named scopes, measured with timestamp queries
+/// only exists if the device has Features::TIMESTAMP_QUERY
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    // nanoseconds per timestamp tick
+    timestamp_period: f32,
+
+    frames: Vec<ProfilerFrame>,
+    // frame being recorded, None if there was no free one
+    current: Option<usize>,
+    next_frame: usize,
+    // scope which has been started, but not yet ended
+    open_scope: Option<&'static str>,
+
+    // last frame that made it back from the gpu
+    results: Vec<(&'static str, Duration)>,
+}
+
+impl GpuProfiler {
+    /// Features needed for profiling, request these if the adapter has them
+    pub const FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY;
+
+    /// None if the device can't write timestamps
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(Self::FEATURES) {
+            log::info!("GPU profiling disabled, the device does not support timestamp queries");
+            return None;
+        }
+
+        let queries_per_frame = MAX_SCOPES * QUERIES_PER_SCOPE;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            ty: wgpu::QueryType::Timestamp,
+            count: queries_per_frame * FRAMES_IN_FLIGHT as u32,
+        });
+
+        let size = (queries_per_frame as usize * std::mem::size_of::<u64>()) as wgpu::BufferAddress;
+        let frames = (0..FRAMES_IN_FLIGHT)
+            .map(|_| ProfilerFrame {
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Resolve Buffer"),
+                    size,
+                    usage: wgpu::BufferUsage::COPY_SRC | wgpu::BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                scopes: Vec::new(),
+                pending: None,
+            })
+            .collect();
+
+        Some(Self {
+            query_set,
+            timestamp_period: queue.get_timestamp_period(),
+
+            frames,
+            current: None,
+            next_frame: 0,
+            open_scope: None,
+
+            results: Vec::new(),
+        })
+    }
+
+    /// GPU time of every scope of the most recent frame, which finished reading back
+    pub fn results(&self) -> &[(&'static str, Duration)] {
+        &self.results
+    }
+
+    /// Pick a frame to record into, has to be called before the first scope
+    pub fn begin_frame(&mut self) {
+        let index = self.next_frame;
+        let frame = &mut self.frames[index];
+        if frame.pending.is_some() {
+            // the gpu is behind, skip profiling instead of waiting
+            self.current = None;
+            return;
+        }
+
+        frame.scopes.clear();
+        self.current = Some(index);
+        self.next_frame = (index + 1) % self.frames.len();
+    }
+
+    /// Write the start timestamp of a scope, scopes can't be nested
+    pub fn begin_scope(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        if let Some(query) = self.next_query() {
+            debug_assert!(self.open_scope.is_none(), "gpu profiler scopes can't be nested");
+            encoder.write_timestamp(&self.query_set, query);
+            self.open_scope = Some(name);
+        }
+    }
+
+    /// Write the end timestamp of the open scope
+    pub fn end_scope(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (index, name) = match (self.current, self.open_scope.take()) {
+            (Some(index), Some(name)) => (index, name),
+            _ => return,
+        };
+        let query = self.next_query().expect("begin_scope checked for space") + 1;
+        encoder.write_timestamp(&self.query_set, query);
+        self.frames[index].scopes.push(name);
+    }
+
+    /// Resolve the timestamps of the frame into its readback buffer
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let index = match self.current {
+            Some(index) if !self.frames[index].scopes.is_empty() => index,
+            _ => return,
+        };
+        let frame = &self.frames[index];
+        let first = Self::first_query(index);
+        let count = frame.scopes.len() as u32 * QUERIES_PER_SCOPE;
+        let size = (count as usize * std::mem::size_of::<u64>()) as wgpu::BufferAddress;
+
+        encoder.resolve_query_set(&self.query_set, first..first + count, &frame.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&frame.resolve_buffer, 0, &frame.readback_buffer, 0, size);
+    }
+
+    /// Start reading the frame back, has to be called after the encoder was submitted
+    pub fn after_submit(&mut self) {
+        if let Some(index) = self.current.take() {
+            let frame = &mut self.frames[index];
+            if !frame.scopes.is_empty() {
+                frame.pending = Some(crate::readback::PendingMap::new(
+                    frame.readback_buffer.slice(..),
+                    wgpu::MapMode::Read,
+                ));
+            }
+        }
+    }
+
+    /// Collect finished readbacks, never blocks
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Poll);
+
+        let period = self.timestamp_period as f64;
+        for frame in &mut self.frames {
+            let result = match frame.pending.as_mut().and_then(|pending| pending.poll()) {
+                Some(result) => result,
+                None => continue,
+            };
+            frame.pending = None;
+
+            if result.is_err() {
+                log::warn!("Could not read back gpu timestamps");
+                continue;
+            }
+
+            let slice = frame.readback_buffer.slice(..);
+            {
+                let data = slice.get_mapped_range();
+                let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                self.results = frame.scopes
+                    .iter()
+                    .zip(timestamps.chunks_exact(QUERIES_PER_SCOPE as usize))
+                    .map(|(name, pair)| {
+                        let ticks = pair[1].saturating_sub(pair[0]);
+                        (*name, Duration::from_nanos((ticks as f64 * period) as u64))
+                    })
+                    .collect();
+            }
+            frame.readback_buffer.unmap();
+        }
+    }
+
+    /// Query for the start of the next scope, None if the frame isn't recorded or is full
+    fn next_query(&self) -> Option<u32> {
+        let index = self.current?;
+        let scopes = self.frames[index].scopes.len() as u32;
+        if scopes >= MAX_SCOPES {
+            return None;
+        }
+        Some(Self::first_query(index) + scopes * QUERIES_PER_SCOPE)
+    }
+
+    fn first_query(frame: usize) -> u32 {
+        frame as u32 * MAX_SCOPES * QUERIES_PER_SCOPE
+    }
+}
@@ -0,0 +1,75 @@
+use cgmath::Matrix4;
+
+/// Per instance vertex data, as the shaders see it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    // inverse transpose of the model matrix, keeps normals perpendicular under scaling
+    normal: [[f32; 3]; 3],
+}
+
+impl InstanceRaw {
+    pub fn from_matrix(model: &Matrix4<f32>) -> Self {
+        use cgmath::{Matrix, SquareMatrix};
+        let normal = model
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .transpose();
+        Self {
+            model: (*model).into(),
+            normal: [
+                normal.x.truncate().into(),
+                normal.y.truncate().into(),
+                normal.z.truncate().into(),
+            ],
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem::size_of;
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // advance once per instance, not per vertex
+            step_mode: wgpu::InputStepMode::Instance,
+            // a mat4 takes 4 vertex slots, leave some space after the Vertex attributes
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 19]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
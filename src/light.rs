@@ -0,0 +1,141 @@
+use wgpu::util::DeviceExt;
+
+/// Point lights the forward path shades, every fragment of every draw loops over all of them
+pub const MAX_FORWARD_LIGHTS: usize = 16;
+/// Point lights the deferred path shades, it loops over them once per pixel
+pub const MAX_DEFERRED_LIGHTS: usize = 1024;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    /// distance at which the light has no influence anymore
+    pub radius: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+/// Start of the light buffer, followed by capacity lights
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightHeader {
+    count: u32,
+    ambient: f32,
+    _padding: [f32; 2],
+}
+
+/// All point lights of the scene and their storage buffer
+/// bound as group 3 in both the forward and the deferred lighting pipeline
+pub struct Lights {
+    pub point_lights: Vec<PointLight>,
+    /// strength of the ambient light, gets multiplied with the ambient occlusion
+    pub ambient: f32,
+
+    // lights that fit into the buffer, the rest is dropped
+    capacity: usize,
+    // number of lights at the last warning about dropping some
+    warned_count: Option<usize>,
+    buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Lights {
+    /// capacity is the most lights the shaders get to see, e.g. MAX_FORWARD_LIGHTS
+    pub fn new(device: &wgpu::Device, point_lights: Vec<PointLight>, ambient: f32, capacity: usize) -> Self {
+        // the lights are a runtime sized array, which can't be empty
+        let capacity = capacity.max(1);
+        let mut contents = vec![0; Self::buffer_size(capacity)];
+        Self::write_contents(&mut contents, &point_lights, ambient);
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Light Buffer"),
+                contents: &contents,
+                usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            }
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("light_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("light_bind_group"),
+        });
+
+        let mut lights = Self {
+            point_lights,
+            ambient,
+
+            capacity,
+            warned_count: None,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        };
+        lights.warn_if_dropped();
+        lights
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Write the current lights to the storage buffer
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.warn_if_dropped();
+        let count = self.point_lights.len().min(self.capacity);
+        let mut contents = vec![0; Self::buffer_size(count)];
+        Self::write_contents(&mut contents, &self.point_lights, self.ambient);
+        queue.write_buffer(&self.buffer, 0, &contents);
+    }
+
+    fn buffer_size(capacity: usize) -> usize {
+        std::mem::size_of::<LightHeader>() + capacity * std::mem::size_of::<PointLight>()
+    }
+
+    /// Header and as many lights as fit into contents
+    fn write_contents(contents: &mut [u8], point_lights: &[PointLight], ambient: f32) {
+        let (header, lights) = contents.split_at_mut(std::mem::size_of::<LightHeader>());
+        let count = point_lights.len().min(lights.len() / std::mem::size_of::<PointLight>());
+        header.copy_from_slice(bytemuck::bytes_of(&LightHeader {
+            count: count as u32,
+            ambient,
+            _padding: [0.0; 2],
+        }));
+        lights[..count * std::mem::size_of::<PointLight>()]
+            .copy_from_slice(bytemuck::cast_slice(&point_lights[..count]));
+    }
+
+    /// Once per light count, not every frame
+    fn warn_if_dropped(&mut self) {
+        let count = self.point_lights.len();
+        if count > self.capacity && self.warned_count != Some(count) {
+            log::warn!("Only the first {} of {} lights are used", self.capacity, count);
+            self.warned_count = Some(count);
+        }
+    }
+}
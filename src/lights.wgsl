@@ -0,0 +1,63 @@
+// Point lights and shading shared by the forward and the deferred path
+// Gets prepended to shader.wgsl and deferred_lighting.wgsl
+struct PointLight {
+	position: vec3<f32>;
+	radius: f32;
+	color: vec3<f32>;
+	intensity: f32;
+};
+
+// as many lights as the render path allows, count of them are used
+[[block]]
+struct Lights {
+	count: u32;
+	ambient: f32;
+	pad0: f32;
+	pad1: f32;
+	lights: array<PointLight>;
+};
+
+[[group(3), binding(0)]]
+var<storage> lights: [[access(read)]] Lights;
+
+// Blinn-Phong for every point light, plus occluded ambient light
+fn shade(
+	albedo: vec3<f32>,
+	position: vec3<f32>,
+	normal: vec3<f32>,
+	eye: vec3<f32>,
+	specular_strength: f32,
+	shininess: f32,
+	ao: f32
+) -> vec3<f32> {
+	let view_dir = normalize(eye - position);
+	var color: vec3<f32> = albedo * lights.ambient * ao;
+
+	var i: u32 = 0u;
+	loop {
+		if (i >= lights.count) {
+			break;
+		}
+
+		let light = lights.lights[i];
+		let to_light = light.position - position;
+		let dist = length(to_light);
+		let light_dir = to_light / dist;
+
+		// smooth falloff, which reaches zero at the radius
+		let falloff = clamp(1.0 - dist / light.radius, 0.0, 1.0);
+		let radiance = light.color * light.intensity * falloff * falloff;
+
+		let diffuse = max(dot(normal, light_dir), 0.0);
+		let half_dir = normalize(light_dir + view_dir);
+		let specular = pow(max(dot(normal, half_dir), 0.0), shininess) * specular_strength;
+
+		color = color + (albedo * diffuse + vec3<f32>(specular, specular, specular)) * radiance;
+
+		continuing {
+			i = i + 1u;
+		}
+	}
+
+	return color;
+}
@@ -6,22 +6,92 @@ mod texture;
 mod camera;
 mod uniform;
 mod camera_controller;
+mod settings;
+mod post;
+mod bloom;
+mod ssao;
+mod light;
+mod material;
+mod deferred;
+mod bounds;
+mod frustum;
+mod mesh;
+mod instance;
+mod scene;
+mod scene_file;
+mod ecs;
+mod game_loop;
+mod frame_stats;
+mod bitmap_font;
+mod overlay;
+mod readback;
+mod gpu_profiler;
+mod ui;
+mod atlas;
+mod text;
+mod sprite;
+mod crosshair;
+mod debug_draw;
+mod debug_view;
+mod picking;
+mod gizmo;
+mod command;
+mod capture;
+mod recording;
+mod golden;
+mod error;
 
+use crate::error::Recovery;
 use crate::state::State;
 
 fn main() {
     env_logger::init();
+
+    // `golden [--update] [--tolerance N] [--max-failing F] [case...]` compares rendered scenes with references
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("golden") {
+        let passed = match golden::GoldenOptions::from_args(args) {
+            Ok(options) => golden::run(&options),
+            Err(error) => {
+                eprintln!("{}", error);
+                false
+            },
+        };
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     let event_loop = EventLoop::new();
-    let mut window = WindowBuilder::new()
-        .build(&event_loop)
-        .unwrap();
-    window.set_cursor_visible(false);
-    window.set_cursor_grab(true).unwrap();
+    let mut window = match WindowBuilder::new().build(&event_loop) {
+        Ok(window) => window,
+        Err(error) => {
+            eprintln!("Could not create a window: {}", error);
+            std::process::exit(1);
+        },
+    };
+    // the cursor is grabbed by the first update, if the platform allows it
+
+    let settings = settings::Settings::from_env();
 
     // wait until Future is ready
-    let mut state = pollster::block_on(State::new(&window));
+    let mut state = match pollster::block_on(State::new(&window, &settings)) {
+        Ok(state) => state,
+        Err(error) => {
+            eprintln!("Could not start the renderer: {}", error);
+            std::process::exit(1);
+        },
+    };
+    let mut frame_errors = error::FrameErrors::new();
+
+    let mut game_loop = game_loop::GameLoop::new(
+        recording::RecordingClock::new(),
+        settings.timestep(),
+        settings.max_simulation_steps,
+        settings.fps_limit,
+    );
 
     event_loop.run(move |event, _, control_flow| match event {
+        // the debug ui gets the first look at every event
+        ref event if state.ui_event(event) => {},
         Event::WindowEvent {
             ref event, // forward event
             window_id
@@ -29,23 +99,65 @@ fn main() {
             state.input(event, control_flow);
         }
         Event::RedrawRequested(_) => {
+            // while recording, every frame moves time by one video frame
+            game_loop.clock_mut().simulate(state.recording_frame_time());
+            // simulate at a fixed rate, independent of the frame rate
+            let tick = game_loop.tick();
+            for _ in 0..tick.steps {
+                state.fixed_update();
+            }
             // update the entire scene
-            state.update(&mut window);
-
-            // render the update
-            match state.render() {
-                Ok(_) => {},
-                // recreate swap_chain
-                Err(wgpu::SwapChainError::Lost) => state.resize(state.size),
-                // no memory left, so quit
-                Err(wgpu::SwapChainError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                // Outdated, Timeout should be handled by the nnext frame
-                Err(error) => eprintln!("{:?}", error),
+            state.update(&mut window, &tick);
+
+            // render the update, unless the device is gone
+            let recovery = if state.device_lost() {
+                log::error!("The graphics device was lost, rebuilding it");
+                Recovery::RebuildDevice
+            } else {
+                match state.render() {
+                    Ok(_) => {
+                        frame_errors.succeeded();
+                        return;
+                    },
+                    Err(error) => {
+                        let recovery = frame_errors.failed(&error);
+                        if recovery == Recovery::Exit {
+                            eprintln!("Could not render: {:?}", error);
+                        }
+                        recovery
+                    },
+                }
+            };
+            // waiting is done by the event loop, MainEventsCleared requests the next frame afterwards
+            let wait_for = |delay| ControlFlow::WaitUntil(std::time::Instant::now() + delay);
+            match recovery {
+                Recovery::Retry(delay) => *control_flow = wait_for(delay),
+                Recovery::RecreateSwapChain(delay) => {
+                    state.resize(state.size);
+                    *control_flow = wait_for(delay);
+                },
+                Recovery::RebuildDevice => {
+                    if let Err(error) = pollster::block_on(state.rebuild(&window)) {
+                        eprintln!("Could not recover from losing the graphics device: {}", error);
+                        *control_flow = ControlFlow::Exit;
+                    }
+                },
+                Recovery::Exit => *control_flow = ControlFlow::Exit,
             }
         },
-        Event::MainEventsCleared => {
-            // RedrawRequested will only trigger once, unless we manually request it
-            window.request_redraw();
+        // don't overwrite an exit request
+        Event::MainEventsCleared if *control_flow != ControlFlow::Exit => {
+            // the recording clock doesn't move while waiting, so the frame limit doesn't apply
+            let wait = game_loop.time_until_next_frame().filter(|_| !state.is_recording());
+            match wait {
+                // frame limit, wake up once the next frame is due
+                Some(wait) => *control_flow = ControlFlow::WaitUntil(std::time::Instant::now() + wait),
+                None => {
+                    *control_flow = ControlFlow::Poll;
+                    // RedrawRequested will only trigger once, unless we manually request it
+                    window.request_redraw();
+                }
+            }
         }
         // Discard all other Events
         _ => {}
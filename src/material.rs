@@ -0,0 +1,124 @@
+use wgpu::util::DeviceExt;
+
+/// Surface parameters besides the diffuse texture
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialParams {
+    pub specular: f32,
+    pub shininess: f32,
+    /// how much of the diffuse color is emitted as light, > 1 feeds into bloom
+    pub emissive: f32,
+    pub _padding: f32,
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        Self {
+            specular: 0.5,
+            shininess: 32.0,
+            emissive: 0.0,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Diffuse texture and parameters, bound as group 0 in every geometry pipeline
+pub struct Material {
+    pub name: String,
+    pub params: MaterialParams,
+    params_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        diffuse_texture: crate::texture::Texture,
+        params: MaterialParams,
+    ) -> Self {
+        let params_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(name),
+                contents: bytemuck::cast_slice(&[params]),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            }
+        );
+
+        // bind groups can be changed on the fly, as long as they're in the same layout
+        // the bind group keeps the texture alive, so it isn't stored separately
+        // every texture and sampler needs to be added to a bindgroup
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some(name),
+            }
+        );
+
+        Self {
+            name: name.to_string(),
+            params,
+            params_buffer,
+            bind_group,
+        }
+    }
+
+    /// Write changed params to the uniform buffer
+    pub fn update(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler {
+                            comparison: false,
+                            filtering: true,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("material_bind_group_layout"),
+            }
+        )
+    }
+}
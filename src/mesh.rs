@@ -0,0 +1,67 @@
+use wgpu::util::DeviceExt;
+
+/// Vertex and index buffers on the gpu, plus bounds on the cpu for culling
+pub struct Mesh {
+    pub name: String,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+    /// in model space
+    pub aabb: crate::bounds::Aabb,
+    /// in model space
+    pub sphere: crate::bounds::BoundingSphere,
+    /// copy of the buffer contents, for debug drawing
+    pub vertices: Vec<crate::vertex::Vertex>,
+    pub indices: Vec<u16>,
+}
+
+impl Mesh {
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[crate::vertex::Vertex],
+        indices: &[u16],
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Vertex Buffer", name)),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsage::VERTEX,
+            }
+        );
+
+        let index_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Index Buffer", name)),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsage::INDEX,
+            }
+        );
+
+        let positions = vertices.iter().map(|vertex| vertex.position());
+        // an empty mesh is just a point at the origin
+        let origin = cgmath::Point3::new(0.0, 0.0, 0.0);
+        let aabb = crate::bounds::Aabb::from_points(positions.clone())
+            .unwrap_or(crate::bounds::Aabb { min: origin, max: origin });
+        let sphere = crate::bounds::BoundingSphere::from_points(positions)
+            .unwrap_or(crate::bounds::BoundingSphere { center: origin, radius: 0.0 });
+
+        Self {
+            name: name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            aabb,
+            sphere,
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        }
+    }
+
+    /// Bind the buffers and draw the instances, instance data has to be bound to slot 1
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, instances: std::ops::Range<u32>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
+    }
+}
@@ -0,0 +1,285 @@
+use wgpu::util::DeviceExt;
+
+/// Characters that fit into the vertex buffer, the rest gets cut off
+const MAX_CHARS: usize = 4096;
+/// Two triangles per character
+const VERTICES_PER_CHAR: usize = 6;
+/// Screen pixels per font pixel
+const SCALE: f32 = 2.0;
+/// Space between the text and the edge of its background, in font pixels
+const PADDING: f32 = 2.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OverlayVertex {
+    // already in clip space
+    position: [f32; 2],
+    texture_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl OverlayVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Lines of bitmap font text on a dark box in the top left corner, drawn on top of everything
+pub struct TextOverlay {
+    font: crate::bitmap_font::BitmapFont,
+    // keeps the atlas alive
+    _font_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl TextOverlay {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let font = crate::bitmap_font::BitmapFont::new();
+
+        let font_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("bitmap_font"),
+                size: wgpu::Extent3d {
+                    width: font.width,
+                    height: font.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // coverage only
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            },
+            &font.pixels,
+        );
+        let font_view = font_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // keep the pixels sharp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = crate::post::create_input_bind_group_layout(device);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&font_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("overlay_bind_group"),
+        });
+
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Overlay Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("overlay.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Overlay Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Overlay Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[OverlayVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        );
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            size: (MAX_CHARS * VERTICES_PER_CHAR * std::mem::size_of::<OverlayVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            font,
+            _font_texture: font_texture,
+            bind_group,
+            pipeline,
+
+            vertex_buffer,
+            num_vertices: 0,
+        }
+    }
+
+    /// Replace the text, lines are separated by '\n'
+    /// the screen size is needed to place the text in pixels
+    pub fn set_text(&mut self, queue: &wgpu::Queue, screen_width: u32, screen_height: u32, text: &str) {
+        let cell_width = crate::bitmap_font::CELL_WIDTH as f32 * SCALE;
+        let cell_height = crate::bitmap_font::CELL_HEIGHT as f32 * SCALE;
+        let padding = PADDING * SCALE;
+
+        let lines: Vec<&str> = text.lines().collect();
+        let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+
+        let mut vertices = Vec::new();
+        if columns > 0 {
+            // background first, so the text ends up on top
+            self.push_quad(
+                &mut vertices,
+                screen_width,
+                screen_height,
+                [0.0, 0.0],
+                [columns as f32 * cell_width + 2.0 * padding, lines.len() as f32 * cell_height + 2.0 * padding],
+                self.font.solid_uv(),
+                BACKGROUND_COLOR,
+            );
+        }
+
+        'lines: for (row, line) in lines.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                if vertices.len() + VERTICES_PER_CHAR > MAX_CHARS * VERTICES_PER_CHAR {
+                    break 'lines;
+                }
+                if c == ' ' {
+                    continue;
+                }
+                self.push_quad(
+                    &mut vertices,
+                    screen_width,
+                    screen_height,
+                    [padding + column as f32 * cell_width, padding + row as f32 * cell_height],
+                    [cell_width, cell_height],
+                    self.font.uv(c),
+                    TEXT_COLOR,
+                );
+            }
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    /// Draw the text on top of whatever is in target
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        if self.num_vertices == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+
+    /// Two triangles covering a rectangle given in pixels from the top left corner
+    #[allow(clippy::too_many_arguments)]
+    fn push_quad(
+        &self,
+        vertices: &mut Vec<OverlayVertex>,
+        screen_width: u32,
+        screen_height: u32,
+        position: [f32; 2],
+        size: [f32; 2],
+        uv: [f32; 4],
+        color: [f32; 4],
+    ) {
+        // pixels -> clip space, y points up in clip space
+        let to_clip = |x: f32, y: f32| [
+            x / screen_width as f32 * 2.0 - 1.0,
+            1.0 - y / screen_height as f32 * 2.0,
+        ];
+        let [u_min, v_min, u_max, v_max] = uv;
+        let left_top = OverlayVertex {
+            position: to_clip(position[0], position[1]),
+            texture_coords: [u_min, v_min],
+            color,
+        };
+        let right_top = OverlayVertex {
+            position: to_clip(position[0] + size[0], position[1]),
+            texture_coords: [u_max, v_min],
+            color,
+        };
+        let left_bottom = OverlayVertex {
+            position: to_clip(position[0], position[1] + size[1]),
+            texture_coords: [u_min, v_max],
+            color,
+        };
+        let right_bottom = OverlayVertex {
+            position: to_clip(position[0] + size[0], position[1] + size[1]),
+            texture_coords: [u_max, v_max],
+            color,
+        };
+
+        vertices.extend_from_slice(&[left_top, left_bottom, right_bottom, left_top, right_bottom, right_top]);
+    }
+}
@@ -0,0 +1,35 @@
+// Vertex shader
+struct VertexInput {
+	[[location(0)]] position: vec2<f32>;
+	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn main(in: VertexInput) -> VertexOutput {
+	var out: VertexOutput;
+	// positions are already in clip space
+	out.clip_coordinate = vec4<f32>(in.position, 0.0, 1.0);
+	out.texture_coords = in.texture_coords;
+	out.color = in.color;
+	return out;
+}
+
+// Fragment shader
+// single channel glyph coverage
+[[group(0), binding(0)]]
+var t_font: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_font: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let coverage = textureSample(t_font, s_font, in.texture_coords).r;
+	return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
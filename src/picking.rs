@@ -0,0 +1,581 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4};
+
+/// Half line, the direction doesn't have to be normalized
+/// distances are measured in multiples of the direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Ray from the near to the far plane through a pixel, position is in physical pixels from
+    /// the top left corner, None if view_projection can't be inverted
+    pub fn from_screen(position: [f32; 2], width: u32, height: u32, view_projection: &Matrix4<f32>) -> Option<Self> {
+        let inverse = view_projection.invert()?;
+        // pixel centers, y points down on the screen but up in clip space
+        let x = (position[0] + 0.5) / width as f32 * 2.0 - 1.0;
+        let y = 1.0 - (position[1] + 0.5) / height as f32 * 2.0;
+        // wgpu clip space depth goes from 0 (near) to 1 (far)
+        let unproject = |z: f32| {
+            let point = inverse * Vector4::new(x, y, z, 1.0);
+            Point3::from_homogeneous(point)
+        };
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        Some(Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Same ray in another space, distances stay the same because the direction isn't normalized
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: matrix.transform_point(self.origin),
+            direction: matrix.transform_vector(self.direction),
+        }
+    }
+
+    /// Distance to the plane through point, None if the ray is parallel to it or points away
+    pub fn intersect_plane(&self, point: Point3<f32>, normal: Vector3<f32>) -> Option<f32> {
+        let denominator = normal.dot(self.direction);
+        if denominator.abs() < 1e-6 {
+            return None;
+        }
+        let distance = normal.dot(point - self.origin) / denominator;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    /// Distance to where the ray enters the box, 0 if it starts inside
+    pub fn intersect_aabb(&self, aabb: &crate::bounds::Aabb) -> Option<f32> {
+        // slab test, division by 0 gives infinities, which compare the right way
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN for a ray inside a slab plane, max/min ignore it
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    /// Distance to the triangle, both sides count, Möller-Trumbore
+    pub fn intersect_triangle(&self, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<f32> {
+        const EPSILON: f32 = 1e-7;
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+        let p = self.direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+        // parallel to the triangle
+        if determinant.abs() < EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+
+        let to_origin = self.origin - a;
+        let u = to_origin.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = to_origin.cross(edge_1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge_2.dot(q) * inverse;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    /// Closest triangle of an indexed triangle list
+    pub fn intersect_triangles(&self, positions: &[Point3<f32>], indices: &[u16]) -> Option<f32> {
+        indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let a = *positions.get(triangle[0] as usize)?;
+                let b = *positions.get(triangle[1] as usize)?;
+                let c = *positions.get(triangle[2] as usize)?;
+                self.intersect_triangle(a, b, c)
+            })
+            .fold(None, |closest: Option<f32>, distance| {
+                Some(closest.map_or(distance, |closest| closest.min(distance)))
+            })
+    }
+}
+
+/// Closest mesh instance hit by a ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub node: crate::scene::NodeId,
+    pub distance: f32,
+    pub point: Point3<f32>,
+}
+
+/// Cast a world space ray against every mesh of the scene, boxes first, then triangles
+pub fn raycast(scene: &crate::scene::Scene, meshes: &[crate::mesh::Mesh], ray: &Ray) -> Option<RayHit> {
+    // box hits, closest first, so most triangle tests can be skipped
+    let mut candidates: Vec<(f32, crate::scene::NodeId, &crate::mesh::Mesh, Matrix4<f32>)> = scene
+        .mesh_instances()
+        .filter_map(|(node, attachment, model)| {
+            let mesh = meshes.get(attachment.mesh)?;
+            let distance = ray.intersect_aabb(&mesh.aabb.transformed(&model))?;
+            Some((distance, node, mesh, model))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut closest: Option<RayHit> = None;
+    for (box_distance, node, mesh, model) in candidates {
+        if closest.is_some_and(|hit| hit.distance < box_distance) {
+            break;
+        }
+        let inverse = match model.invert() {
+            Some(inverse) => inverse,
+            None => continue,
+        };
+        let positions: Vec<Point3<f32>> = mesh.vertices.iter().map(|vertex| vertex.position()).collect();
+        if let Some(distance) = ray.transformed(&inverse).intersect_triangles(&positions, &mesh.indices) {
+            if closest.is_none_or(|hit| distance < hit.distance) {
+                closest = Some(RayHit {
+                    node,
+                    distance,
+                    point: ray.at(distance),
+                });
+            }
+        }
+    }
+    closest
+}
+
+/// Reads back which instance covers a pixel, by drawing instance ids into an integer target
+/// a pick takes a few frames, poll it every frame until it's done
+pub struct IdPicker {
+    pipeline: wgpu::RenderPipeline,
+    id_texture: wgpu::Texture,
+    id_view: wgpu::TextureView,
+    depth_texture: crate::texture::Texture,
+    readback_buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+
+    // nodes of the instance buffer at the time of the pick, index = id - 1
+    nodes: Vec<crate::scene::NodeId>,
+    // pixel of the pick, drawn this frame, mapping starts after the submit
+    position: [u32; 2],
+    recorded: bool,
+    pending: Option<crate::readback::PendingMap>,
+}
+
+impl IdPicker {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Picking Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("picking.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Picking Pipeline Layout"),
+                bind_group_layouts: &[uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Picking Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[crate::vertex::Vertex::desc(), crate::instance::InstanceRaw::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    // integer targets can't be blended
+                    targets: &[wgpu::ColorTargetState {
+                        format: Self::FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+            }
+        );
+
+        let (id_texture, id_view) = Self::create_id_texture(device, sc_desc);
+        let depth_texture = crate::texture::Texture::create_depth_texture(device, sc_desc, 1, "pick_depth_texture");
+        // a single texel, but copies work in whole aligned rows
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pick Readback Buffer"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            id_texture,
+            id_view,
+            depth_texture,
+            readback_buffer,
+            width: sc_desc.width,
+            height: sc_desc.height,
+
+            nodes: Vec::new(),
+            position: [0, 0],
+            recorded: false,
+            pending: None,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        let (id_texture, id_view) = Self::create_id_texture(device, sc_desc);
+        self.id_texture = id_texture;
+        self.id_view = id_view;
+        self.depth_texture = crate::texture::Texture::create_depth_texture(device, sc_desc, 1, "pick_depth_texture");
+        self.width = sc_desc.width;
+        self.height = sc_desc.height;
+    }
+
+    /// True while an earlier pick hasn't been read back yet
+    pub fn is_busy(&self) -> bool {
+        self.recorded || self.pending.is_some()
+    }
+
+    /// Pick the pixel in the next pass, nodes are the nodes of the instance buffer in order
+    /// false if a pick is still in flight or position is outside of the screen
+    pub fn request(&mut self, position: [u32; 2], nodes: Vec<crate::scene::NodeId>) -> bool {
+        if self.is_busy() || position[0] >= self.width || position[1] >= self.height {
+            return false;
+        }
+        self.nodes = nodes;
+        self.position = position;
+        self.recorded = true;
+        true
+    }
+
+    /// Start drawing ids, only the picked pixel is rasterized, None without a request
+    /// the caller binds the camera (0), draws the scene and calls end_pass after dropping the pass
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> Option<wgpu::RenderPass<'a>> {
+        if !self.recorded {
+            return None;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Picking Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: &self.id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // 0 => nothing
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_scissor_rect(self.position[0], self.position[1], 1, 1);
+        Some(render_pass)
+    }
+
+    /// Copy the picked texel to the readback buffer
+    pub fn end_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recorded {
+            return;
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.id_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: self.position[0], y: self.position[1], z: 0 },
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        );
+    }
+
+    /// Start reading the pick back, has to be called after the encoder was submitted
+    pub fn after_submit(&mut self) {
+        if self.recorded {
+            self.recorded = false;
+            self.pending = Some(crate::readback::PendingMap::new(
+                self.readback_buffer.slice(..),
+                wgpu::MapMode::Read,
+            ));
+        }
+    }
+
+    /// Some once the pick is done, with the node under the pixel if there is one, never blocks
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Option<crate::scene::NodeId>> {
+        device.poll(wgpu::Maintain::Poll);
+
+        let result = self.pending.as_mut()?.poll()?;
+        self.pending = None;
+        if result.is_err() {
+            log::warn!("Could not read back the picked id");
+            return Some(None);
+        }
+
+        let id = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            bytemuck::cast_slice::<u8, u32>(&data)[0]
+        };
+        self.readback_buffer.unmap();
+        // ids are instance index + 1
+        Some(id.checked_sub(1).and_then(|index| self.nodes.get(index as usize).copied()))
+    }
+
+    fn create_id_texture(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Pick Id Texture"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            // COPY_SRC: the picked texel is copied to the readback buffer
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounds::Aabb;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn ray(origin: (f32, f32, f32), direction: (f32, f32, f32)) -> Ray {
+        Ray {
+            origin: Point3::new(origin.0, origin.1, origin.2),
+            direction: Vector3::new(direction.0, direction.1, direction.2),
+        }
+    }
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: Point3::new(-1.0, -1.0, -1.0),
+            max: Point3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Triangle in the z = 0 plane, around the origin
+    fn triangle() -> [Point3<f32>; 3] {
+        [Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0)]
+    }
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        match actual {
+            Some(actual) => assert!((actual - expected).abs() < EPSILON, "{} != {}", actual, expected),
+            None => panic!("no hit, expected one at {}", expected),
+        }
+    }
+
+    /// Camera at z = 5 looking at the origin, 90° field of view, near 1 and far 100
+    fn view_projection() -> Matrix4<f32> {
+        let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let projection = cgmath::perspective(cgmath::Deg(90.0), 1.0, 1.0, 100.0);
+        crate::camera::OPENGL_TO_WGPU_MATRIX * projection * view
+    }
+
+    #[test]
+    fn center_pixel_looks_forward() {
+        // odd size, so there is a pixel right in the middle
+        let ray = Ray::from_screen([100.0, 100.0], 201, 201, &view_projection()).unwrap();
+        assert!((ray.origin - Point3::new(0.0, 0.0, 4.0)).magnitude() < EPSILON, "{:?}", ray.origin);
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).magnitude() < EPSILON, "{:?}", ray.direction);
+    }
+
+    #[test]
+    fn corner_pixels_point_outwards() {
+        let top_left = Ray::from_screen([0.0, 0.0], 201, 201, &view_projection()).unwrap();
+        assert!(top_left.direction.x < 0.0 && top_left.direction.y > 0.0);
+        // the field of view is 90°, so the edge of the screen is about 45° off
+        let edge = 1.0 - 1.0 / 201.0;
+        let expected = Vector3::new(-edge, edge, -1.0).normalize();
+        assert!((top_left.direction - expected).magnitude() < EPSILON, "{:?}", top_left.direction);
+        assert!((top_left.direction.magnitude() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn singular_matrix_has_no_ray() {
+        assert_eq!(Ray::from_screen([0.0, 0.0], 10, 10, &Matrix4::from_scale(0.0)), None);
+    }
+
+    #[test]
+    fn aabb_hits_and_misses() {
+        let aabb = unit_box();
+        assert_close(ray((0.0, 0.0, 5.0), (0.0, 0.0, -1.0)).intersect_aabb(&aabb), 4.0);
+        // diagonal, enters through the corner region
+        assert_close(ray((-3.0, -3.0, 0.0), (1.0, 1.0, 0.0)).intersect_aabb(&aabb), 2.0);
+        // distances are in multiples of the direction
+        assert_close(ray((0.0, 0.0, 5.0), (0.0, 0.0, -2.0)).intersect_aabb(&aabb), 2.0);
+
+        assert_eq!(ray((0.0, 2.0, 5.0), (0.0, 0.0, -1.0)).intersect_aabb(&aabb), None);
+        assert_eq!(ray((0.0, 0.0, 5.0), (0.0, 0.0, 1.0)).intersect_aabb(&aabb), None);
+        assert_eq!(ray((-3.0, 0.0, 0.0), (1.0, 3.0, 0.0)).intersect_aabb(&aabb), None);
+    }
+
+    #[test]
+    fn aabb_from_inside() {
+        assert_close(ray((0.0, 0.0, 0.0), (1.0, 0.0, 0.0)).intersect_aabb(&unit_box()), 0.0);
+        assert_close(ray((0.5, -0.5, 0.9), (0.3, 0.1, -1.0)).intersect_aabb(&unit_box()), 0.0);
+    }
+
+    #[test]
+    fn aabb_along_a_face() {
+        // the ray lies in the plane of the top face
+        assert!(ray((-3.0, 1.0, 0.0), (1.0, 0.0, 0.0)).intersect_aabb(&unit_box()).is_some());
+        assert_eq!(ray((-3.0, 1.5, 0.0), (1.0, 0.0, 0.0)).intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn triangle_hit_from_both_sides() {
+        let [a, b, c] = triangle();
+        assert_close(ray((0.0, 0.0, 3.0), (0.0, 0.0, -1.0)).intersect_triangle(a, b, c), 3.0);
+        assert_close(ray((0.0, 0.0, -2.0), (0.0, 0.0, 1.0)).intersect_triangle(a, b, c), 2.0);
+        assert_close(ray((0.0, 0.0, 3.0), (0.0, 0.0, -0.5)).intersect_triangle(a, b, c), 6.0);
+    }
+
+    #[test]
+    fn triangle_misses() {
+        let [a, b, c] = triangle();
+        // inside the bounding square of the triangle, but beyond its slanted edge
+        assert_eq!(ray((0.9, 0.9, 3.0), (0.0, 0.0, -1.0)).intersect_triangle(a, b, c), None);
+        assert_eq!(ray((0.0, -1.5, 3.0), (0.0, 0.0, -1.0)).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn triangle_parallel() {
+        let [a, b, c] = triangle();
+        assert_eq!(ray((-3.0, 0.0, 0.0), (1.0, 0.0, 0.0)).intersect_triangle(a, b, c), None);
+        assert_eq!(ray((-3.0, 0.0, 1.0), (1.0, 0.0, 0.0)).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn triangle_behind_the_origin() {
+        let [a, b, c] = triangle();
+        assert_eq!(ray((0.0, 0.0, 3.0), (0.0, 0.0, 1.0)).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn closest_of_many_triangles() {
+        let positions = [
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, -1.0, 2.0),
+            Point3::new(1.0, -1.0, 2.0),
+            Point3::new(0.0, 1.0, 2.0),
+        ];
+        let ray = ray((0.0, 0.0, 5.0), (0.0, 0.0, -1.0));
+        assert_close(ray.intersect_triangles(&positions, &[0, 1, 2, 3, 4, 5]), 3.0);
+        assert_close(ray.intersect_triangles(&positions, &[0, 1, 2]), 5.0);
+        // out of range indices are skipped
+        assert_close(ray.intersect_triangles(&positions, &[0, 1, 9, 3, 4, 5]), 3.0);
+        assert_eq!(ray.intersect_triangles(&positions, &[]), None);
+    }
+
+    #[test]
+    fn transformed_keeps_the_distance() {
+        let [a, b, c] = triangle();
+        let model = Matrix4::from_translation(Vector3::new(1.0, 2.0, -3.0))
+            * Matrix4::from_angle_y(cgmath::Deg(30.0))
+            * Matrix4::from_scale(2.0);
+        let world = ray((1.2, 2.2, 5.0), (0.0, 0.0, -1.0));
+        let world_distance = world.intersect_triangle(
+            model.transform_point(a),
+            model.transform_point(b),
+            model.transform_point(c),
+        );
+
+        let local = world.transformed(&model.invert().unwrap());
+        let local_distance = local.intersect_triangle(a, b, c);
+        assert!(world_distance.is_some());
+        assert_close(local_distance, world_distance.unwrap());
+        // and the hit is the same point
+        let hit = model.transform_point(local.at(local_distance.unwrap()));
+        assert!((hit - world.at(world_distance.unwrap())).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn plane_intersection() {
+        let ray = ray((0.0, 3.0, 0.0), (0.0, -1.0, 0.0));
+        assert_close(ray.intersect_plane(Point3::new(5.0, 0.0, 5.0), Vector3::unit_y()), 3.0);
+        assert_eq!(ray.intersect_plane(Point3::new(0.0, 4.0, 0.0), Vector3::unit_y()), None);
+        assert_eq!(ray.intersect_plane(Point3::new(0.0, 0.0, 0.0), Vector3::unit_x()), None);
+    }
+}
@@ -0,0 +1,45 @@
+// Vertex shader
+[[block]]
+struct Uniform {
+	view_projection: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> uniform: Uniform;
+
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+};
+
+struct InstanceInput {
+	[[location(5)]] model_0: vec4<f32>;
+	[[location(6)]] model_1: vec4<f32>;
+	[[location(7)]] model_2: vec4<f32>;
+	[[location(8)]] model_3: vec4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	// integers can't be interpolated
+	[[location(0), interpolate(flat)]] id: u32;
+};
+
+[[stage(vertex)]]
+fn main(
+	model: VertexInput,
+	instance: InstanceInput,
+	[[builtin(instance_index)]] instance_index: u32
+) -> VertexOutput {
+	let model_matrix = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+	var out: VertexOutput;
+	out.clip_coordinate = uniform.view_projection * model_matrix * vec4<f32>(model.position, 1.0);
+	// 0 is left for the background
+	out.id = instance_index + 1u;
+	return out;
+}
+
+// Fragment shader
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] u32 {
+	return in.id;
+}
@@ -0,0 +1,584 @@
+use wgpu::util::DeviceExt;
+
+/// Format of the scene target and all intermediate post-processing targets
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Number of slices (and texels per axis) of the color grading LUT
+pub const LUT_SIZE: u32 = 16;
+
+const FULLSCREEN_SHADER: &str = include_str!("post_fullscreen.wgsl");
+
+/// A single fullscreen pass of the post-processing chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostEffect {
+    /// HDR -> LDR with the ACES filmic curve
+    ToneMapping { exposure: f32 },
+    /// only needed, if the swap_chain isn't sRGB
+    Gamma { gamma: f32 },
+    Vignette { intensity: f32, radius: f32, softness: f32 },
+    /// blend between the input and the color grading LUT
+    ColorGrading { intensity: f32 },
+    Fxaa,
+}
+
+impl PostEffect {
+    /// Parse an effect name with default parameters, e.g. from the POST_EFFECTS env variable
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tonemap" | "tone_mapping" => Some(Self::ToneMapping { exposure: 1.0 }),
+            "gamma" => Some(Self::Gamma { gamma: 2.2 }),
+            "vignette" => Some(Self::Vignette { intensity: 0.5, radius: 0.75, softness: 0.45 }),
+            "grading" | "color_grading" => Some(Self::ColorGrading { intensity: 1.0 }),
+            "fxaa" => Some(Self::Fxaa),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ToneMapping { .. } => "Tone Mapping",
+            Self::Gamma { .. } => "Gamma",
+            Self::Vignette { .. } => "Vignette",
+            Self::ColorGrading { .. } => "Color Grading",
+            Self::Fxaa => "FXAA",
+        }
+    }
+
+    fn shader_source(&self) -> &'static str {
+        match self {
+            Self::ToneMapping { .. } => include_str!("post_tone_mapping.wgsl"),
+            Self::Gamma { .. } => include_str!("post_gamma.wgsl"),
+            Self::Vignette { .. } => include_str!("post_vignette.wgsl"),
+            Self::ColorGrading { .. } => include_str!("post_color_grading.wgsl"),
+            Self::Fxaa => include_str!("post_fxaa.wgsl"),
+        }
+    }
+
+    /// Contents of the uniform buffer, every effect uses 16 bytes
+    /// layout has to match the struct in the effects shader
+    fn uniform(&self, width: u32, height: u32) -> [f32; 4] {
+        match *self {
+            Self::ToneMapping { exposure } => [exposure, 0.0, 0.0, 0.0],
+            Self::Gamma { gamma } => [gamma, 0.0, 0.0, 0.0],
+            Self::Vignette { intensity, radius, softness } => [intensity, radius, softness, 0.0],
+            Self::ColorGrading { intensity } => [intensity, LUT_SIZE as f32, 0.0, 0.0],
+            Self::Fxaa => [1.0 / width as f32, 1.0 / height as f32, 0.0, 0.0],
+        }
+    }
+}
+
+struct PostPass {
+    effect: PostEffect,
+    enabled: bool,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Owns the HDR scene target and runs it through bloom and the chain of effects
+/// scene -> bloom (+=) -> effect 0 -> ping -> effect 1 -> pong -> ... -> blit -> swap_chain
+pub struct PostProcessor {
+    width: u32,
+    height: u32,
+
+    // the scene gets rendered into this
+    scene_target: crate::texture::Texture,
+    scene_bind_group: wgpu::BindGroup,
+    // intermediate targets, effects alternate between them
+    ping_pong: [crate::texture::Texture; 2],
+    ping_pong_bind_groups: [wgpu::BindGroup; 2],
+
+    input_bind_group_layout: wgpu::BindGroupLayout,
+    // bound by the color grading passes, only kept alive here
+    #[allow(dead_code)]
+    lut: crate::texture::Texture,
+
+    // runs on the HDR scene, before the chain (and therefore before tone mapping)
+    bloom: crate::bloom::Bloom,
+    passes: Vec<PostPass>,
+    blit_pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        effects: &[PostEffect],
+        lut_image: Option<&image::DynamicImage>,
+        bloom_settings: crate::bloom::BloomSettings,
+    ) -> Self {
+        let input_bind_group_layout = create_input_bind_group_layout(device);
+
+        let scene_target = Self::create_target(device, sc_desc, "scene_target");
+        let scene_bind_group = create_input_bind_group(device, &input_bind_group_layout, &scene_target);
+        let ping_pong = [
+            Self::create_target(device, sc_desc, "post_ping"),
+            Self::create_target(device, sc_desc, "post_pong"),
+        ];
+        let ping_pong_bind_groups = [
+            create_input_bind_group(device, &input_bind_group_layout, &ping_pong[0]),
+            create_input_bind_group(device, &input_bind_group_layout, &ping_pong[1]),
+        ];
+
+        let lut = match lut_image {
+            Some(img) => Self::create_lut(device, queue, &img.to_rgba8()),
+            None => Self::create_lut(device, queue, &Self::identity_lut()),
+        };
+
+        let bloom = crate::bloom::Bloom::new(device, &input_bind_group_layout, sc_desc, bloom_settings);
+
+        let passes = effects
+            .iter()
+            .map(|effect| Self::create_pass(device, &input_bind_group_layout, &lut, *effect, sc_desc))
+            .collect();
+
+        let blit_pipeline = create_fullscreen_pipeline(
+            device,
+            "Post Blit",
+            include_str!("post_blit.wgsl"),
+            &[&input_bind_group_layout],
+            sc_desc.format,
+            wgpu::BlendState::REPLACE,
+        );
+
+        Self {
+            width: sc_desc.width,
+            height: sc_desc.height,
+
+            scene_target,
+            scene_bind_group,
+            ping_pong,
+            ping_pong_bind_groups,
+
+            input_bind_group_layout,
+            lut,
+
+            bloom,
+            passes,
+            blit_pipeline,
+        }
+    }
+
+    /// View, which the scene has to be rendered (or resolved) into
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene_target.view
+    }
+
+    pub fn bloom_settings(&self) -> &crate::bloom::BloomSettings {
+        &self.bloom.settings
+    }
+
+    pub fn bloom_settings_mut(&mut self) -> &mut crate::bloom::BloomSettings {
+        &mut self.bloom.settings
+    }
+
+    pub fn effects(&self) -> impl Iterator<Item = (&PostEffect, bool)> {
+        self.passes.iter().map(|pass| (&pass.effect, pass.enabled))
+    }
+
+    /// Change the parameters of the effect at the given position in the chain
+    /// the kind of effect can't be changed, as it has its own pipeline
+    pub fn set_effect(&mut self, index: usize, effect: PostEffect) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            if std::mem::discriminant(&pass.effect) == std::mem::discriminant(&effect) {
+                pass.effect = effect;
+            }
+        }
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            pass.enabled = enabled;
+        }
+    }
+
+    /// Targets always have to match the size of the swap_chain
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.width = sc_desc.width;
+        self.height = sc_desc.height;
+
+        self.scene_target = Self::create_target(device, sc_desc, "scene_target");
+        self.scene_bind_group = create_input_bind_group(device, &self.input_bind_group_layout, &self.scene_target);
+        self.ping_pong = [
+            Self::create_target(device, sc_desc, "post_ping"),
+            Self::create_target(device, sc_desc, "post_pong"),
+        ];
+        self.ping_pong_bind_groups = [
+            create_input_bind_group(device, &self.input_bind_group_layout, &self.ping_pong[0]),
+            create_input_bind_group(device, &self.input_bind_group_layout, &self.ping_pong[1]),
+        ];
+        self.bloom.resize(device, &self.input_bind_group_layout, sc_desc);
+    }
+
+    /// Write the current effect parameters to their uniform buffers
+    pub fn update(&self, queue: &wgpu::Queue) {
+        self.bloom.update(queue);
+        for pass in &self.passes {
+            let uniform = pass.effect.uniform(self.width, self.height);
+            queue.write_buffer(&pass.uniform_buffer, 0, bytemuck::cast_slice(&uniform));
+        }
+    }
+
+    /// Run bloom and all enabled effects on the scene target and blit the result to output
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        self.bloom.render(encoder, &self.scene_bind_group, &self.scene_target.view);
+
+        let mut source = &self.scene_bind_group;
+        let mut target = 0;
+
+        for pass in self.passes.iter().filter(|pass| pass.enabled) {
+            draw_fullscreen(
+                encoder,
+                pass.effect.name(),
+                &pass.pipeline,
+                &[source, &pass.bind_group],
+                &self.ping_pong[target].view,
+                // every pixel gets overwritten anyways
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            );
+            // the output of this pass is the input of the next one
+            source = &self.ping_pong_bind_groups[target];
+            target = 1 - target;
+        }
+
+        draw_fullscreen(
+            encoder,
+            "Post Blit",
+            &self.blit_pipeline,
+            &[source],
+            output,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        );
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        label: &str,
+    ) -> crate::texture::Texture {
+        crate::texture::Texture::create_render_target(device, sc_desc.width, sc_desc.height, HDR_FORMAT, 1, label)
+    }
+
+    fn create_pass(
+        device: &wgpu::Device,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        lut: &crate::texture::Texture,
+        effect: PostEffect,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> PostPass {
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(effect.name()),
+                contents: bytemuck::cast_slice(&effect.uniform(sc_desc.width, sc_desc.height)),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            }
+        );
+
+        let mut layout_entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ];
+
+        // color grading additionally needs the LUT
+        if let PostEffect::ColorGrading { .. } = effect {
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    comparison: false,
+                    filtering: true,
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&lut.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&lut.sampler),
+            });
+        }
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &layout_entries,
+            label: Some("post_effect_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: Some("post_effect_bind_group"),
+        });
+
+        let pipeline = create_fullscreen_pipeline(
+            device,
+            effect.name(),
+            effect.shader_source(),
+            &[input_bind_group_layout, &bind_group_layout],
+            HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+
+        PostPass {
+            effect,
+            enabled: true,
+            pipeline,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    /// LUT, which maps every color to itself
+    fn identity_lut() -> image::RgbaImage {
+        let max = (LUT_SIZE - 1) as f32;
+        image::RgbaImage::from_fn(LUT_SIZE * LUT_SIZE, LUT_SIZE, |x, y| {
+            let r = (x % LUT_SIZE) as f32 / max;
+            let g = y as f32 / max;
+            let b = (x / LUT_SIZE) as f32 / max;
+            image::Rgba([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8, 255])
+        })
+    }
+
+    fn create_lut(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: &image::RgbaImage,
+    ) -> crate::texture::Texture {
+        let dimensions = rgba.dimensions();
+        if dimensions != (LUT_SIZE * LUT_SIZE, LUT_SIZE) {
+            log::warn!(
+                "Color grading LUT should be {}x{}, but is {}x{}",
+                LUT_SIZE * LUT_SIZE, LUT_SIZE, dimensions.0, dimensions.1
+            );
+        }
+
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("color_grading_lut"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // LUT values are already linear
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
+                rows_per_image: std::num::NonZeroU32::new(dimensions.1),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // interpolate within a slice, slices are blended in the shader
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        crate::texture::Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+/// Layout of group 0 in every fullscreen pass: input texture and sampler
+pub fn create_input_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(
+        &wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("post_input_bind_group_layout"),
+        }
+    )
+}
+
+/// Bind group to sample texture in a fullscreen pass
+pub fn create_input_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &crate::texture::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                }
+            ],
+            label: Some("post_input_bind_group"),
+        }
+    )
+}
+
+/// Fullscreen pipeline for the given fragment shader
+pub fn create_fullscreen_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    fragment_source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+) -> wgpu::RenderPipeline {
+    // WGSL has no includes, so glue the shared vertex shader in front
+    let shader = device.create_shader_module(
+        &wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            flags: wgpu::ShaderFlags::all(),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", FULLSCREEN_SHADER, fragment_source).into()),
+        }
+    );
+
+    let layout = device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        }
+    );
+
+    device.create_render_pipeline(
+        &wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "main",
+                // vertices are generated in the shader
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // the fullscreen triangle is always visible
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            }
+        }
+    )
+}
+
+/// Draw a fullscreen triangle with the given pipeline into target
+pub fn draw_fullscreen(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+    target: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) {
+    let mut render_pass = encoder.begin_render_pass(
+        &wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: true,
+                    }
+                }
+            ],
+            depth_stencil_attachment: None,
+        });
+
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    // fullscreen triangle
+    render_pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,10 @@
+// Copy the result of the chain to the swap_chain
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return textureSample(t_input, s_input, in.texture_coords);
+}
@@ -0,0 +1,43 @@
+// Look up every color in a 3D LUT, which is stored as a 2D strip
+// of lut_size slices with lut_size x lut_size texels each (blue selects the slice)
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct ColorGrading {
+	intensity: f32;
+	lut_size: f32;
+	pad0: f32;
+	pad1: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: ColorGrading;
+[[group(1), binding(1)]]
+var t_lut: texture_2d<f32>;
+[[group(1), binding(2)]]
+var s_lut: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let input = textureSample(t_input, s_input, in.texture_coords);
+	let color = clamp(input.rgb, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0));
+
+	let size = params.lut_size;
+	let blue = color.b * (size - 1.0);
+	let slice0 = floor(blue);
+	let slice1 = min(slice0 + 1.0, size - 1.0);
+	// sample texel centers, so neighbouring slices don't bleed into each other
+	let x = (color.r * (size - 1.0) + 0.5) / (size * size);
+	let y = (color.g * (size - 1.0) + 0.5) / size;
+
+	let graded0 = textureSample(t_lut, s_lut, vec2<f32>(x + slice0 / size, y)).rgb;
+	let graded1 = textureSample(t_lut, s_lut, vec2<f32>(x + slice1 / size, y)).rgb;
+	let t = blue - slice0;
+	let graded = mix(graded0, graded1, vec3<f32>(t, t, t));
+
+	let i = params.intensity;
+	return vec4<f32>(mix(color, graded, vec3<f32>(i, i, i)), input.a);
+}
@@ -0,0 +1,18 @@
+// Shared vertex shader of all fullscreen passes
+// Gets prepended to every fragment shader of a fullscreen pass
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+};
+
+// one triangle, which covers the whole screen
+// no vertex buffer needed, everything is derived from the index
+[[stage(vertex)]]
+fn main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	var out: VertexOutput;
+	out.clip_coordinate = vec4<f32>(uv.x * 2.0 - 1.0, uv.y * 2.0 - 1.0, 0.0, 1.0);
+	// texture coordinates start top left
+	out.texture_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+	return out;
+}
@@ -0,0 +1,64 @@
+// Fast approximate anti aliasing, should run after tone mapping
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Fxaa {
+	// size of one texel in texture coordinates
+	texel_size: vec2<f32>;
+	pad0: f32;
+	pad1: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Fxaa;
+
+fn luma(color: vec3<f32>) -> f32 {
+	return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn sample_rgb(uv: vec2<f32>) -> vec3<f32> {
+	return textureSample(t_input, s_input, uv).rgb;
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let uv = in.texture_coords;
+	let texel = params.texel_size;
+
+	let luma_nw = luma(sample_rgb(uv + vec2<f32>(-1.0, -1.0) * texel));
+	let luma_ne = luma(sample_rgb(uv + vec2<f32>(1.0, -1.0) * texel));
+	let luma_sw = luma(sample_rgb(uv + vec2<f32>(-1.0, 1.0) * texel));
+	let luma_se = luma(sample_rgb(uv + vec2<f32>(1.0, 1.0) * texel));
+	let center = textureSample(t_input, s_input, uv);
+	let luma_m = luma(center.rgb);
+
+	let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	// direction along the edge
+	var dir: vec2<f32> = vec2<f32>(
+		(luma_sw + luma_se) - (luma_nw + luma_ne),
+		(luma_nw + luma_sw) - (luma_ne + luma_se)
+	);
+	let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * (0.25 / 8.0), 1.0 / 128.0);
+	let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+	// search at most 8 texels along the edge
+	dir = clamp(dir * rcp_dir_min, vec2<f32>(-8.0, -8.0), vec2<f32>(8.0, 8.0)) * texel;
+
+	let rgb_a = 0.5 * (
+		sample_rgb(uv + dir * (1.0 / 3.0 - 0.5)) +
+		sample_rgb(uv + dir * (2.0 / 3.0 - 0.5)));
+	let rgb_b = rgb_a * 0.5 + 0.25 * (
+		sample_rgb(uv + dir * -0.5) +
+		sample_rgb(uv + dir * 0.5));
+
+	// rgb_b went too far, if it left the local luma range
+	let luma_b = luma(rgb_b);
+	if (luma_b < luma_min || luma_b > luma_max) {
+		return vec4<f32>(rgb_a, center.a);
+	}
+	return vec4<f32>(rgb_b, center.a);
+}
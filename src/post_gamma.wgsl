@@ -0,0 +1,23 @@
+// Gamma correction, only useful if the swap_chain format isn't sRGB
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Gamma {
+	gamma: f32;
+	pad0: f32;
+	pad1: f32;
+	pad2: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Gamma;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let color = textureSample(t_input, s_input, in.texture_coords);
+	let exponent = 1.0 / params.gamma;
+	return vec4<f32>(pow(color.rgb, vec3<f32>(exponent, exponent, exponent)), color.a);
+}
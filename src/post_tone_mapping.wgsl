@@ -0,0 +1,29 @@
+// Map HDR colors into [0, 1]
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct ToneMapping {
+	exposure: f32;
+	pad0: f32;
+	pad1: f32;
+	pad2: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: ToneMapping;
+
+// ACES filmic curve, approximation by Krzysztof Narkowicz
+fn aces(x: vec3<f32>) -> vec3<f32> {
+	let a = x * (2.51 * x + vec3<f32>(0.03, 0.03, 0.03));
+	let b = x * (2.43 * x + vec3<f32>(0.59, 0.59, 0.59)) + vec3<f32>(0.14, 0.14, 0.14);
+	return clamp(a / b, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0));
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let hdr = textureSample(t_input, s_input, in.texture_coords);
+	return vec4<f32>(aces(hdr.rgb * params.exposure), hdr.a);
+}
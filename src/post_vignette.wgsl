@@ -0,0 +1,27 @@
+// Darken the corners of the screen
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[block]]
+struct Vignette {
+	intensity: f32;
+	// distance from the center, where darkening starts
+	radius: f32;
+	// width of the transition
+	softness: f32;
+	pad0: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> params: Vignette;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let color = textureSample(t_input, s_input, in.texture_coords);
+	let dist = distance(in.texture_coords, vec2<f32>(0.5, 0.5));
+	let vignette = smoothStep(params.radius, params.radius - params.softness, dist);
+	let factor = mix(1.0, vignette, params.intensity);
+	return vec4<f32>(color.rgb * factor, color.a);
+}
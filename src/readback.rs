@@ -0,0 +1,41 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// A buffer mapping in flight, checked once per frame instead of blocking on it
+/// the device has to be polled for it to ever finish
+pub struct PendingMap {
+    future: MapFuture,
+}
+
+impl PendingMap {
+    pub fn new(slice: wgpu::BufferSlice, mode: wgpu::MapMode) -> Self {
+        Self {
+            future: Box::pin(slice.map_async(mode)),
+        }
+    }
+
+    /// Some once the mapping is done, the buffer can be read if it was successful
+    pub fn poll(&mut self) -> Option<Result<(), wgpu::BufferAsyncError>> {
+        // wgpu resolves the future while polling the device, nobody has to be woken up
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        match self.future.as_mut().poll(&mut context) {
+            Poll::Ready(result) => Some(result),
+            Poll::Pending => None,
+        }
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // the vtable never touches the data pointer
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
@@ -0,0 +1,220 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crate::game_loop::Clock;
+
+/// Where recorded frames go
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingOutput {
+    /// numbered PNGs in a new directory, frame-000000.png, frame-000001.png, ...
+    Images(std::path::PathBuf),
+    /// raw RGBA frames piped to the stdin of a command, e.g. ffmpeg
+    /// {width}, {height} and {fps} in its arguments are replaced before it's started
+    Command(String),
+}
+
+/// Wall clock, which is replaced by simulated time while recording
+/// time continues from where the recording stopped, so the simulation doesn't jump ahead
+pub struct RecordingClock {
+    system: crate::game_loop::SystemClock,
+    // simulated time, Some while recording
+    manual: Option<crate::game_loop::ManualClock>,
+    // clock and wall clock time, when the clock switched back to the wall clock
+    resumed: (Duration, Duration),
+}
+
+impl RecordingClock {
+    pub fn new() -> Self {
+        Self {
+            system: crate::game_loop::SystemClock::new(),
+            manual: None,
+            resumed: (Duration::from_secs(0), Duration::from_secs(0)),
+        }
+    }
+
+    /// Move time by frame_time while recording, follow the wall clock for None
+    /// call once per frame before GameLoop::tick
+    pub fn simulate(&mut self, frame_time: Option<Duration>) {
+        match frame_time {
+            Some(frame_time) => {
+                let now = self.now();
+                let manual = self.manual.get_or_insert_with(|| {
+                    let mut manual = crate::game_loop::ManualClock::new();
+                    manual.advance(now);
+                    manual
+                });
+                manual.advance(frame_time);
+            },
+            None if self.manual.is_some() => {
+                let now = self.now();
+                self.manual = None;
+                self.resumed = (now, self.system.now());
+            },
+            None => {},
+        }
+    }
+}
+
+impl Clock for RecordingClock {
+    fn now(&self) -> Duration {
+        match &self.manual {
+            Some(manual) => manual.now(),
+            None => self.resumed.0 + self.system.now().saturating_sub(self.resumed.1),
+        }
+    }
+}
+
+enum Sink {
+    Images(std::path::PathBuf),
+    Command(std::process::Child),
+}
+
+impl Sink {
+    fn write(&mut self, frame: &crate::capture::CapturedFrame, index: u64) -> std::io::Result<()> {
+        match self {
+            Self::Images(directory) => {
+                let path = directory.join(format!("frame-{:06}.png", index));
+                image::save_buffer(path, &frame.pixels, frame.width, frame.height, image::ColorType::Rgba8)
+                    .map_err(std::io::Error::other)
+            },
+            Self::Command(child) => match &mut child.stdin {
+                Some(stdin) => stdin.write_all(&frame.pixels),
+                None => Err(std::io::ErrorKind::BrokenPipe.into()),
+            },
+        }
+    }
+
+    fn finish(self) {
+        if let Self::Command(mut child) = self {
+            // closing stdin tells the encoder, that there are no more frames
+            drop(child.stdin.take());
+            match child.wait() {
+                Ok(status) if status.success() => {},
+                Ok(status) => log::warn!("Recording command exited with {}", status),
+                Err(error) => log::error!("Could not wait for the recording command: {}", error),
+            }
+        }
+    }
+}
+
+/// Start the command, with the size of the video in its arguments
+fn spawn_command(command: &str, width: u32, height: u32, fps: u32) -> std::io::Result<std::process::Child> {
+    let mut arguments = command.split_whitespace().map(|argument| {
+        argument
+            .replace("{width}", &width.to_string())
+            .replace("{height}", &height.to_string())
+            .replace("{fps}", &fps.to_string())
+    });
+    let program = arguments
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty recording command"))?;
+    std::process::Command::new(program)
+        .args(arguments)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+}
+
+/// Records every rendered frame at a fixed frame rate
+/// while it's recording, one video frame of simulated time passes per rendered frame, however long
+/// it took, so the video is smooth even if the renderer can't keep up
+/// frames are written in order on another thread
+pub struct Recorder {
+    fps: u32,
+    width: u32,
+    height: u32,
+    // next frame to request from the renderer
+    requested: u64,
+    // next frame to hand to the writer, later ones wait in pending
+    written: u64,
+    pending: std::collections::BTreeMap<u64, crate::capture::CapturedFrame>,
+    sender: Option<std::sync::mpsc::Sender<(u64, crate::capture::CapturedFrame)>>,
+    writer: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Recorder {
+    /// Start a recording of width x height frames
+    pub fn start(output: &RecordingOutput, fps: u32, width: u32, height: u32) -> anyhow::Result<Self> {
+        let fps = fps.max(1);
+        let mut sink = match output {
+            RecordingOutput::Images(directory) => {
+                let directory = directory.join(crate::capture::timestamped_name("recording").trim_end_matches(".png"));
+                std::fs::create_dir_all(&directory)?;
+                log::info!("Recording to {:?}", directory);
+                Sink::Images(directory)
+            },
+            RecordingOutput::Command(command) => {
+                log::info!("Recording to {:?}", command);
+                Sink::Command(spawn_command(command, width, height, fps)?)
+            },
+        };
+
+        let (sender, receiver) = std::sync::mpsc::channel::<(u64, crate::capture::CapturedFrame)>();
+        let writer = std::thread::spawn(move || {
+            for (index, frame) in receiver {
+                if let Err(error) = sink.write(&frame, index) {
+                    log::error!("Could not write frame {} of the recording: {}", index, error);
+                    break;
+                }
+            }
+            sink.finish();
+        });
+
+        Ok(Self {
+            fps,
+            width,
+            height,
+            requested: 0,
+            written: 0,
+            pending: std::collections::BTreeMap::new(),
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    /// Simulated time between two frames
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps as f64)
+    }
+
+    /// Number of the next frame to capture
+    pub fn next_frame(&mut self) -> u64 {
+        let frame = self.requested;
+        self.requested += 1;
+        frame
+    }
+
+    /// Hand a captured frame to the writer, frames can arrive in any order
+    pub fn write(&mut self, index: u64, frame: crate::capture::CapturedFrame) {
+        self.pending.insert(index, frame);
+        while let Some(frame) = self.pending.remove(&self.written) {
+            // a video can't change its size, images could but it would be confusing
+            if frame.width != self.width || frame.height != self.height {
+                log::warn!("Dropping frame {} of the recording, the window was resized", self.written);
+            } else if let Some(sender) = &self.sender {
+                // an error means the writer gave up, it already logged why
+                let _ = sender.send((self.written, frame));
+            }
+            self.written += 1;
+        }
+    }
+
+    /// Whether every requested frame was handed to the writer
+    pub fn is_complete(&self) -> bool {
+        self.written == self.requested
+    }
+
+    /// Wait for the writer to finish the frames it got, frames still on the gpu are lost
+    pub fn finish(mut self) {
+        if !self.is_complete() {
+            log::warn!("Recording stopped with {} frames missing", self.requested - self.written);
+        }
+        // closing the channel ends the writer
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                log::error!("Recording writer panicked");
+            }
+        }
+        log::info!("Recorded {} frames", self.written);
+    }
+}
@@ -0,0 +1,576 @@
+use cgmath::{InnerSpace, Matrix3, Matrix4, One, Quaternion, SquareMatrix, Vector3};
+
+/// Handle to a node, stays valid until the node is removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+/// Translation, rotation and scale relative to the parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            ..Self::identity()
+        }
+    }
+
+    /// Split a matrix built by matrix() back into its parts, shearing is lost
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let scale = Vector3::new(matrix.x.truncate().magnitude(), matrix.y.truncate().magnitude(), matrix.z.truncate().magnitude());
+        let rotation = Matrix3::from_cols(
+            matrix.x.truncate() / scale.x,
+            matrix.y.truncate() / scale.y,
+            matrix.z.truncate() / scale.z,
+        );
+        Self {
+            translation: matrix.w.truncate(),
+            rotation: Quaternion::from(rotation).normalize(),
+            scale,
+        }
+    }
+
+    /// Blend towards other, t = 0 is self, t = 1 is other
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    /// Scale first, then rotate, then translate
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Draw this mesh with this material at the node's world transform
+/// both are indices into the renderer's lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshAttachment {
+    pub mesh: usize,
+    pub material: usize,
+}
+
+/// Point light, which sits at the node's world position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightAttachment {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+/// Camera looking down the node's -z axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraAttachment {
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    local: Transform,
+    world: Matrix4<f32>,
+    // world needs to be recomputed
+    dirty: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+
+    pub mesh: Option<MeshAttachment>,
+    pub light: Option<LightAttachment>,
+    pub camera: Option<CameraAttachment>,
+}
+
+impl Node {
+    fn new(name: &str, local: Transform, parent: Option<NodeId>) -> Self {
+        Self {
+            name: name.to_string(),
+            local,
+            world: Matrix4::identity(),
+            dirty: true,
+            parent,
+            children: Vec::new(),
+
+            mesh: None,
+            light: None,
+            camera: None,
+        }
+    }
+
+    pub fn local_transform(&self) -> &Transform {
+        &self.local
+    }
+
+    /// Only up to date after Scene::update_world_transforms
+    pub fn world_transform(&self) -> &Matrix4<f32> {
+        &self.world
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    /// the node was removed or never existed
+    InvalidNode(NodeId),
+    /// the new parent is the node itself or one of its descendants
+    Cycle { node: NodeId, parent: NodeId },
+    /// a restored node is still there
+    Exists(NodeId),
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidNode(id) => write!(f, "node {:?} does not exist", id),
+            Self::Cycle { node, parent } => write!(f, "making {:?} the parent of {:?} would create a cycle", parent, node),
+            Self::Exists(id) => write!(f, "node {:?} exists already", id),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Copy of a node with its descendants, which can be put back with the same ids after remove()
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subtree {
+    parent: Option<NodeId>,
+    // position among the children of the parent, or among the roots
+    index: usize,
+    // the top node first, parents before their children
+    nodes: Vec<(NodeId, Node)>,
+}
+
+impl Subtree {
+    pub fn root(&self) -> NodeId {
+        self.nodes[0].0
+    }
+
+    /// The top node and all its descendants
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().map(|(id, _)| *id)
+    }
+}
+
+/// Hierarchy of nodes, world transforms are derived from the local transforms of all ancestors
+/// nodes live in an arena, removed slots are never reused so old NodeIds can't alias new nodes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scene {
+    nodes: Vec<Option<Node>>,
+    roots: Vec<NodeId>,
+}
+
+// scene graph API for editing tools, the renderer itself only needs parts of it
+#[allow(dead_code)]
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node without parent
+    pub fn add_node(&mut self, name: &str, local: Transform) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node::new(name, local, None)));
+        self.roots.push(id);
+        id
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, name: &str, local: Transform) -> Result<NodeId, SceneError> {
+        if self.node(parent).is_none() {
+            return Err(SceneError::InvalidNode(parent));
+        }
+
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node::new(name, local, Some(parent))));
+        self.node_entry(parent).children.push(id);
+        Ok(id)
+    }
+
+    /// Remove the node and all its descendants
+    pub fn remove(&mut self, id: NodeId) -> Result<(), SceneError> {
+        let parent = self.node(id).ok_or(SceneError::InvalidNode(id))?.parent;
+        self.detach(id, parent);
+
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            if let Some(node) = self.nodes[current.0].take() {
+                stack.extend(node.children);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy of the node and all its descendants, for restore()
+    pub fn subtree(&self, id: NodeId) -> Option<Subtree> {
+        let parent = self.node(id)?.parent;
+        let siblings = match parent {
+            Some(parent) => self.node(parent)?.children.as_slice(),
+            None => self.roots.as_slice(),
+        };
+        let index = siblings.iter().position(|sibling| *sibling == id)?;
+
+        let mut nodes = Vec::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let node = self.node(current)?;
+            stack.extend(node.children.iter().rev());
+            nodes.push((current, node.clone()));
+        }
+        Some(Subtree { parent, index, nodes })
+    }
+
+    /// Put removed nodes back where they were, with the same ids
+    /// the parent has to exist, none of the nodes may
+    pub fn restore(&mut self, subtree: &Subtree) -> Result<(), SceneError> {
+        if let Some(parent) = subtree.parent {
+            if self.node(parent).is_none() {
+                return Err(SceneError::InvalidNode(parent));
+            }
+        }
+        if let Some(id) = subtree.ids().find(|id| self.node(*id).is_some()) {
+            return Err(SceneError::Exists(id));
+        }
+
+        let end = subtree.ids().map(|id| id.0 + 1).max().unwrap_or(0);
+        if self.nodes.len() < end {
+            self.nodes.resize(end, None);
+        }
+        for (id, node) in &subtree.nodes {
+            self.nodes[id.0] = Some(Node {
+                dirty: true,
+                ..node.clone()
+            });
+        }
+
+        let root = subtree.root();
+        let siblings = match subtree.parent {
+            Some(parent) => &mut self.node_entry(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.insert(subtree.index.min(siblings.len()), root);
+        Ok(())
+    }
+
+    /// Move node below another parent, or make it a root with None
+    /// the local transform is kept, so the node moves with its new parent
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), SceneError> {
+        let old_parent = self.node(id).ok_or(SceneError::InvalidNode(id))?.parent;
+
+        if let Some(new_parent) = parent {
+            if self.node(new_parent).is_none() {
+                return Err(SceneError::InvalidNode(new_parent));
+            }
+            // walking up from the new parent must never reach the node itself
+            if self.ancestors(new_parent).chain(std::iter::once(new_parent)).any(|ancestor| ancestor == id) {
+                return Err(SceneError::Cycle { node: id, parent: new_parent });
+            }
+        }
+
+        self.detach(id, old_parent);
+        match parent {
+            Some(new_parent) => self.node_entry(new_parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        let node = self.node_entry(id);
+        node.parent = parent;
+        node.dirty = true;
+        Ok(())
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0).and_then(Option::as_ref)
+    }
+
+    /// Attachments can be changed directly, transforms have to go through set_local_transform
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0).and_then(Option::as_mut)
+    }
+
+    pub fn set_local_transform(&mut self, id: NodeId, local: Transform) -> Result<(), SceneError> {
+        let node = self.node_mut(id).ok_or(SceneError::InvalidNode(id))?;
+        node.local = local;
+        node.dirty = true;
+        Ok(())
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Parent, grandparent, ... up to the root
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.node(id).and_then(|node| node.parent), move |current| {
+            self.node(*current).and_then(|node| node.parent)
+        })
+    }
+
+    /// First node with the given name
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.iter().find(|(_, node)| node.name == name).map(|(id, _)| id)
+    }
+
+    /// All nodes, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| node.as_ref().map(|node| (NodeId(index), node)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Recompute the world transform of every node, whose local transform
+    /// or an ancestor's local transform changed since the last update
+    /// returns the number of recomputed nodes
+    pub fn update_world_transforms(&mut self) -> usize {
+        let mut updated = 0;
+        // (node, world transform of parent, parent changed)
+        let mut stack: Vec<(NodeId, Matrix4<f32>, bool)> = self.roots
+            .iter()
+            .rev()
+            .map(|root| (*root, Matrix4::identity(), false))
+            .collect();
+
+        while let Some((id, parent_world, parent_dirty)) = stack.pop() {
+            let node = self.node_entry(id);
+            let dirty = node.dirty || parent_dirty;
+            if dirty {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+                updated += 1;
+            }
+
+            let world = node.world;
+            // reversed, so children are visited in insertion order
+            for child in node.children.iter().rev() {
+                stack.push((*child, world, dirty));
+            }
+        }
+
+        updated
+    }
+
+    /// Depth first traversal from the roots, visitor gets the depth of every node
+    pub fn traverse<F: FnMut(NodeId, &Node, usize)>(&self, mut visitor: F) {
+        let mut stack: Vec<(NodeId, usize)> = self.roots.iter().rev().map(|root| (*root, 0)).collect();
+        while let Some((id, depth)) = stack.pop() {
+            if let Some(node) = self.node(id) {
+                visitor(id, node, depth);
+                stack.extend(node.children.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+    }
+
+    /// Everything the renderer has to draw, with world transforms
+    pub fn mesh_instances(&self) -> impl Iterator<Item = (NodeId, MeshAttachment, Matrix4<f32>)> + '_ {
+        self.iter().filter_map(|(id, node)| node.mesh.map(|mesh| (id, mesh, node.world)))
+    }
+
+    /// Point lights at their world positions
+    pub fn point_lights(&self) -> impl Iterator<Item = crate::light::PointLight> + '_ {
+        self.iter().filter_map(|(_, node)| {
+            node.light.map(|light| crate::light::PointLight {
+                position: node.world.w.truncate().into(),
+                radius: light.radius,
+                color: light.color,
+                intensity: light.intensity,
+            })
+        })
+    }
+
+    /// All camera attachments with their world transforms
+    pub fn cameras(&self) -> impl Iterator<Item = (NodeId, CameraAttachment, Matrix4<f32>)> + '_ {
+        self.iter().filter_map(|(id, node)| node.camera.map(|camera| (id, camera, node.world)))
+    }
+
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        match parent {
+            Some(parent) => self.node_entry(parent).children.retain(|child| *child != id),
+            None => self.roots.retain(|root| *root != id),
+        }
+    }
+
+    /// Only for ids, which are known to be valid
+    fn node_entry(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("scene node was removed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(x: f32) -> Transform {
+        Transform::from_translation(Vector3::new(x, 0.0, 0.0))
+    }
+
+    /// root -> a -> b, root -> c
+    fn chain() -> (Scene, [NodeId; 4]) {
+        let mut scene = Scene::new();
+        let root = scene.add_node("root", translation(1.0));
+        let a = scene.add_child(root, "a", translation(2.0)).unwrap();
+        let b = scene.add_child(a, "b", translation(3.0)).unwrap();
+        let c = scene.add_child(root, "c", translation(4.0)).unwrap();
+        (scene, [root, a, b, c])
+    }
+
+    fn world_x(scene: &Scene, id: NodeId) -> f32 {
+        scene.node(id).unwrap().world_transform().w.x
+    }
+
+    #[test]
+    fn from_matrix_undoes_matrix() {
+        let transform = Transform {
+            translation: Vector3::new(1.0, -2.0, 3.0),
+            rotation: Quaternion::new(0.8, 0.0, 0.6, 0.0),
+            scale: Vector3::new(2.0, 1.0, 0.5),
+        };
+        let parts = Transform::from_matrix(&transform.matrix());
+        assert!((parts.translation - transform.translation).magnitude() < 1e-5);
+        assert!((parts.scale - transform.scale).magnitude() < 1e-5);
+        assert!(parts.rotation.dot(transform.rotation).abs() > 1.0 - 1e-5);
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles() {
+        let (mut scene, [root, a, b, _]) = chain();
+        assert_eq!(scene.set_parent(a, Some(a)), Err(SceneError::Cycle { node: a, parent: a }));
+        assert_eq!(scene.set_parent(root, Some(b)), Err(SceneError::Cycle { node: root, parent: b }));
+        assert_eq!(scene.set_parent(a, Some(b)), Err(SceneError::Cycle { node: a, parent: b }));
+        // nothing changed
+        assert_eq!(scene.node(a).unwrap().parent(), Some(root));
+        assert_eq!(scene.roots(), [root]);
+    }
+
+    #[test]
+    fn set_parent_moves_the_subtree() {
+        let (mut scene, [root, a, b, c]) = chain();
+        scene.set_parent(a, Some(c)).unwrap();
+        assert_eq!(scene.node(root).unwrap().children(), [c]);
+        assert_eq!(scene.node(c).unwrap().children(), [a]);
+        assert_eq!(scene.ancestors(b).collect::<Vec<_>>(), [a, c, root]);
+
+        scene.set_parent(a, None).unwrap();
+        assert_eq!(scene.roots(), [root, a]);
+        assert_eq!(scene.ancestors(b).collect::<Vec<_>>(), [a]);
+
+        scene.update_world_transforms();
+        assert_eq!(world_x(&scene, b), 5.0);
+    }
+
+    #[test]
+    fn restore_undoes_remove() {
+        let (mut scene, [root, a, b, c]) = chain();
+        scene.update_world_transforms();
+        let before = scene.clone();
+        let subtree = scene.subtree(a).unwrap();
+        assert_eq!(subtree.ids().collect::<Vec<_>>(), [a, b]);
+
+        scene.remove(a).unwrap();
+        scene.restore(&subtree).unwrap();
+        // back in front of c
+        assert_eq!(scene.node(root).unwrap().children(), [a, c]);
+        assert_eq!(scene.update_world_transforms(), 2);
+        assert_eq!(scene, before);
+
+        assert_eq!(scene.restore(&subtree), Err(SceneError::Exists(a)));
+        scene.remove(root).unwrap();
+        assert_eq!(scene.restore(&subtree), Err(SceneError::InvalidNode(root)));
+    }
+
+    #[test]
+    fn set_parent_of_missing_nodes() {
+        let (mut scene, [_, a, b, _]) = chain();
+        scene.remove(b).unwrap();
+        assert_eq!(scene.set_parent(b, None), Err(SceneError::InvalidNode(b)));
+        assert_eq!(scene.set_parent(a, Some(b)), Err(SceneError::InvalidNode(b)));
+    }
+
+    #[test]
+    fn remove_deletes_descendants_and_detaches() {
+        let (mut scene, [root, a, b, c]) = chain();
+        scene.remove(a).unwrap();
+        assert!(scene.node(a).is_none());
+        assert!(scene.node(b).is_none());
+        assert_eq!(scene.node(root).unwrap().children(), [c]);
+        assert_eq!(scene.len(), 2);
+        assert_eq!(scene.remove(a), Err(SceneError::InvalidNode(a)));
+
+        // ids aren't reused
+        let d = scene.add_child(root, "d", Transform::identity()).unwrap();
+        assert_ne!(d, a);
+        assert_ne!(d, b);
+
+        scene.remove(root).unwrap();
+        assert!(scene.is_empty());
+        assert!(scene.roots().is_empty());
+    }
+
+    #[test]
+    fn update_only_recomputes_dirty_subtrees() {
+        let (mut scene, [root, a, b, c]) = chain();
+        assert_eq!(scene.update_world_transforms(), 4);
+        assert_eq!(world_x(&scene, b), 6.0);
+        assert_eq!(world_x(&scene, c), 5.0);
+        assert_eq!(scene.update_world_transforms(), 0);
+
+        // a and its child b, but not c
+        scene.set_local_transform(a, translation(10.0)).unwrap();
+        assert_eq!(scene.update_world_transforms(), 2);
+        assert_eq!(world_x(&scene, b), 14.0);
+        assert_eq!(world_x(&scene, c), 5.0);
+
+        scene.set_local_transform(root, translation(0.0)).unwrap();
+        assert_eq!(scene.update_world_transforms(), 4);
+        assert_eq!(world_x(&scene, b), 13.0);
+    }
+
+    #[test]
+    fn traverse_is_depth_first_in_insertion_order() {
+        let (mut scene, [root, a, b, c]) = chain();
+        let other = scene.add_node("other", Transform::identity());
+        let e = scene.add_child(a, "e", Transform::identity()).unwrap();
+
+        let mut visited = Vec::new();
+        scene.traverse(|id, _, depth| visited.push((id, depth)));
+        assert_eq!(visited, [(root, 0), (a, 1), (b, 2), (e, 2), (c, 1), (other, 0)]);
+        assert_eq!(scene.find("e"), Some(e));
+    }
+}
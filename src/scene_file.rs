@@ -0,0 +1,471 @@
+use std::path::{Path, PathBuf};
+
+use cgmath::{Quaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Version written by this build, bump whenever the format changes incompatibly
+pub const SCENE_VERSION: u32 = 1;
+
+/// A whole scene as it is stored on disk
+/// meshes are referenced by name, materials by name and texture path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub version: u32,
+    #[serde(default = "default_ambient")]
+    pub ambient: f32,
+    #[serde(default)]
+    pub materials: Vec<MaterialDescription>,
+    #[serde(default)]
+    pub nodes: Vec<NodeDescription>,
+}
+
+fn default_ambient() -> f32 {
+    0.3
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialDescription {
+    pub name: String,
+    /// diffuse texture, relative to the scene file
+    pub texture: PathBuf,
+    #[serde(default = "default_specular")]
+    pub specular: f32,
+    #[serde(default = "default_shininess")]
+    pub shininess: f32,
+    #[serde(default)]
+    pub emissive: f32,
+}
+
+fn default_specular() -> f32 {
+    crate::material::MaterialParams::default().specular
+}
+
+fn default_shininess() -> f32 {
+    crate::material::MaterialParams::default().shininess
+}
+
+impl MaterialDescription {
+    pub fn params(&self) -> crate::material::MaterialParams {
+        crate::material::MaterialParams {
+            specular: self.specular,
+            shininess: self.shininess,
+            emissive: self.emissive,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDescription {
+    pub name: String,
+    #[serde(default)]
+    pub transform: TransformDescription,
+    #[serde(default)]
+    pub mesh: Option<MeshDescription>,
+    #[serde(default)]
+    pub light: Option<LightDescription>,
+    #[serde(default)]
+    pub camera: Option<CameraDescription>,
+    #[serde(default)]
+    pub children: Vec<NodeDescription>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransformDescription {
+    #[serde(default = "default_translation")]
+    pub translation: [f32; 3],
+    /// quaternion as x, y, z, w
+    #[serde(default = "default_rotation")]
+    pub rotation: [f32; 4],
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+}
+
+fn default_translation() -> [f32; 3] {
+    [0.0, 0.0, 0.0]
+}
+
+fn default_rotation() -> [f32; 4] {
+    [0.0, 0.0, 0.0, 1.0]
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl Default for TransformDescription {
+    fn default() -> Self {
+        Self {
+            translation: default_translation(),
+            rotation: default_rotation(),
+            scale: default_scale(),
+        }
+    }
+}
+
+impl From<&crate::scene::Transform> for TransformDescription {
+    fn from(transform: &crate::scene::Transform) -> Self {
+        let rotation = transform.rotation;
+        Self {
+            translation: transform.translation.into(),
+            rotation: [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s],
+            scale: transform.scale.into(),
+        }
+    }
+}
+
+impl From<&TransformDescription> for crate::scene::Transform {
+    fn from(description: &TransformDescription) -> Self {
+        let [x, y, z, w] = description.rotation;
+        Self {
+            translation: Vector3::from(description.translation),
+            rotation: Quaternion::new(w, x, y, z),
+            scale: Vector3::from(description.scale),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshDescription {
+    pub mesh: String,
+    pub material: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LightDescription {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraDescription {
+    /// vertical field of view in degrees
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+#[derive(Debug)]
+pub enum SceneLoadError {
+    Io { path: PathBuf, error: std::io::Error },
+    /// syntax error or wrong structure, ron reports line and column
+    Parse { path: PathBuf, error: ron::Error },
+    UnsupportedVersion { found: u32, supported: u32 },
+    DuplicateMaterial { name: String },
+    UnknownMaterial { node: String, material: String },
+    UnknownMesh { node: String, mesh: String },
+    /// the diffuse texture of a material could not be loaded
+    Texture { material: String, path: PathBuf, message: String },
+}
+
+impl std::fmt::Display for SceneLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "could not read {:?}: {}", path, error),
+            Self::Parse { path, error } => write!(f, "could not parse {:?}: {}", path, error),
+            Self::UnsupportedVersion { found, supported } => write!(
+                f, "scene has version {}, but only version {} is supported", found, supported,
+            ),
+            Self::DuplicateMaterial { name } => write!(f, "material {:?} is defined more than once", name),
+            Self::UnknownMaterial { node, material } => write!(
+                f, "node {:?} uses material {:?}, which is not defined in the scene", node, material,
+            ),
+            Self::UnknownMesh { node, mesh } => write!(f, "node {:?} uses unknown mesh {:?}", node, mesh),
+            Self::Texture { material, path, message } => write!(
+                f, "could not load texture {:?} of material {:?}: {}", path, material, message,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneLoadError {}
+
+impl SceneDescription {
+    /// Read and parse a scene file, only checks the version
+    /// references get resolved by build()
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneLoadError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| SceneLoadError::Io { path: path.to_path_buf(), error })?;
+        Self::parse(&source).map_err(|error| match error {
+            // parse() has no idea where the source came from
+            SceneLoadError::Parse { error, .. } => SceneLoadError::Parse { path: path.to_path_buf(), error },
+            error => error,
+        })
+    }
+
+    pub fn parse(source: &str) -> Result<Self, SceneLoadError> {
+        // look at the version first, so old files don't fail with confusing field errors
+        #[derive(Deserialize)]
+        #[serde(rename = "SceneDescription")]
+        struct Versioned {
+            version: u32,
+        }
+        let parse_error = |error| SceneLoadError::Parse { path: PathBuf::new(), error };
+
+        let Versioned { version } = ron::de::from_str::<Versioned>(source).map_err(parse_error)?;
+        if version != SCENE_VERSION {
+            return Err(SceneLoadError::UnsupportedVersion { found: version, supported: SCENE_VERSION });
+        }
+
+        ron::de::from_str(source).map_err(parse_error)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?)
+    }
+
+    /// Create the scene graph, mesh attachments index into meshes and self.materials
+    pub fn build(&self, meshes: &[&str]) -> Result<crate::scene::Scene, SceneLoadError> {
+        for (index, material) in self.materials.iter().enumerate() {
+            if self.materials[..index].iter().any(|other| other.name == material.name) {
+                return Err(SceneLoadError::DuplicateMaterial { name: material.name.clone() });
+            }
+        }
+
+        let mut scene = crate::scene::Scene::new();
+        for node in &self.nodes {
+            let id = scene.add_node(&node.name, (&node.transform).into());
+            self.build_node(&mut scene, id, node, meshes)?;
+        }
+        Ok(scene)
+    }
+
+    fn build_node(
+        &self,
+        scene: &mut crate::scene::Scene,
+        id: crate::scene::NodeId,
+        description: &NodeDescription,
+        meshes: &[&str],
+    ) -> Result<(), SceneLoadError> {
+        let mesh = match &description.mesh {
+            Some(mesh) => Some(crate::scene::MeshAttachment {
+                mesh: meshes
+                    .iter()
+                    .position(|name| *name == mesh.mesh)
+                    .ok_or_else(|| SceneLoadError::UnknownMesh {
+                        node: description.name.clone(),
+                        mesh: mesh.mesh.clone(),
+                    })?,
+                material: self.materials
+                    .iter()
+                    .position(|material| material.name == mesh.material)
+                    .ok_or_else(|| SceneLoadError::UnknownMaterial {
+                        node: description.name.clone(),
+                        material: mesh.material.clone(),
+                    })?,
+            }),
+            None => None,
+        };
+
+        let node = scene.node_mut(id).expect("node was just added");
+        node.mesh = mesh;
+        node.light = description.light.map(|light| crate::scene::LightAttachment {
+            color: light.color,
+            intensity: light.intensity,
+            radius: light.radius,
+        });
+        node.camera = description.camera.map(|camera| crate::scene::CameraAttachment {
+            fovy: camera.fovy,
+            znear: camera.znear,
+            zfar: camera.zfar,
+        });
+
+        for child in &description.children {
+            let child_id = scene
+                .add_child(id, &child.name, (&child.transform).into())
+                .expect("parent was just added");
+            self.build_node(scene, child_id, child, meshes)?;
+        }
+        Ok(())
+    }
+
+    /// Describe an existing scene, the inverse of build()
+    /// meshes and materials give the names of the indices in the mesh attachments
+    pub fn from_scene(
+        scene: &crate::scene::Scene,
+        meshes: &[&str],
+        materials: Vec<MaterialDescription>,
+        ambient: f32,
+    ) -> Self {
+        let nodes = scene
+            .roots()
+            .iter()
+            .map(|root| Self::describe_node(scene, *root, meshes, &materials))
+            .collect();
+
+        Self {
+            version: SCENE_VERSION,
+            ambient,
+            materials,
+            nodes,
+        }
+    }
+
+    fn describe_node(
+        scene: &crate::scene::Scene,
+        id: crate::scene::NodeId,
+        meshes: &[&str],
+        materials: &[MaterialDescription],
+    ) -> NodeDescription {
+        let node = scene.node(id).expect("roots and children are always valid");
+        NodeDescription {
+            name: node.name.clone(),
+            transform: node.local_transform().into(),
+            // attachments with indices out of range are dropped, they are never drawn anyway
+            mesh: node.mesh.and_then(|mesh| Some(MeshDescription {
+                mesh: meshes.get(mesh.mesh)?.to_string(),
+                material: materials.get(mesh.material)?.name.clone(),
+            })),
+            light: node.light.map(|light| LightDescription {
+                color: light.color,
+                intensity: light.intensity,
+                radius: light.radius,
+            }),
+            camera: node.camera.map(|camera| CameraDescription {
+                fovy: camera.fovy,
+                znear: camera.znear,
+                zfar: camera.zfar,
+            }),
+            children: node
+                .children()
+                .iter()
+                .map(|child| Self::describe_node(scene, *child, meshes, materials))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESHES: &[&str] = &["pentagon", "cube"];
+
+    fn material(name: &str) -> MaterialDescription {
+        MaterialDescription {
+            name: name.to_string(),
+            texture: PathBuf::from(format!("{}.png", name)),
+            specular: 0.5,
+            shininess: 32.0,
+            emissive: 0.0,
+        }
+    }
+
+    fn node(name: &str, mesh: Option<(&str, &str)>, children: Vec<NodeDescription>) -> NodeDescription {
+        NodeDescription {
+            name: name.to_string(),
+            transform: TransformDescription::default(),
+            mesh: mesh.map(|(mesh, material)| MeshDescription {
+                mesh: mesh.to_string(),
+                material: material.to_string(),
+            }),
+            light: None,
+            camera: None,
+            children,
+        }
+    }
+
+    fn description() -> SceneDescription {
+        let mut light = node("light", None, Vec::new());
+        light.light = Some(LightDescription { color: [1.0, 0.5, 0.0], intensity: 2.0, radius: 5.0 });
+        let mut arm = node("arm", Some(("cube", "tree")), vec![light]);
+        arm.transform = TransformDescription {
+            translation: [1.5, 0.0, -0.5],
+            rotation: [0.0, 0.0, 0.6, 0.8],
+            scale: [0.5, 0.5, 0.5],
+        };
+        let mut camera = node("camera", None, Vec::new());
+        camera.camera = Some(CameraDescription { fovy: 45.0, znear: 0.1, zfar: 100.0 });
+
+        SceneDescription {
+            version: SCENE_VERSION,
+            ambient: 0.2,
+            materials: vec![material("aqua"), material("tree")],
+            nodes: vec![camera, node("center", Some(("pentagon", "aqua")), vec![arm])],
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let description = description();
+        let path = std::env::temp_dir().join(format!("scene-round-trip-{}.ron", std::process::id()));
+        description.save(&path).unwrap();
+        let loaded = SceneDescription::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), description);
+    }
+
+    #[test]
+    fn build_and_describe_round_trip() {
+        let description = description();
+        let scene = description.build(MESHES).unwrap();
+        assert_eq!(scene.len(), 4);
+        let described = SceneDescription::from_scene(&scene, MESHES, description.materials.clone(), description.ambient);
+        assert_eq!(described, description);
+    }
+
+    #[test]
+    fn defaults_of_missing_fields() {
+        let description = SceneDescription::parse("(version: 1, nodes: [(name: \"empty\")])").unwrap();
+        assert_eq!(description.ambient, default_ambient());
+        assert!(description.materials.is_empty());
+        assert_eq!(description.nodes[0].transform, TransformDescription::default());
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let source = "(version: 2, materials: [], nodes: [], some_new_field: true)";
+        match SceneDescription::parse(source) {
+            Err(SceneLoadError::UnsupportedVersion { found: 2, supported: SCENE_VERSION }) => {},
+            other => panic!("expected an unsupported version, got {:?}", other),
+        }
+        assert!(matches!(SceneDescription::parse("(nodes: [])"), Err(SceneLoadError::Parse { .. })));
+    }
+
+    #[test]
+    fn unknown_mesh() {
+        let mut description = description();
+        description.nodes.push(node("sphere", Some(("sphere", "aqua")), Vec::new()));
+        match description.build(MESHES) {
+            Err(SceneLoadError::UnknownMesh { node, mesh }) => assert_eq!((node.as_str(), mesh.as_str()), ("sphere", "sphere")),
+            other => panic!("expected an unknown mesh, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_material() {
+        let mut description = description();
+        description.nodes[1].children[0].children.push(node("deep", Some(("cube", "gold")), Vec::new()));
+        match description.build(MESHES) {
+            Err(SceneLoadError::UnknownMaterial { node, material }) => assert_eq!((node.as_str(), material.as_str()), ("deep", "gold")),
+            other => panic!("expected an unknown material, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn duplicate_material() {
+        let mut description = description();
+        description.materials.push(material("aqua"));
+        match description.build(MESHES) {
+            Err(SceneLoadError::DuplicateMaterial { name }) => assert_eq!(name, "aqua"),
+            other => panic!("expected a duplicate material, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bundled_scene_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/hierarchy.ron");
+        let description = SceneDescription::load(path).unwrap();
+        assert_eq!(description.version, SCENE_VERSION);
+        let scene = description.build(&["pentagon"]).unwrap();
+        assert!(scene.find("camera").is_some());
+        assert!(scene.iter().any(|(_, node)| node.mesh.is_some()));
+    }
+}
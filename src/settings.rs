@@ -0,0 +1,218 @@
+/// Sample counts we know how to handle for MSAA
+pub const SUPPORTED_SAMPLE_COUNTS: &[u32] = &[1, 2, 4, 8];
+
+/// Startup configuration of the renderer
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// number of MSAA samples per pixel (1 disables multisampling)
+    /// only used by the forward path
+    pub sample_count: u32,
+    /// forward or deferred shading
+    pub render_path: crate::deferred::RenderPath,
+    /// ordered chain of post-processing effects
+    pub post_effects: Vec<crate::post::PostEffect>,
+    /// image to use as color grading LUT, identity if None
+    pub color_lut: Option<std::path::PathBuf>,
+    /// initial bloom parameters, can be changed at runtime
+    pub bloom: crate::bloom::BloomSettings,
+    /// ambient occlusion parameters, can be changed at runtime
+    pub ssao: crate::ssao::SsaoSettings,
+    /// scene file to load, the built-in scene if None
+    pub scene: Option<std::path::PathBuf>,
+    /// fixed updates per second
+    pub simulation_rate: u32,
+    /// fixed updates per frame, before the simulation starts skipping time
+    pub max_simulation_steps: u32,
+    /// frames per second, unlimited if None
+    pub fps_limit: Option<u32>,
+    /// start with the frame stats overlay visible
+    pub show_stats: bool,
+    /// TTF or OTF font for labels, text rendering is disabled without it
+    pub font: Option<std::path::PathBuf>,
+    /// pick clicked objects with an id buffer on the gpu instead of a ray cast
+    pub gpu_picking: bool,
+    /// only use an adapter running on the cpu, e.g. for images that look the same on every machine
+    pub software_adapter: bool,
+    /// where screenshots are saved
+    pub screenshot_dir: std::path::PathBuf,
+    /// where recordings go
+    pub recording: crate::recording::RecordingOutput,
+    /// frames per second of a recording, also the simulated time per recorded frame
+    pub recording_fps: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sample_count: 4,
+            render_path: crate::deferred::RenderPath::Forward,
+            post_effects: ["tonemap", "vignette", "fxaa"]
+                .iter()
+                .filter_map(|name| crate::post::PostEffect::from_name(name))
+                .collect(),
+            color_lut: None,
+            bloom: crate::bloom::BloomSettings::default(),
+            ssao: crate::ssao::SsaoSettings::default(),
+            scene: None,
+            simulation_rate: 60,
+            max_simulation_steps: 5,
+            fps_limit: None,
+            show_stats: false,
+            font: None,
+            gpu_picking: false,
+            software_adapter: false,
+            screenshot_dir: "screenshots".into(),
+            recording: crate::recording::RecordingOutput::Images("recordings".into()),
+            recording_fps: 60,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings from environment variables, falling back to the defaults
+    /// e.g. `SAMPLE_COUNT=2 cargo run`
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+
+        if let Ok(value) = std::env::var("SAMPLE_COUNT") {
+            match value.parse::<u32>() {
+                Ok(count) if SUPPORTED_SAMPLE_COUNTS.contains(&count) => {
+                    settings.sample_count = count;
+                }
+                _ => log::warn!(
+                    "Ignoring SAMPLE_COUNT={}, expected one of {:?}",
+                    value, SUPPORTED_SAMPLE_COUNTS
+                ),
+            }
+        }
+
+        if let Ok(value) = std::env::var("RENDER_PATH") {
+            match crate::deferred::RenderPath::from_name(&value) {
+                Some(render_path) => settings.render_path = render_path,
+                None => log::warn!("Ignoring RENDER_PATH={}, expected forward or deferred", value),
+            }
+        }
+
+        // comma separated list, e.g. POST_EFFECTS=tonemap,grading,fxaa
+        if let Ok(value) = std::env::var("POST_EFFECTS") {
+            settings.post_effects = value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .filter_map(|name| {
+                    let effect = crate::post::PostEffect::from_name(name);
+                    if effect.is_none() {
+                        log::warn!("Ignoring unknown post effect {}", name);
+                    }
+                    effect
+                })
+                .collect();
+        }
+
+        if let Ok(value) = std::env::var("COLOR_LUT") {
+            settings.color_lut = Some(value.into());
+        }
+
+        if let Ok(value) = std::env::var("BLOOM") {
+            settings.bloom.enabled = !matches!(value.as_str(), "0" | "off" | "false");
+        }
+
+        if let Ok(value) = std::env::var("SSAO") {
+            settings.ssao.enabled = !matches!(value.as_str(), "0" | "off" | "false");
+        }
+
+        if let Ok(value) = std::env::var("SCENE") {
+            settings.scene = Some(value.into());
+        }
+
+        if let Ok(value) = std::env::var("SIMULATION_RATE") {
+            match value.parse::<u32>() {
+                Ok(rate) if rate > 0 => settings.simulation_rate = rate,
+                _ => log::warn!("Ignoring SIMULATION_RATE={}, expected updates per second", value),
+            }
+        }
+
+        if let Ok(value) = std::env::var("FPS_LIMIT") {
+            match value.parse::<u32>() {
+                // 0 => unlimited
+                Ok(fps) => settings.fps_limit = Some(fps).filter(|fps| *fps > 0),
+                _ => log::warn!("Ignoring FPS_LIMIT={}, expected frames per second", value),
+            }
+        }
+
+        if let Ok(value) = std::env::var("STATS") {
+            settings.show_stats = matches!(value.as_str(), "1" | "on" | "true");
+        }
+
+        if let Ok(value) = std::env::var("FONT") {
+            settings.font = Some(value.into());
+        }
+
+        if let Ok(value) = std::env::var("PICKING") {
+            match value.as_str() {
+                "gpu" => settings.gpu_picking = true,
+                "cpu" => settings.gpu_picking = false,
+                _ => log::warn!("Ignoring PICKING={}, expected cpu or gpu", value),
+            }
+        }
+
+        if let Ok(value) = std::env::var("SOFTWARE_ADAPTER") {
+            settings.software_adapter = matches!(value.as_str(), "1" | "on" | "true");
+        }
+
+        if let Ok(value) = std::env::var("SCREENSHOT_DIR") {
+            settings.screenshot_dir = value.into();
+        }
+
+        if let Ok(value) = std::env::var("RECORDING_DIR") {
+            settings.recording = crate::recording::RecordingOutput::Images(value.into());
+        }
+
+        // the command wins over the directory
+        if let Ok(value) = std::env::var("RECORDING_COMMAND") {
+            settings.recording = crate::recording::RecordingOutput::Command(value);
+        }
+
+        if let Ok(value) = std::env::var("RECORDING_FPS") {
+            match value.parse::<u32>() {
+                Ok(fps) if fps > 0 => settings.recording_fps = fps,
+                _ => log::warn!("Ignoring RECORDING_FPS={}, expected frames per second", value),
+            }
+        }
+
+        settings
+    }
+
+    /// Duration of one fixed update
+    pub fn timestep(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(1.0 / self.simulation_rate as f64)
+    }
+}
+
+/// Check the requested sample count against what the adapter can do with the multisampled formats
+/// wgpu 0.9 only tells whether a format can be rendered to, not with how many samples, with
+/// TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES enabled on the device it's at least the adapter's answer
+pub fn validate_sample_count(
+    adapter: &wgpu::Adapter,
+    requested: u32,
+    formats: &[wgpu::TextureFormat],
+) -> Result<u32, crate::error::RendererError> {
+    if !SUPPORTED_SAMPLE_COUNTS.contains(&requested) {
+        return Err(crate::error::RendererError::InvalidSampleCount(requested));
+    }
+
+    let renderable = formats.iter().all(|&format| {
+        adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(wgpu::TextureUsage::RENDER_ATTACHMENT)
+    });
+    if requested > 1 && !renderable {
+        return Err(crate::error::RendererError::UnsupportedSampleCount {
+            count: requested,
+            adapter: adapter.get_info().name,
+        });
+    }
+
+    Ok(requested)
+}
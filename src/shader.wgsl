@@ -2,6 +2,9 @@
 [[block]]
 struct Uniform {
 view_projection: mat4x4<f32>;
+view: mat4x4<f32>;
+inverse_view_projection: mat4x4<f32>;
+eye: vec4<f32>;
 };
 
 [[group(1), binding(0)]]
@@ -10,29 +13,79 @@ var<uniform> uniform: Uniform;
 struct VertexInput {
 	[[location(0)]] position: vec3<f32>;
 	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+	[[location(5)]] model_0: vec4<f32>;
+	[[location(6)]] model_1: vec4<f32>;
+	[[location(7)]] model_2: vec4<f32>;
+	[[location(8)]] model_3: vec4<f32>;
+	[[location(9)]] normal_0: vec3<f32>;
+	[[location(10)]] normal_1: vec3<f32>;
+	[[location(11)]] normal_2: vec3<f32>;
 };
 
 struct VertexOutput {
 	[[builtin(position)]] clip_coordinate: vec4<f32>;
 	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] world_position: vec3<f32>;
+	[[location(2)]] normal: vec3<f32>;
 };
 
 [[stage(vertex)]]
-fn main(model: VertexInput) -> VertexOutput {
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let model_matrix = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	let normal_matrix = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+	let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+
 	var out: VertexOutput;
-	out.clip_coordinate = uniform.view_projection * vec4<f32>(model.position, 1.0);
+	out.clip_coordinate = uniform.view_projection * world_position;
 	out.texture_coords = model.texture_coords;
+	out.world_position = world_position.xyz;
+	out.normal = normal_matrix * model.normal;
 	return out;
 }
 
 // Fragment shader
+[[block]]
+struct Material {
+	specular: f32;
+	shininess: f32;
+	emissive: f32;
+	pad0: f32;
+};
+
 [[group(0), binding(0)]]
-var t_aqua: texture_2d<f32>;
+var t_diffuse: texture_2d<f32>;
 [[group(0), binding(1)]]
-var s_aqua: sampler;
+var s_diffuse: sampler;
+[[group(0), binding(2)]]
+var<uniform> material: Material;
+
+// blurred ambient occlusion, screen sized
+[[group(2), binding(0)]]
+var t_ao: texture_2d<f32>;
+[[group(2), binding(1)]]
+var s_ao: sampler;
 
 [[stage(fragment)]]
 fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-	//return vec4<f32>(in.color, 1.0);
-	return textureSample(t_aqua, s_aqua, in.texture_coords);
+	let albedo = textureSample(t_diffuse, s_diffuse, in.texture_coords);
+
+	// in the fragment stage clip_coordinate holds the pixel position
+	let size = textureDimensions(t_ao);
+	let screen_coords = vec2<f32>(in.clip_coordinate.x / f32(size.x), in.clip_coordinate.y / f32(size.y));
+	let ao = textureSample(t_ao, s_ao, screen_coords).r;
+
+	let lit = shade(
+		albedo.rgb,
+		in.world_position,
+		normalize(in.normal),
+		uniform.eye.xyz,
+		material.specular,
+		material.shininess,
+		ao
+	);
+	return vec4<f32>(lit + albedo.rgb * material.emissive, albedo.a);
 }
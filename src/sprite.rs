@@ -0,0 +1,387 @@
+use wgpu::util::DeviceExt;
+
+/// Two triangles per sprite
+const VERTICES_PER_SPRITE: usize = 6;
+/// Sprites the vertex buffer has room for at first, it grows if there are more
+const INITIAL_CAPACITY: usize = 256;
+
+/// Handle of a texture added to a SpriteBatch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpriteTexture(usize);
+
+/// A textured quad in the 2D world of the sprite camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub texture: SpriteTexture,
+    /// part of the texture in texels, the whole texture if None
+    pub region: Option<crate::atlas::AtlasRect>,
+    pub position: cgmath::Point2<f32>,
+    /// radians, counter clockwise around the anchor
+    pub rotation: f32,
+    /// multiplies the size of the region, one texel is one world unit at scale 1
+    pub scale: cgmath::Vector2<f32>,
+    /// point of the sprite that ends up at position, (0, 0) bottom left and (1, 1) top right
+    pub anchor: [f32; 2],
+    /// multiplied with the texture
+    pub color: [f32; 4],
+    /// bigger is drawn later, on top of smaller
+    pub z: i32,
+}
+
+impl Sprite {
+    /// Whole texture, centered on the origin
+    pub fn new(texture: SpriteTexture) -> Self {
+        Self {
+            texture,
+            region: None,
+            position: cgmath::Point2::new(0.0, 0.0),
+            rotation: 0.0,
+            scale: cgmath::Vector2::new(1.0, 1.0),
+            anchor: [0.5, 0.5],
+            color: [1.0; 4],
+            z: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteVertex {
+    position: [f32; 2],
+    texture_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl SpriteVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+struct SpriteTextureEntry {
+    // keeps the texture alive
+    _texture: crate::texture::Texture,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+/// Consecutive sprites with the same texture
+struct SpriteDrawCall {
+    texture: usize,
+    vertices: std::ops::Range<u32>,
+}
+
+/// Collects sprites during a frame and draws them sorted by z,
+/// one draw call per run of sprites sharing a texture
+pub struct SpriteBatch {
+    pub camera: crate::camera::OrthographicCamera,
+
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    textures: Vec<SpriteTextureEntry>,
+
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+
+    vertex_buffer: wgpu::Buffer,
+    // in sprites
+    capacity: usize,
+    sprites: Vec<Sprite>,
+    draw_calls: Vec<SpriteDrawCall>,
+}
+
+impl SpriteBatch {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let camera = crate::camera::OrthographicCamera::new(width, height);
+        let texture_bind_group_layout = crate::post::create_input_bind_group_layout(device);
+
+        let view_projection: [[f32; 4]; 4] = camera.build_view_projection_matrix().into();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sprite Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[view_projection]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("sprite_uniform_bind_group_layout"),
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("sprite_uniform_bind_group"),
+        });
+
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Sprite Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("sprite.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Sprite Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout, &uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Sprite Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[SpriteVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    // negative scale mirrors the sprite
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                // z only decides the order of drawing
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        );
+
+        let capacity = INITIAL_CAPACITY;
+        let vertex_buffer = Self::create_vertex_buffer(device, capacity);
+
+        Self {
+            camera,
+
+            texture_bind_group_layout,
+            textures: Vec::new(),
+
+            uniform_buffer,
+            uniform_bind_group,
+            pipeline,
+
+            vertex_buffer,
+            capacity,
+            sprites: Vec::new(),
+            draw_calls: Vec::new(),
+        }
+    }
+
+    /// Make a texture usable for sprites, the size is needed to turn regions into texture coordinates
+    pub fn add_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: crate::texture::Texture,
+        width: u32,
+        height: u32,
+    ) -> SpriteTexture {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some("sprite_texture_bind_group"),
+        });
+        self.textures.push(SpriteTextureEntry {
+            _texture: texture,
+            bind_group,
+            width,
+            height,
+        });
+        SpriteTexture(self.textures.len() - 1)
+    }
+
+    /// Upload an image, e.g. the result of atlas::pack_images, as a sprite texture
+    pub fn add_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: &str,
+    ) -> anyhow::Result<SpriteTexture> {
+        // from_image expects rgba
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let texture = crate::texture::Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(rgba),
+            Some(label),
+        )?;
+        Ok(self.add_texture(device, texture, width, height))
+    }
+
+    /// Draw a sprite this frame, has to be called again every frame
+    pub fn draw(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    /// Draw calls of the last prepare()
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls.len()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.camera.resize(width, height);
+    }
+
+    /// Sort the sprites of this frame, build their quads and upload them with the camera
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut sprites = std::mem::take(&mut self.sprites);
+        // stable, so sprites with the same z and texture keep the order they were drawn in
+        sprites.sort_by_key(|sprite| (sprite.z, sprite.texture));
+
+        let mut vertices = Vec::with_capacity(sprites.len() * VERTICES_PER_SPRITE);
+        self.draw_calls.clear();
+        for sprite in &sprites {
+            let texture = match self.textures.get(sprite.texture.0) {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let start = vertices.len() as u32;
+            Self::push_quad(&mut vertices, sprite, texture);
+            let end = vertices.len() as u32;
+
+            match self.draw_calls.last_mut() {
+                Some(call) if call.texture == sprite.texture.0 => call.vertices.end = end,
+                _ => self.draw_calls.push(SpriteDrawCall {
+                    texture: sprite.texture.0,
+                    vertices: start..end,
+                }),
+            }
+        }
+
+        let needed = vertices.len() / VERTICES_PER_SPRITE;
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+
+        let view_projection: [[f32; 4]; 4] = self.camera.build_view_projection_matrix().into();
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[view_projection]));
+    }
+
+    /// Draw the prepared sprites on top of whatever is in target
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        if self.draw_calls.is_empty() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Sprite Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for call in &self.draw_calls {
+            render_pass.set_bind_group(0, &self.textures[call.texture].bind_group, &[]);
+            render_pass.draw(call.vertices.clone(), 0..1);
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Vertex Buffer"),
+            size: (capacity * VERTICES_PER_SPRITE * std::mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Two triangles covering the scaled region, rotated around the anchor
+    fn push_quad(vertices: &mut Vec<SpriteVertex>, sprite: &Sprite, texture: &SpriteTextureEntry) {
+        let region = sprite.region.unwrap_or(crate::atlas::AtlasRect {
+            x: 0,
+            y: 0,
+            width: texture.width,
+            height: texture.height,
+        });
+        let [u_min, v_min, u_max, v_max] = region.uv(texture.width, texture.height);
+
+        let width = region.width as f32 * sprite.scale.x;
+        let height = region.height as f32 * sprite.scale.y;
+        let (sin, cos) = sprite.rotation.sin_cos();
+        // corner relative to the anchor, rotated and moved to the position
+        let corner = |x: f32, y: f32, u: f32, v: f32| {
+            let x = (x - sprite.anchor[0]) * width;
+            let y = (y - sprite.anchor[1]) * height;
+            SpriteVertex {
+                position: [
+                    sprite.position.x + x * cos - y * sin,
+                    sprite.position.y + x * sin + y * cos,
+                ],
+                texture_coords: [u, v],
+                color: sprite.color,
+            }
+        };
+        // v points down in the texture, y points up in the world
+        let left_bottom = corner(0.0, 0.0, u_min, v_max);
+        let right_bottom = corner(1.0, 0.0, u_max, v_max);
+        let left_top = corner(0.0, 1.0, u_min, v_min);
+        let right_top = corner(1.0, 1.0, u_max, v_min);
+
+        vertices.extend_from_slice(&[left_top, left_bottom, right_bottom, left_top, right_bottom, right_top]);
+    }
+}
@@ -0,0 +1,40 @@
+// Vertex shader
+[[block]]
+struct SpriteUniform {
+	view_projection: mat4x4<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> sprites: SpriteUniform;
+
+struct VertexInput {
+	// already rotated and scaled
+	[[location(0)]] position: vec2<f32>;
+	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn main(in: VertexInput) -> VertexOutput {
+	var out: VertexOutput;
+	out.clip_coordinate = sprites.view_projection * vec4<f32>(in.position, 0.0, 1.0);
+	out.texture_coords = in.texture_coords;
+	out.color = in.color;
+	return out;
+}
+
+// Fragment shader
+[[group(0), binding(0)]]
+var t_sprite: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_sprite: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return textureSample(t_sprite, s_sprite, in.texture_coords) * in.color;
+}
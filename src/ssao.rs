@@ -0,0 +1,676 @@
+use wgpu::util::DeviceExt;
+
+/// Number of samples in the hemisphere kernel, has to match the array size in ssao.wgsl
+pub const KERNEL_SIZE: usize = 32;
+/// Width and height of the tiled rotation noise
+pub const NOISE_SIZE: u32 = 4;
+
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const AO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Startup and runtime parameters of the ambient occlusion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    /// radius of the sample hemisphere in view space units
+    pub radius: f32,
+    /// avoids self occlusion on flat surfaces
+    pub bias: f32,
+    /// exponent applied to the result, bigger => darker
+    pub intensity: f32,
+    /// seed for kernel and noise generation
+    pub seed: u64,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 0.5,
+            bias: 0.025,
+            intensity: 1.0,
+            seed: 0x55A0,
+        }
+    }
+}
+
+/// Small xorshift generator, so kernels are reproducible without an extra dependency
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0
+        Self(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        // upper 24 bits fit exactly into the mantissa
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Sample points in the unit hemisphere around +z
+/// samples get denser towards the origin, as close geometry matters most
+pub fn generate_kernel(size: usize, seed: u64) -> Vec<[f32; 4]> {
+    use cgmath::InnerSpace;
+    let mut rng = Rng::new(seed);
+
+    (0..size)
+        .map(|i| {
+            let direction = cgmath::Vector3::new(
+                rng.next_f32() * 2.0 - 1.0,
+                rng.next_f32() * 2.0 - 1.0,
+                rng.next_f32(),
+            );
+            // a zero vector can't be normalized
+            let direction = if direction.magnitude2() > 0.0 {
+                direction.normalize()
+            } else {
+                cgmath::Vector3::unit_z()
+            };
+
+            let t = i as f32 / size as f32;
+            let scale = 0.1 + 0.9 * t * t;
+            let sample = direction * rng.next_f32() * scale;
+            [sample.x, sample.y, sample.z, 0.0]
+        })
+        .collect()
+}
+
+/// Random rotation vectors around +z, tiled over the screen
+pub fn generate_noise(size: u32, seed: u64) -> Vec<[f32; 4]> {
+    let mut rng = Rng::new(seed);
+
+    (0..size * size)
+        .map(|_| [rng.next_f32() * 2.0 - 1.0, rng.next_f32() * 2.0 - 1.0, 0.0, 0.0])
+        .collect()
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniform {
+    projection: [[f32; 4]; 4],
+    inverse_projection: [[f32; 4]; 4],
+    kernel: [[f32; 4]; KERNEL_SIZE],
+    kernel_size: i32,
+    radius: f32,
+    bias: f32,
+    intensity: f32,
+}
+
+/// Depth + normal prepass, occlusion pass and bilateral blur
+/// the blurred result is bound as group 2 of the main pipeline
+pub struct Ssao {
+    pub settings: SsaoSettings,
+    kernel: Vec<[f32; 4]>,
+
+    depth_texture: crate::texture::Texture,
+    normal_texture: crate::texture::Texture,
+    noise_texture: crate::texture::Texture,
+    ao_texture: crate::texture::Texture,
+    blurred_texture: crate::texture::Texture,
+
+    uniform_buffer: wgpu::Buffer,
+
+    prepass_pipeline: wgpu::RenderPipeline,
+    prepass_bind_group: wgpu::BindGroup,
+
+    ssao_pipeline: wgpu::RenderPipeline,
+    ssao_bind_group_layout: wgpu::BindGroupLayout,
+    ssao_bind_group: wgpu::BindGroup,
+
+    blur_pipeline: wgpu::RenderPipeline,
+    blur_bind_group_layout: wgpu::BindGroupLayout,
+    blur_bind_group: wgpu::BindGroup,
+
+    output_bind_group_layout: wgpu::BindGroupLayout,
+    output_bind_group: wgpu::BindGroup,
+}
+
+impl Ssao {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        camera_uniform_buffer: &wgpu::Buffer,
+        settings: SsaoSettings,
+    ) -> Self {
+        let kernel = generate_kernel(KERNEL_SIZE, settings.seed);
+        let noise_texture = Self::create_noise_texture(device, queue, settings.seed);
+        let (depth_texture, normal_texture, ao_texture, blurred_texture) = Self::create_targets(device, sc_desc);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SSAO Uniform Buffer"),
+            size: std::mem::size_of::<SsaoUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // prepass: only needs the camera
+        let prepass_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("ssao_prepass_bind_group_layout"),
+        });
+
+        let prepass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &prepass_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_uniform_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("ssao_prepass_bind_group"),
+        });
+
+        let prepass_pipeline = Self::create_prepass_pipeline(device, &prepass_bind_group_layout);
+
+        // occlusion: depth, normals, noise and parameters
+        let ssao_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                Self::depth_layout_entry(0),
+                Self::texture_layout_entry(1, false),
+                Self::texture_layout_entry(2, false),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("ssao_bind_group_layout"),
+        });
+
+        let ssao_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "SSAO",
+            include_str!("ssao.wgsl"),
+            &[&ssao_bind_group_layout],
+            AO_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+
+        // blur: raw occlusion and depth
+        let blur_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                Self::texture_layout_entry(0, false),
+                Self::depth_layout_entry(1),
+            ],
+            label: Some("ssao_blur_bind_group_layout"),
+        });
+
+        let blur_pipeline = crate::post::create_fullscreen_pipeline(
+            device,
+            "SSAO Blur",
+            include_str!("ssao_blur.wgsl"),
+            &[&blur_bind_group_layout],
+            AO_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+
+        // output: what the main pipeline samples
+        let output_bind_group_layout = crate::post::create_input_bind_group_layout(device);
+
+        let (ssao_bind_group, blur_bind_group, output_bind_group) = Self::create_bind_groups(
+            device,
+            &ssao_bind_group_layout,
+            &blur_bind_group_layout,
+            &output_bind_group_layout,
+            &depth_texture,
+            &normal_texture,
+            &noise_texture,
+            &ao_texture,
+            &blurred_texture,
+            &uniform_buffer,
+        );
+
+        Self {
+            settings,
+            kernel,
+
+            depth_texture,
+            normal_texture,
+            noise_texture,
+            ao_texture,
+            blurred_texture,
+
+            uniform_buffer,
+
+            prepass_pipeline,
+            prepass_bind_group,
+
+            ssao_pipeline,
+            ssao_bind_group_layout,
+            ssao_bind_group,
+
+            blur_pipeline,
+            blur_bind_group_layout,
+            blur_bind_group,
+
+            output_bind_group_layout,
+            output_bind_group,
+        }
+    }
+
+    /// Layout of the blurred occlusion, for pipelines which want to use it
+    pub fn output_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.output_bind_group_layout
+    }
+
+    pub fn output_bind_group(&self) -> &wgpu::BindGroup {
+        &self.output_bind_group
+    }
+
+    /// Targets always have to match the size of the swap_chain
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        let (depth_texture, normal_texture, ao_texture, blurred_texture) = Self::create_targets(device, sc_desc);
+        self.depth_texture = depth_texture;
+        self.normal_texture = normal_texture;
+        self.ao_texture = ao_texture;
+        self.blurred_texture = blurred_texture;
+
+        let (ssao_bind_group, blur_bind_group, output_bind_group) = Self::create_bind_groups(
+            device,
+            &self.ssao_bind_group_layout,
+            &self.blur_bind_group_layout,
+            &self.output_bind_group_layout,
+            &self.depth_texture,
+            &self.normal_texture,
+            &self.noise_texture,
+            &self.ao_texture,
+            &self.blurred_texture,
+            &self.uniform_buffer,
+        );
+        self.ssao_bind_group = ssao_bind_group;
+        self.blur_bind_group = blur_bind_group;
+        self.output_bind_group = output_bind_group;
+    }
+
+    /// Write projection and settings to the uniform buffer
+    pub fn update(&self, queue: &wgpu::Queue, camera: &crate::camera::Camera) {
+        use cgmath::SquareMatrix;
+        let projection = camera.build_projection_matrix();
+        let inverse_projection = projection.invert().unwrap_or_else(cgmath::Matrix4::identity);
+
+        let mut kernel = [[0.0; 4]; KERNEL_SIZE];
+        kernel.copy_from_slice(&self.kernel);
+
+        let uniform = SsaoUniform {
+            projection: projection.into(),
+            inverse_projection: inverse_projection.into(),
+            kernel,
+            kernel_size: KERNEL_SIZE as i32,
+            radius: self.settings.radius,
+            bias: self.settings.bias,
+            intensity: self.settings.intensity,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Start the depth + normal prepass, the caller only has to bind vertex/index buffers and draw
+    /// Returns None if SSAO is disabled, the output is cleared to "no occlusion" then
+    pub fn begin_prepass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> Option<wgpu::RenderPass<'a>> {
+        if !self.settings.enabled {
+            // white => nothing is occluded
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("SSAO Clear"),
+                color_attachments: &[
+                    wgpu::RenderPassColorAttachment {
+                        view: &self.blurred_texture.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                            store: true,
+                        }
+                    }
+                ],
+                depth_stencil_attachment: None,
+            });
+            return None;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SSAO Prepass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: &self.normal_texture.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 }),
+                        store: true,
+                    }
+                }
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_pipeline(&self.prepass_pipeline);
+        render_pass.set_bind_group(0, &self.prepass_bind_group, &[]);
+        Some(render_pass)
+    }
+
+    /// Compute and blur the occlusion, has to be called after the prepass
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        crate::post::draw_fullscreen(
+            encoder,
+            "SSAO",
+            &self.ssao_pipeline,
+            &[&self.ssao_bind_group],
+            &self.ao_texture.view,
+            wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+        );
+        crate::post::draw_fullscreen(
+            encoder,
+            "SSAO Blur",
+            &self.blur_pipeline,
+            &[&self.blur_bind_group],
+            &self.blurred_texture.view,
+            wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+        );
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> (crate::texture::Texture, crate::texture::Texture, crate::texture::Texture, crate::texture::Texture) {
+        let depth = crate::texture::Texture::create_depth_texture(device, sc_desc, 1, "ssao_depth");
+        let normal = crate::texture::Texture::create_render_target(
+            device, sc_desc.width, sc_desc.height, NORMAL_FORMAT, 1, "ssao_normal",
+        );
+        let ao = crate::texture::Texture::create_render_target(
+            device, sc_desc.width, sc_desc.height, AO_FORMAT, 1, "ssao_raw",
+        );
+        let blurred = crate::texture::Texture::create_render_target(
+            device, sc_desc.width, sc_desc.height, AO_FORMAT, 1, "ssao_blurred",
+        );
+        (depth, normal, ao, blurred)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        ssao_layout: &wgpu::BindGroupLayout,
+        blur_layout: &wgpu::BindGroupLayout,
+        output_layout: &wgpu::BindGroupLayout,
+        depth: &crate::texture::Texture,
+        normal: &crate::texture::Texture,
+        noise: &crate::texture::Texture,
+        ao: &crate::texture::Texture,
+        blurred: &crate::texture::Texture,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> (wgpu::BindGroup, wgpu::BindGroup, wgpu::BindGroup) {
+        let ssao = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: ssao_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&noise.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("ssao_bind_group"),
+        });
+
+        let blur = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: blur_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&ao.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth.view),
+                },
+            ],
+            label: Some("ssao_blur_bind_group"),
+        });
+
+        let output = crate::post::create_input_bind_group(device, output_layout, blurred);
+
+        (ssao, blur, output)
+    }
+
+    fn create_noise_texture(device: &wgpu::Device, queue: &wgpu::Queue, seed: u64) -> crate::texture::Texture {
+        // different seed than the kernel, so both aren't correlated
+        let noise = generate_noise(NOISE_SIZE, seed.wrapping_add(1));
+        let size = wgpu::Extent3d {
+            width: NOISE_SIZE,
+            height: NOISE_SIZE,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("ssao_noise"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            },
+            bytemuck::cast_slice(&noise),
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // only read with textureLoad, but every Texture carries a sampler
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            ..Default::default()
+        });
+
+        crate::texture::Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    fn create_prepass_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("SSAO Prepass Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("ssao_prepass.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("SSAO Prepass Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("SSAO Prepass Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[crate::vertex::Vertex::desc(), crate::instance::InstanceRaw::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format: NORMAL_FORMAT,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                // same rasterization as the main pipeline
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        )
+    }
+
+    fn texture_layout_entry(binding: u32, filterable: bool) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+            },
+            count: None,
+        }
+    }
+
+    fn depth_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Depth,
+            },
+            count: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(sample: &[f32; 4]) -> f32 {
+        (sample[0] * sample[0] + sample[1] * sample[1] + sample[2] * sample[2]).sqrt()
+    }
+
+    #[test]
+    fn same_seed_same_kernel() {
+        assert_eq!(generate_kernel(KERNEL_SIZE, 42), generate_kernel(KERNEL_SIZE, 42));
+        assert_ne!(generate_kernel(KERNEL_SIZE, 42), generate_kernel(KERNEL_SIZE, 43));
+        assert_eq!(generate_noise(NOISE_SIZE, 42), generate_noise(NOISE_SIZE, 42));
+    }
+
+    #[test]
+    fn zero_seed_is_usable() {
+        let mut rng = Rng::new(0);
+        assert_ne!(rng.next_u64(), 0);
+        assert_ne!(generate_kernel(4, 0), vec![[0.0; 4]; 4]);
+    }
+
+    #[test]
+    fn kernel_is_in_the_upper_unit_hemisphere() {
+        for seed in 0..16 {
+            let kernel = generate_kernel(KERNEL_SIZE, seed);
+            assert_eq!(kernel.len(), KERNEL_SIZE);
+            for sample in &kernel {
+                assert!(sample[2] >= 0.0, "{:?} is below the surface", sample);
+                assert!(length(sample) <= 1.0, "{:?} is outside the hemisphere", sample);
+                assert_eq!(sample[3], 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn scale_grows_towards_the_end() {
+        let kernel = generate_kernel(KERNEL_SIZE, SsaoSettings::default().seed);
+        for (i, sample) in kernel.iter().enumerate() {
+            let t = i as f32 / KERNEL_SIZE as f32;
+            assert!(length(sample) <= 0.1 + 0.9 * t * t + 1e-6);
+        }
+
+        let quarter = KERNEL_SIZE / 4;
+        let mean = |samples: &[[f32; 4]]| samples.iter().map(length).sum::<f32>() / samples.len() as f32;
+        assert!(mean(&kernel[..quarter]) < mean(&kernel[KERNEL_SIZE - quarter..]));
+    }
+
+    #[test]
+    fn noise_rotates_around_z() {
+        let noise = generate_noise(NOISE_SIZE, 7);
+        assert_eq!(noise.len(), (NOISE_SIZE * NOISE_SIZE) as usize);
+        for rotation in &noise {
+            assert!(rotation[0] >= -1.0 && rotation[0] < 1.0);
+            assert!(rotation[1] >= -1.0 && rotation[1] < 1.0);
+            assert_eq!(&rotation[2..], &[0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn uniform_floats_are_in_range() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Hemisphere SSAO, see ssao.rs for the kernel and noise generation
+[[block]]
+struct Ssao {
+	projection: mat4x4<f32>;
+	inverse_projection: mat4x4<f32>;
+	kernel: array<vec4<f32>, 32>;
+	kernel_size: i32;
+	radius: f32;
+	bias: f32;
+	intensity: f32;
+};
+
+[[group(0), binding(0)]]
+var t_depth: texture_depth_2d;
+[[group(0), binding(1)]]
+var t_normal: texture_2d<f32>;
+[[group(0), binding(2)]]
+var t_noise: texture_2d<f32>;
+[[group(0), binding(3)]]
+var<uniform> params: Ssao;
+
+// reconstruct the view space position of a pixel from the depth buffer
+fn view_position(coords: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+	let depth = textureLoad(t_depth, coords, 0);
+	let uv = vec2<f32>((f32(coords.x) + 0.5) / f32(size.x), (f32(coords.y) + 0.5) / f32(size.y));
+	// y points up in NDC, but down in texture coordinates
+	let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+	let view = params.inverse_projection * ndc;
+	return view.xyz / view.w;
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(t_depth);
+	let coords = vec2<i32>(i32(in.clip_coordinate.x), i32(in.clip_coordinate.y));
+
+	// nothing to occlude on the background
+	if (textureLoad(t_depth, coords, 0) >= 1.0) {
+		return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+	}
+
+	let position = view_position(coords, size);
+	let normal = normalize(textureLoad(t_normal, coords, 0).xyz);
+	// 4x4 noise texture gets tiled over the screen
+	let noise = textureLoad(t_noise, vec2<i32>(coords.x % 4, coords.y % 4), 0).xyz;
+
+	// random rotation of the hemisphere around the normal
+	let tangent = normalize(noise - normal * dot(noise, normal));
+	let bitangent = cross(normal, tangent);
+	let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+	var occlusion: f32 = 0.0;
+	var i: i32 = 0;
+	loop {
+		if (i >= params.kernel_size) {
+			break;
+		}
+
+		let sample_position = position + (tbn * params.kernel[i].xyz) * params.radius;
+
+		// project the sample to find it on the screen
+		let clip = params.projection * vec4<f32>(sample_position, 1.0);
+		let ndc = clip.xy / clip.w;
+		let sample_coords = vec2<i32>(
+			clamp(i32((ndc.x * 0.5 + 0.5) * f32(size.x)), 0, size.x - 1),
+			clamp(i32((0.5 - ndc.y * 0.5) * f32(size.y)), 0, size.y - 1)
+		);
+		let scene_depth = view_position(sample_coords, size).z;
+
+		// geometry far away from the sample shouldn't occlude it
+		let range = smoothStep(0.0, 1.0, params.radius / abs(position.z - scene_depth));
+		// view space looks down -z, so bigger z is closer
+		occlusion = occlusion + select(0.0, range, scene_depth >= sample_position.z + params.bias);
+
+		continuing {
+			i = i + 1;
+		}
+	}
+
+	let ao = pow(1.0 - occlusion / f32(params.kernel_size), params.intensity);
+	return vec4<f32>(ao, ao, ao, 1.0);
+}
@@ -0,0 +1,45 @@
+// Bilateral 4x4 blur, which removes the noise pattern but keeps edges
+[[group(0), binding(0)]]
+var t_ao: texture_2d<f32>;
+[[group(0), binding(1)]]
+var t_depth: texture_depth_2d;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(t_ao);
+	let coords = vec2<i32>(i32(in.clip_coordinate.x), i32(in.clip_coordinate.y));
+	let center_depth = textureLoad(t_depth, coords, 0);
+
+	var sum: f32 = 0.0;
+	var weights: f32 = 0.0;
+	var y: i32 = -2;
+	loop {
+		if (y >= 2) {
+			break;
+		}
+		var x: i32 = -2;
+		loop {
+			if (x >= 2) {
+				break;
+			}
+
+			let sample_coords = clamp(coords + vec2<i32>(x, y), vec2<i32>(0, 0), size - vec2<i32>(1, 1));
+			let depth = textureLoad(t_depth, sample_coords, 0);
+			// samples at a different depth belong to another surface
+			let weight = 1.0 / (0.0001 + abs(center_depth - depth) * 1000.0);
+			sum = sum + textureLoad(t_ao, sample_coords, 0).r * weight;
+			weights = weights + weight;
+
+			continuing {
+				x = x + 1;
+			}
+		}
+
+		continuing {
+			y = y + 1;
+		}
+	}
+
+	let ao = sum / weights;
+	return vec4<f32>(ao, ao, ao, 1.0);
+}
@@ -0,0 +1,49 @@
+// Depth + view space normal prepass for SSAO
+[[block]]
+struct Uniform {
+view_projection: mat4x4<f32>;
+view: mat4x4<f32>;
+inverse_view_projection: mat4x4<f32>;
+eye: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniform: Uniform;
+
+struct VertexInput {
+	[[location(0)]] position: vec3<f32>;
+	[[location(1)]] texture_coords: vec2<f32>;
+	[[location(2)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+	[[location(5)]] model_0: vec4<f32>;
+	[[location(6)]] model_1: vec4<f32>;
+	[[location(7)]] model_2: vec4<f32>;
+	[[location(8)]] model_3: vec4<f32>;
+	[[location(9)]] normal_0: vec3<f32>;
+	[[location(10)]] normal_1: vec3<f32>;
+	[[location(11)]] normal_2: vec3<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] normal: vec3<f32>;
+};
+
+[[stage(vertex)]]
+fn main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let model_matrix = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	let normal_matrix = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+	let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+
+	var out: VertexOutput;
+	out.clip_coordinate = uniform.view_projection * world_position;
+	out.normal = (uniform.view * vec4<f32>(normal_matrix * model.normal, 0.0)).xyz;
+	return out;
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(normalize(in.normal), 1.0);
+}
@@ -4,71 +4,327 @@ use wgpu::{
 };
 use winit::{dpi::PhysicalPosition, event::*, window::Window, event_loop::{ControlFlow}};
 
+/// Background of the scene, until it's changed in the debug UI
+const CLEAR_COLOR: wgpu::Color = wgpu::Color { r: 0.2, g: 0.5, b: 0.5, a: 1.0 };
+
+/// Built-in meshes, scene files refer to them by these names
+/// has to match the order of State::meshes
+const MESH_NAMES: &[&str] = &["pentagon"];
+
+/// Where the built-in scene gets saved
+const DEFAULT_SCENE_PATH: &str = "scene.ron";
+
+/// Width of the box the name of the selected object is right aligned in, in pixels
+const SELECTION_LABEL_WIDTH: f32 = 300.0;
+/// Distance of the label to the top right corner of the window, in pixels
+const SELECTION_LABEL_MARGIN: f32 = 8.0;
+
+/// Visible instances of one mesh with one material, drawn in a single call
+struct DrawBatch {
+    mesh: usize,
+    material: usize,
+    // range in the instance buffer
+    instances: std::ops::Range<u32>,
+}
+
+/// Where State draws to
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Window(&'a Window),
+    /// a texture of this size, needs neither a window nor a display
+    Offscreen(winit::dpi::PhysicalSize<u32>),
+}
+
+/// Where finished frames go
+enum Output {
+    Window {
+        surface: wgpu::Surface,
+        swap_chain: wgpu::SwapChain,
+    },
+    /// nobody sees it, frames can only be captured
+    Offscreen(wgpu::Texture),
+}
+
+/// Texture a frame gets drawn into, doesn't borrow State while the passes are recorded
+enum Frame {
+    Window(wgpu::SwapChainTexture),
+    Offscreen(wgpu::TextureView),
+}
+
+impl Frame {
+    fn view(&self) -> &wgpu::TextureView {
+        match self {
+            Self::Window(frame) => &frame.view,
+            Self::Offscreen(view) => view,
+        }
+    }
+}
+
+impl Output {
+    /// Format of offscreen targets, captured frames don't need any swizzling
+    const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    fn create_offscreen_texture(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: sc_desc.format,
+            usage: sc_desc.usage,
+        })
+    }
+
+    /// Recreate the swap chain or texture with the new size
+    fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        match self {
+            Self::Window { surface, swap_chain } => *swap_chain = device.create_swap_chain(surface, sc_desc),
+            Self::Offscreen(texture) => *texture = Self::create_offscreen_texture(device, sc_desc),
+        }
+    }
+
+    fn next_frame(&self) -> Result<Frame, wgpu::SwapChainError> {
+        match self {
+            Self::Window { swap_chain, .. } => Ok(Frame::Window(swap_chain.get_current_frame()?.output)),
+            Self::Offscreen(texture) => Ok(Frame::Offscreen(texture.create_view(&wgpu::TextureViewDescriptor::default()))),
+        }
+    }
+}
+
 /// Hold state with important information
 pub struct State {
-    surface: wgpu::Surface,
+    output: Output,
     device: wgpu::Device,
     queue: wgpu::Queue,
 
     sc_desc: wgpu::SwapChainDescriptor,
-    swap_chain: wgpu::SwapChain,
     pub size: winit::dpi::PhysicalSize<u32>,
 
+    // MSAA samples per pixel, already validated against the adapter, always 1 on the deferred path
+    sample_count: u32,
+    // only exists if sample_count > 1, resolved into the scene target
+    multisampled_framebuffer: Option<crate::texture::Texture>,
+    depth_texture: crate::texture::Texture,
+
+    // scene target and everything between it and the swap_chain
+    post_processor: crate::post::PostProcessor,
+    // ambient occlusion, computed before the main pass
+    ssao: crate::ssao::Ssao,
+
+    // forward pipeline, only used if there is no deferred renderer
     render_pipeline: wgpu::RenderPipeline,
+    // only exists if the deferred path was chosen at startup
+    deferred: Option<crate::deferred::DeferredRenderer>,
+
+    // what gets drawn where, mesh attachments index into meshes and materials
+    scene: crate::scene::Scene,
+    meshes: Vec<crate::mesh::Mesh>,
+    materials: Vec<crate::material::Material>,
+    // how the materials were loaded, kept for saving
+    material_descriptions: Vec<crate::scene_file::MaterialDescription>,
+    // file the scene is saved to
+    scene_path: std::path::PathBuf,
 
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    num_indices: u32,
+    // only holds the instances, which survived culling this frame
+    instance_buffer: wgpu::Buffer,
+    // in instances, grows if the scene does
+    instance_capacity: usize,
+    batches: Vec<DrawBatch>,
+    culling_stats: crate::frustum::CullingStats,
 
-    aqua_bind_group: wgpu::BindGroup,
-    aqua_texture: crate::texture::Texture,
+    // filled from the light nodes of the scene every frame
+    lights: crate::light::Lights,
 
+    // game logic, synced into the scene every update
+    world: hecs::World,
+    // extracted from the world, what the frame is rendered with
     camera: crate::camera::Camera,
-    camera_controller: crate::camera_controller::CameraController,
+    clear_color: wgpu::Color,
 
     uniform: crate::uniform::Uniform,
     uniform_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
+
+    frame_stats: crate::frame_stats::FrameStats,
+    // only exists if the device supports timestamp queries
+    gpu_profiler: Option<crate::gpu_profiler::GpuProfiler>,
+    // frame stats on top of the final image
+    overlay: crate::overlay::TextOverlay,
+    show_overlay: bool,
+
+    // lines drawn into the scene, cleared every frame
+    debug_draw: crate::debug_draw::DebugDraw,
+    // draw bounds, normals, lights and cameras of the scene
+    show_debug_shapes: bool,
+    // replaces the shaded scene if it isn't Lit
+    debug_view: crate::debug_view::DebugView,
+    debug_views: crate::debug_view::DebugViews,
+
+    // labels and other text, only exists if a font was loaded
+    text: Option<crate::text::TextRenderer>,
+    // 2D overlays, drawn on top of the text
+    sprites: crate::sprite::SpriteBatch,
+    // drawn while the cursor is grabbed, None if its texture could not be created
+    crosshair: Option<crate::crosshair::Crosshair>,
+
+    // tweaking panels, on top of the overlay
+    ui: crate::ui::DebugUi,
+    // the cursor is released while the ui is open
+    cursor_grabbed: bool,
+    // false once the platform turned out not to support grabbing or moving the cursor
+    cursor_grab_supported: bool,
+    // set by the device's error handler, the renderer has to be rebuilt
+    device_lost: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    cursor_position: PhysicalPosition<f64>,
+
+    // clicked object, picked by casting a ray or reading back the id buffer
+    selected: Option<crate::scene::NodeId>,
+    gpu_picking: bool,
+    picker: crate::picking::IdPicker,
+    // pixel to draw into the id buffer in the next render
+    pick_request: Option<[u32; 2]>,
+    // node of every instance in the instance buffer, in the same order
+    visible_nodes: Vec<crate::scene::NodeId>,
+
+    // handles on the selected object, finished drags go on the command stack
+    gizmo: crate::gizmo::Gizmo,
+    hovered_handle: Option<crate::gizmo::Constraint>,
+    // entity being dragged
+    dragged: Option<hecs::Entity>,
+    commands: crate::command::CommandStack<crate::command::SceneEdit>,
+    modifiers: ModifiersState,
+
+    // copies frames back from the gpu, for screenshots
+    capture: crate::capture::FrameCapture,
+    screenshot_dir: std::path::PathBuf,
+    // Some while recording
+    recorder: Option<crate::recording::Recorder>,
+    recording_output: crate::recording::RecordingOutput,
+    recording_fps: u32,
+
+    // startup configuration, everything is created from it again if the device is lost
+    settings: crate::settings::Settings,
 }
 
 impl State {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, settings: &crate::settings::Settings) -> Result<Self, crate::error::RendererError> {
+        Self::with_scene(Target::Window(window), settings, None).await
+    }
+
+    /// Render into a texture instead of a window, frames can only be read back with capture_frame
+    pub async fn offscreen(
+        size: winit::dpi::PhysicalSize<u32>,
+        settings: &crate::settings::Settings,
+    ) -> Result<Self, crate::error::RendererError> {
+        Self::with_scene(Target::Offscreen(size), settings, None).await
+    }
+
+    /// Like new, but with a scene from memory instead of the one in the settings
+    async fn with_scene(
+        target: Target<'_>,
+        settings: &crate::settings::Settings,
+        scene: Option<(std::path::PathBuf, crate::scene_file::SceneDescription)>,
+    ) -> Result<Self, crate::error::RendererError> {
         // actual screen size
-        let size = window.inner_size();
+        let (size, scale_factor) = match target {
+            Target::Window(window) => (window.inner_size(), window.scale_factor()),
+            Target::Offscreen(size) => (size, 1.0),
+        };
 
         // handle to gpu
         // PRIMARY, VULKAN, DX12, METAL, BROWSER_WEBGPU
         let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
 
         // a surface to draw to
-        let surface = unsafe { instance.create_surface(window) };
+        let surface = match target {
+            Target::Window(window) => Some(unsafe { instance.create_surface(window) }),
+            Target::Offscreen(_) => None,
+        };
+
+        // a software rasterizer like lavapipe or WARP, if asked for one, a GPU would give other images
+        // wgpu 0.9 can't check an enumerated adapter against the surface, every one of them
+        // on a primary backend is expected to present
+        let software_adapter = if settings.software_adapter {
+            let adapter = instance
+                .enumerate_adapters(wgpu::BackendBit::PRIMARY)
+                .find(|adapter| adapter.get_info().device_type == wgpu::DeviceType::Cpu)
+                .ok_or(crate::error::RendererError::NoSoftwareAdapter)?;
+            Some(adapter)
+        } else {
+            None
+        };
 
         // get a physical adapter for the current system
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-            },
-        ).await.unwrap();
+        let adapter = match software_adapter {
+            Some(adapter) => adapter,
+            None => instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: surface.as_ref(),
+                },
+            ).await.ok_or(crate::error::RendererError::NoAdapter)?,
+        };
+        log::info!("Using {:?}", adapter.get_info());
 
         // logical device and command queue to work with
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
-                // no special features required
-                features: wgpu::Features::empty(),
+                // timestamps and line rasterization are optional, gpu profiling is disabled
+                // and the wireframe is drawn by the shader without them
+                // the adapter's own format features are needed to check the multisampled formats
+                features: adapter.features()
+                    & (crate::gpu_profiler::GpuProfiler::FEATURES
+                        | crate::debug_view::DebugViews::FEATURES
+                        | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES),
                 // limits of the adapter
                 limits: wgpu::Limits::default(),
                 label: None,
             },
             None, // Trace path
-        ).await.unwrap();
+        ).await?;
+
+        // presenting reports a lost device as a swap chain error, every other call only here
+        let device_lost = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let lost = device_lost.clone();
+        device.on_uncaptured_error(move |error| {
+            if crate::error::is_device_lost(&error) {
+                lost.store(true, std::sync::atomic::Ordering::Relaxed);
+            } else {
+                // like the default handler, these are bugs
+                log::error!("Graphics error: {}", error);
+                panic!("Handling wgpu errors as fatal");
+            }
+        });
+
+        // not every adapter can multisample every format, the swap chain itself is never multisampled
+        // and neither is the G-buffer of the deferred path
+        let sample_count = match settings.render_path {
+            crate::deferred::RenderPath::Forward => crate::settings::validate_sample_count(
+                &adapter,
+                settings.sample_count,
+                &[crate::post::HDR_FORMAT, crate::texture::Texture::DEPTH_FORMAT],
+            )?,
+            crate::deferred::RenderPath::Deferred => 1,
+        };
 
-        // description of the swap_chain
+        // how swap_chain textures will be stored
+        let format = match &surface {
+            Some(surface) => adapter
+                .get_swap_chain_preferred_format(surface)
+                .ok_or(crate::error::RendererError::IncompatibleSurface)?,
+            None => Output::OFFSCREEN_FORMAT,
+        };
+
+        // description of the swap_chain, or the offscreen texture
         let sc_desc = wgpu::SwapChainDescriptor {
             // how textures will be used
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
-            // how swap_chain textures will be stored
-            format: adapter.get_swap_chain_preferred_format(&surface).unwrap(),
+            format,
             // size of the swap_chain
             width: size.width,
             height: size.height,
@@ -77,58 +333,85 @@ impl State {
         };
 
         // actually create a swap_chain
-        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
-
-        let aqua_bytes = include_bytes!("../img/aqua.png");
-        let aqua_texture = crate::texture::Texture::from_bytes(&device, &queue, aqua_bytes, "aqua").unwrap();
-
-        let texture_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler {
-                            comparison: false,
-                            filtering: true,
-                        },
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
+        let output = match surface {
+            Some(surface) => {
+                let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                Output::Window { surface, swap_chain }
+            },
+            None => Output::Offscreen(Output::create_offscreen_texture(&device, &sc_desc)),
+        };
+
+        let multisampled_framebuffer = Self::create_multisampled_framebuffer(&device, &sc_desc, sample_count);
+        let depth_texture = crate::texture::Texture::create_depth_texture(&device, &sc_desc, sample_count, "depth_texture");
+
+        let color_lut = settings.color_lut.as_ref().and_then(|path| match image::open(path) {
+            Ok(img) => Some(img),
+            Err(error) => {
+                log::warn!("Could not load color grading LUT {:?}: {}", path, error);
+                None
             }
+        });
+        let post_processor = crate::post::PostProcessor::new(
+            &device,
+            &queue,
+            &sc_desc,
+            &settings.post_effects,
+            color_lut.as_ref(),
+            settings.bloom,
         );
 
-        // bind groups can be changed on the fly, as long as they're in the same layout
-        // every texture and sampler needs to be added to a bindgroup
-        let aqua_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&aqua_texture.view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&aqua_texture.sampler),
-                    }
-                ],
-                label: Some("diffuse_bind_group"),
+        let material_bind_group_layout = crate::material::Material::bind_group_layout(&device);
+
+        let loaded = match scene {
+            Some((path, description)) => {
+                let loaded = Self::build_scene(&device, &queue, &material_bind_group_layout, description, &path);
+                Some((path, loaded))
+            },
+            None => settings.scene.as_ref().map(|path| {
+                (path.clone(), Self::load_scene(&device, &queue, &material_bind_group_layout, path))
+            }),
+        };
+        let loaded = loaded.and_then(|(path, loaded)| match loaded {
+            Ok(loaded) => Some((path, loaded)),
+            Err(error) => {
+                log::error!("Using the built-in scene, {}", error);
+                None
             }
-        );
+        });
+        let (mut scene, materials, material_descriptions, ambient, scene_path) = match loaded {
+            Some((path, (description, scene, materials))) => {
+                (scene, materials, description.materials, description.ambient, path)
+            },
+            None => {
+                let aqua_bytes = include_bytes!("../img/aqua.png");
+                let aqua_texture = crate::texture::Texture::from_bytes(&device, &queue, aqua_bytes, "aqua")
+                    .map_err(|error| crate::error::RendererError::Texture { name: "aqua".to_string(), error })?;
+                let params = crate::material::MaterialParams::default();
+                let materials = vec![crate::material::Material::new(
+                    &device,
+                    &material_bind_group_layout,
+                    "aqua",
+                    aqua_texture,
+                    params,
+                )];
+                let material_descriptions = vec![crate::scene_file::MaterialDescription {
+                    name: "aqua".to_string(),
+                    texture: "img/aqua.png".into(),
+                    specular: params.specular,
+                    shininess: params.shininess,
+                    emissive: params.emissive,
+                }];
+                (Self::default_scene(), materials, material_descriptions, 0.3, DEFAULT_SCENE_PATH.into())
+            },
+        };
+        scene.update_world_transforms();
+
+        let aspect = size.width as f32 / size.height as f32;
+        let mut world = hecs::World::new();
+        crate::ecs::spawn_scene(&mut world, &scene, aspect);
 
-        let camera = crate::camera::Camera {
+        // the user flies the active camera of the scene, or a default one
+        let default_camera = crate::camera::Camera {
             // x, y, z
             // 1 up, 2 back
             // +z is out of the screen
@@ -137,13 +420,17 @@ impl State {
             target: (0.0, 0.0, 0.0).into(),
             // which way is up
             up: cgmath::Vector3::unit_y(),
-            aspect: size.width as f32 / size.height as f32,
+            aspect,
             fovy: 45.0,
             znear: 0.1,
             zfar: 100.0,
         };
-
-        let camera_controller = crate::camera_controller::CameraController::new(0.05);
+        let camera_entity = crate::ecs::active_camera(&world).unwrap_or_else(|| {
+            world.spawn((default_camera, crate::ecs::Previous(default_camera), crate::ecs::ActiveCamera))
+        });
+        // the entity was just found or spawned, it can't be missing
+        let _ = world.insert_one(camera_entity, crate::camera_controller::CameraController::new(0.05));
+        let camera = crate::ecs::render_extraction_system(&world, &mut scene, 1.0).unwrap_or(default_camera);
 
         let mut uniform = crate::uniform::Uniform::new();
         uniform.update_view_proj(&camera);
@@ -156,11 +443,14 @@ impl State {
             }
         );
 
+        let ssao = crate::ssao::Ssao::new(&device, &queue, &sc_desc, &uniform_buffer, settings.ssao);
+
         let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStage::VERTEX,
+                    // fragment needs the camera for lighting
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -183,12 +473,23 @@ impl State {
             label: Some("uniform_bind_group"),
         });
 
-        // load shader file
+        // the deferred path shades every pixel once, so it can afford many more lights
+        let light_capacity = match settings.render_path {
+            crate::deferred::RenderPath::Forward => crate::light::MAX_FORWARD_LIGHTS,
+            crate::deferred::RenderPath::Deferred => crate::light::MAX_DEFERRED_LIGHTS,
+        };
+        let lights = crate::light::Lights::new(&device, scene.point_lights().collect(), ambient, light_capacity);
+
+        // load shader file, lighting is shared with the deferred path
         let shader = device.create_shader_module(
             &wgpu::ShaderModuleDescriptor {
                 label: Some("Shader"),
                 flags: wgpu::ShaderFlags::all(),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+                source: wgpu::ShaderSource::Wgsl(format!(
+                    "{}\n{}",
+                    include_str!("lights.wgsl"),
+                    include_str!("shader.wgsl"),
+                ).into()),
             }
         );
 
@@ -196,8 +497,10 @@ impl State {
             &wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layput"),
                 bind_group_layouts: &[
-                    &texture_bind_group_layout,
+                    &material_bind_group_layout,
                     &uniform_bind_group_layout,
+                    ssao.output_bind_group_layout(),
+                    lights.bind_group_layout(),
                 ],
                 push_constant_ranges: &[],
             }
@@ -213,16 +516,16 @@ impl State {
                     // function name in shader.wgsl for [[stage(vertex)]]
                     entry_point: "main",
                     // specify memory layout
-                    buffers: &[crate::vertex::Vertex::desc()],
+                    buffers: &[crate::vertex::Vertex::desc(), crate::instance::InstanceRaw::desc()],
 
                 },
                 // needed to sotre color data to swap_chain
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: "main",
-                    // setup of color outputs
+                    // setup of color outputs, the scene is drawn in HDR
                     targets: &[wgpu::ColorTargetState {
-                        format: sc_desc.format,
+                        format: crate::post::HDR_FORMAT,
                         blend: Some(wgpu::BlendState::REPLACE),
                         write_mask: wgpu::ColorWrite::ALL,
                     }],
@@ -237,60 +540,663 @@ impl State {
                     clamp_depth: false,
                     conservative: false,
                 },
-                depth_stencil: None,
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    // draw pixels front to back
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
                 multisample: wgpu::MultisampleState {
-                    // only use 1 sample => no extra sampling
-                    count: 1,
+                    // must match the sample count of the attachments
+                    count: sample_count,
                     // use all
                     mask: !0,
-                    // not using anti aliasing
+                    // not using alpha to coverage
                     alpha_to_coverage_enabled: false,
                 }
             }
         );
 
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(crate::vertex::VERTICES),
-                usage: wgpu::BufferUsage::VERTEX,
-            }
-        );
+        let meshes = vec![
+            crate::mesh::Mesh::new(&device, "pentagon", crate::vertex::VERTICES, crate::vertex::INDICES),
+        ];
 
-        let index_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(crate::vertex::INDICES),
-                usage: wgpu::BufferUsage::INDEX,
-            }
-        );
+        // room for every instance, culling only ever makes the list shorter
+        let instance_capacity = scene.mesh_instances().count().max(1);
+        let instance_buffer = Self::create_instance_buffer(&device, instance_capacity);
 
-        let num_indices = crate::vertex::INDICES.len() as u32;
+        let deferred = match settings.render_path {
+            crate::deferred::RenderPath::Forward => None,
+            crate::deferred::RenderPath::Deferred => Some(crate::deferred::DeferredRenderer::new(
+                &device,
+                &sc_desc,
+                &material_bind_group_layout,
+                &uniform_bind_group_layout,
+                ssao.output_bind_group_layout(),
+                lights.bind_group_layout(),
+            )),
+        };
 
-        Self {
-            surface,
+        let overlay = crate::overlay::TextOverlay::new(&device, &queue, sc_desc.format);
+        let gpu_profiler = crate::gpu_profiler::GpuProfiler::new(&device, &queue);
+        let ui = crate::ui::DebugUi::new(&device, size, scale_factor, sc_desc.format);
+        // drawn into whatever the scene was drawn into
+        let debug_draw = crate::debug_draw::DebugDraw::new(&device, &uniform_bind_group_layout, sample_count);
+        let debug_views = crate::debug_view::DebugViews::new(&device, &uniform_bind_group_layout, sample_count, &meshes);
+        let picker = crate::picking::IdPicker::new(&device, &uniform_bind_group_layout, &sc_desc);
+        let capture = crate::capture::FrameCapture::new(&device, &sc_desc);
+        let mut sprites = crate::sprite::SpriteBatch::new(&device, sc_desc.format, sc_desc.width, sc_desc.height);
+        let crosshair = match crate::crosshair::Crosshair::new(&mut sprites, &device, &queue) {
+            Ok(crosshair) => Some(crosshair),
+            Err(error) => {
+                log::warn!("Running without crosshair: {}", error);
+                None
+            }
+        };
+        let text = settings.font.as_ref().and_then(|path| match crate::text::load_font(path) {
+            Ok(font) => Some(crate::text::TextRenderer::new(&device, sc_desc.format, font)),
+            Err(error) => {
+                log::warn!("Text rendering disabled, could not load font {:?}: {}", path, error);
+                None
+            }
+        });
+
+        Ok(Self {
+            output,
             device,
             queue,
 
             sc_desc, // saved, so we can create a new swap_chain later
-            swap_chain,
             size,
 
+            sample_count,
+            multisampled_framebuffer,
+            depth_texture,
+
+            post_processor,
+            ssao,
+
             render_pipeline,
+            deferred,
+
+            scene,
+            meshes,
+            materials,
+            material_descriptions,
+            scene_path,
 
-            vertex_buffer,
-            index_buffer,
-            num_indices,
+            instance_buffer,
+            instance_capacity,
+            batches: Vec::new(),
+            culling_stats: crate::frustum::CullingStats::default(),
 
-            aqua_bind_group,
-            aqua_texture,
+            lights,
 
+            world,
             camera,
-            camera_controller,
+            clear_color: CLEAR_COLOR,
 
             uniform,
             uniform_buffer,
             uniform_bind_group,
+
+            frame_stats: crate::frame_stats::FrameStats::default(),
+            gpu_profiler,
+            overlay,
+            show_overlay: settings.show_stats,
+
+            debug_draw,
+            show_debug_shapes: false,
+            debug_view: crate::debug_view::DebugView::default(),
+            debug_views,
+
+            text,
+            sprites,
+            crosshair,
+
+            ui,
+            // grabbed by the first update, unless the ui is open
+            cursor_grabbed: false,
+            cursor_grab_supported: true,
+            device_lost,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+
+            selected: None,
+            gpu_picking: settings.gpu_picking,
+            picker,
+            pick_request: None,
+            visible_nodes: Vec::new(),
+
+            gizmo: crate::gizmo::Gizmo::new(),
+            hovered_handle: None,
+            dragged: None,
+            commands: crate::command::CommandStack::new(),
+            modifiers: ModifiersState::empty(),
+
+            capture,
+            screenshot_dir: settings.screenshot_dir.clone(),
+            recorder: None,
+            recording_output: settings.recording.clone(),
+            recording_fps: settings.recording_fps,
+
+            settings: settings.clone(),
+        })
+    }
+
+    /// Whether the device was lost outside of presenting, rebuild() has to be called then
+    pub fn device_lost(&self) -> bool {
+        self.device_lost.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Create every gpu resource again, after the device was lost
+    /// the scene is rebuilt from its description, like it would be saved, runtime settings are kept
+    /// entities are spawned again, so selection and undo history are gone
+    pub async fn rebuild(&mut self, window: &Window) -> Result<(), crate::error::RendererError> {
+        // whatever is still on the old device is lost
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish();
+        }
+
+        let scene = (self.scene_path.clone(), self.scene_description());
+        let mut state = Self::with_scene(Target::Window(window), &self.settings, Some(scene)).await?;
+        *state.bloom_settings_mut() = *self.post_processor.bloom_settings();
+        // the chain is the same, it's built from the same settings
+        for (index, (effect, enabled)) in self.post_processor.effects().enumerate() {
+            state.post_processor.set_effect(index, *effect);
+            state.post_processor.set_enabled(index, enabled);
+        }
+        state.ssao.settings = self.ssao.settings;
+        state.clear_color = self.clear_color;
+        state.show_overlay = self.show_overlay;
+        state.show_debug_shapes = self.show_debug_shapes;
+        state.debug_view = self.debug_view;
+        state.gizmo.mode = self.gizmo.mode;
+        state.gizmo.space = self.gizmo.space;
+        state.gizmo.snapping = self.gizmo.snapping;
+        state.ui.visible = self.ui.visible;
+        state.cursor_grab_supported = self.cursor_grab_supported;
+        // the built-in scene has no file of its own, keep saving to the same place
+        state.scene_path = self.scene_path.clone();
+
+        *self = state;
+        log::info!("Rebuilt the renderer on a new device");
+        Ok(())
+    }
+
+    /// Grid of pentagons below one root node, most of them outside of the initial view,
+    /// a few colored lights around the origin and the starting camera
+    fn default_scene() -> crate::scene::Scene {
+        use cgmath::Rotation3;
+        use crate::scene::{CameraAttachment, LightAttachment, MeshAttachment, Scene, Transform};
+        const PER_ROW: i32 = 10;
+        const SPACING: f32 = 1.2;
+
+        let mut scene = Scene::new();
+
+        let grid = scene.add_node("pentagons", Transform::identity());
+        for y in 0..PER_ROW {
+            for x in 0..PER_ROW {
+                let transform = Transform {
+                    translation: cgmath::Vector3::new(
+                        (x - PER_ROW / 2) as f32 * SPACING,
+                        (y - PER_ROW / 2) as f32 * SPACING,
+                        -((x + y) % 3) as f32,
+                    ),
+                    rotation: cgmath::Quaternion::from_angle_z(cgmath::Deg((x * 36) as f32)),
+                    ..Transform::identity()
+                };
+                let node = scene.add_child(grid, &format!("pentagon_{}_{}", x, y), transform).unwrap();
+                scene.node_mut(node).unwrap().mesh = Some(MeshAttachment { mesh: 0, material: 0 });
+            }
+        }
+
+        let lights = scene.add_node("lights", Transform::identity());
+        let default_lights = [
+            ("key", [1.0, 1.0, 1.0], LightAttachment { color: [1.0, 0.9, 0.8], intensity: 1.5, radius: 5.0 }),
+            ("fill", [-1.5, 0.5, 1.0], LightAttachment { color: [0.3, 0.5, 1.0], intensity: 1.0, radius: 4.0 }),
+            ("rim", [0.0, -1.0, 0.5], LightAttachment { color: [1.0, 0.3, 0.2], intensity: 1.0, radius: 3.0 }),
+        ];
+        for (name, position, light) in default_lights.iter() {
+            let node = scene.add_child(lights, name, Transform::from_translation((*position).into())).unwrap();
+            scene.node_mut(node).unwrap().light = Some(*light);
+        }
+
+        // 2 back, +z is out of the screen
+        let camera = scene.add_node("camera", Transform::from_translation((0.0, 0.0, 2.0).into()));
+        scene.node_mut(camera).unwrap().camera = Some(CameraAttachment { fovy: 45.0, znear: 0.1, zfar: 100.0 });
+
+        scene
+    }
+
+    /// Read a scene file, resolve its references and load the textures of its materials
+    /// texture paths are relative to the scene file
+    fn load_scene(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        material_bind_group_layout: &wgpu::BindGroupLayout,
+        path: &std::path::Path,
+    ) -> Result<
+        (crate::scene_file::SceneDescription, crate::scene::Scene, Vec<crate::material::Material>),
+        crate::scene_file::SceneLoadError,
+    > {
+        let description = crate::scene_file::SceneDescription::load(path)?;
+        Self::build_scene(device, queue, material_bind_group_layout, description, path)
+    }
+
+    /// Resolve the references of a scene description and load the textures of its materials
+    fn build_scene(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        material_bind_group_layout: &wgpu::BindGroupLayout,
+        description: crate::scene_file::SceneDescription,
+        path: &std::path::Path,
+    ) -> Result<
+        (crate::scene_file::SceneDescription, crate::scene::Scene, Vec<crate::material::Material>),
+        crate::scene_file::SceneLoadError,
+    > {
+        let scene = description.build(MESH_NAMES)?;
+
+        let directory = path.parent().unwrap_or_else(|| std::path::Path::new(""));
+        let materials = description.materials
+            .iter()
+            .map(|material| {
+                let texture_path = directory.join(&material.texture);
+                let texture_error = |message: String| crate::scene_file::SceneLoadError::Texture {
+                    material: material.name.clone(),
+                    path: texture_path.clone(),
+                    message,
+                };
+
+                let img = image::open(&texture_path).map_err(|error| texture_error(error.to_string()))?;
+                // from_image expects rgba
+                let img = image::DynamicImage::ImageRgba8(img.to_rgba8());
+                let texture = crate::texture::Texture::from_image(device, queue, &img, Some(&material.name))
+                    .map_err(|error| texture_error(error.to_string()))?;
+
+                Ok(crate::material::Material::new(
+                    device,
+                    material_bind_group_layout,
+                    &material.name,
+                    texture,
+                    material.params(),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((description, scene, materials))
+    }
+
+    /// Write the scene back to the file it was loaded from, including runtime changes
+    pub fn save_scene(&mut self) -> anyhow::Result<()> {
+        self.scene_description().save(&self.scene_path)
+    }
+
+    /// The scene as it would be saved, including runtime changes like where the camera went
+    fn scene_description(&mut self) -> crate::scene_file::SceneDescription {
+        crate::ecs::camera_write_back_system(&mut self.world, &mut self.scene);
+        let materials = self.material_descriptions
+            .iter()
+            .zip(&self.materials)
+            .map(|(description, material)| crate::scene_file::MaterialDescription {
+                specular: material.params.specular,
+                shininess: material.params.shininess,
+                emissive: material.params.emissive,
+                ..description.clone()
+            })
+            .collect();
+
+        crate::scene_file::SceneDescription::from_scene(&self.scene, MESH_NAMES, materials, self.lights.ambient)
+    }
+
+    /// Entities of the scene, changes are picked up in the next update()
+    #[allow(dead_code)]
+    pub fn world_mut(&mut self) -> &mut hecs::World {
+        &mut self.world
+    }
+
+    /// CPU frame and pass timings
+    #[allow(dead_code)]
+    pub fn frame_stats(&self) -> &crate::frame_stats::FrameStats {
+        &self.frame_stats
+    }
+
+    /// GPU time of every pass, a few frames old, None if the device can't measure it
+    pub fn gpu_timings(&self) -> Option<&[(&'static str, std::time::Duration)]> {
+        self.gpu_profiler.as_ref().map(|profiler| profiler.results())
+    }
+
+    /// Objects drawn and culled in the last frame
+    #[allow(dead_code)]
+    pub fn culling_stats(&self) -> crate::frustum::CullingStats {
+        self.culling_stats
+    }
+
+    /// Save one of the next frames as a PNG in the screenshot directory, with everything on screen
+    pub fn screenshot(&mut self) {
+        self.capture.request(crate::capture::CaptureKind::Screenshot);
+    }
+
+    /// Record every following frame, until stop_recording
+    pub fn start_recording(&mut self) {
+        if self.recorder.is_some() {
+            return;
+        }
+        match crate::recording::Recorder::start(
+            &self.recording_output,
+            self.recording_fps,
+            self.sc_desc.width,
+            self.sc_desc.height,
+        ) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(error) => log::error!("Could not start recording: {}", error),
+        }
+    }
+
+    /// Wait for the frames still on the gpu and finish writing them
+    pub fn stop_recording(&mut self) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let frames = self.capture.finish(&self.device);
+        self.handle_captures(frames);
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish();
+        }
+    }
+
+    /// Render a frame and wait until it's read back, e.g. for comparing it with a reference image
+    pub fn capture_frame(&mut self) -> Result<crate::capture::CapturedFrame, wgpu::SwapChainError> {
+        // frames in flight could take the readback this frame needs
+        let frames = self.capture.finish(&self.device);
+        self.handle_captures(frames);
+
+        self.capture.request(crate::capture::CaptureKind::Image);
+        self.render()?;
+        let (images, frames) = self.capture
+            .finish(&self.device)
+            .into_iter()
+            .partition::<Vec<_>, _>(|frame| frame.kind == crate::capture::CaptureKind::Image);
+        self.handle_captures(frames);
+        // the capture is always drawn, all readbacks were free
+        Ok(images.into_iter().next().expect("captured frame was not read back"))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Simulated time per frame while recording, the game loop's clock should follow it
+    pub fn recording_frame_time(&self) -> Option<std::time::Duration> {
+        self.recorder.as_ref().map(|recorder| recorder.frame_time())
+    }
+
+    fn handle_captures(&mut self, frames: Vec<crate::capture::CapturedFrame>) {
+        for frame in frames {
+            match frame.kind {
+                crate::capture::CaptureKind::Screenshot => {
+                    let path = self.screenshot_dir.join(crate::capture::timestamped_name("screenshot"));
+                    crate::capture::save_png(frame, path);
+                },
+                crate::capture::CaptureKind::Recording { frame: index } => {
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.write(index, frame);
+                    }
+                },
+                // capture_frame waits for these itself
+                crate::capture::CaptureKind::Image => {},
+            }
+        }
+    }
+
+    /// Object clicked last, None if the click hit nothing
+    #[allow(dead_code)]
+    pub fn selected(&self) -> Option<crate::scene::NodeId> {
+        self.selected
+    }
+
+    /// Ray through a pixel of the window, from the camera into the scene
+    pub fn cursor_ray(&self, position: [f32; 2]) -> Option<crate::picking::Ray> {
+        crate::picking::Ray::from_screen(
+            position,
+            self.sc_desc.width,
+            self.sc_desc.height,
+            &self.camera.build_view_projection_matrix(),
+        )
+    }
+
+    /// Select the object under a pixel, right away with a ray cast
+    /// or a few frames later with the id buffer
+    pub fn pick(&mut self, position: [f32; 2]) {
+        if self.gpu_picking {
+            self.pick_request = Some([position[0] as u32, position[1] as u32]);
+            return;
+        }
+        let hit = self.cursor_ray(position)
+            .and_then(|ray| crate::picking::raycast(&self.scene, &self.meshes, &ray));
+        self.select(hit.map(|hit| hit.node));
+    }
+
+    fn select(&mut self, node: Option<crate::scene::NodeId>) {
+        self.selected = node;
+        match node.and_then(|node| self.scene.node(node)) {
+            Some(node) => log::info!("Selected {}", node.name),
+            None => log::info!("Selected nothing"),
+        }
+    }
+
+    /// Apply an edit of the scene, so it can be undone
+    pub fn edit(&mut self, edit: crate::command::SceneEdit) {
+        let mut target = crate::command::EditTarget {
+            world: &mut self.world,
+            scene: &mut self.scene,
+            materials: &mut self.materials,
+            ambient: &mut self.lights.ambient,
+        };
+        self.commands.execute(edit, &mut target);
+    }
+
+    /// Revert the last edit, false if there is nothing to undo
+    /// a gizmo drag in progress ends first
+    pub fn undo(&mut self) -> bool {
+        self.end_gizmo_drag();
+        let mut target = crate::command::EditTarget {
+            world: &mut self.world,
+            scene: &mut self.scene,
+            materials: &mut self.materials,
+            ambient: &mut self.lights.ambient,
+        };
+        self.commands.undo(&mut target)
+    }
+
+    /// Apply the last undone edit again, false if there is nothing to redo
+    /// a gizmo drag in progress ends first
+    pub fn redo(&mut self) -> bool {
+        self.end_gizmo_drag();
+        let mut target = crate::command::EditTarget {
+            world: &mut self.world,
+            scene: &mut self.scene,
+            materials: &mut self.materials,
+            ambient: &mut self.lights.ambient,
+        };
+        self.commands.redo(&mut target)
+    }
+
+    /// Undo history of the scene edits
+    #[allow(dead_code)]
+    pub fn commands(&self) -> &crate::command::CommandStack<crate::command::SceneEdit> {
+        &self.commands
+    }
+
+    /// Remove the selected object together with its children, so it can be undone
+    fn delete_selected(&mut self) {
+        let entity = match self.selected.and_then(|selected| crate::ecs::entity_of(&self.world, selected)) {
+            Some(entity) => entity,
+            None => return,
+        };
+        // the removed object comes back where the drag started
+        self.cancel_gizmo_drag();
+        let tree = match crate::ecs::copy_tree(&self.world, &self.scene, entity) {
+            Some(tree) => tree,
+            None => {
+                log::warn!("Cameras can't be removed");
+                return;
+            },
+        };
+        self.edit(crate::command::SceneEdit::Despawn(tree));
+        self.select(None);
+    }
+
+    /// Copy the selected object with its parent, transform, mesh and light, but without children
+    /// the copy gets selected, so the gizmo moves it away from the original
+    fn duplicate_selected(&mut self) {
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => return,
+        };
+        let entity = match crate::ecs::entity_of(&self.world, selected) {
+            Some(entity) => entity,
+            None => return,
+        };
+        self.end_gizmo_drag();
+        let parent = self.scene.node(selected)
+            .and_then(|node| node.parent())
+            .and_then(|parent| crate::ecs::entity_of(&self.world, parent));
+        let (name, transform, mesh, light) = match self.world.query_one_mut::<(
+            &crate::ecs::Name,
+            &crate::ecs::Transform,
+            Option<&crate::ecs::MeshRenderer>,
+            Option<&crate::ecs::Light>,
+        )>(entity) {
+            Ok((name, transform, mesh, light)) => (format!("{} copy", name.0), *transform, mesh.copied(), light.copied()),
+            Err(_) => return,
+        };
+
+        let copy = crate::ecs::spawn(&mut self.world, &mut self.scene, &name, transform, parent);
+        if let Some(mesh) = mesh {
+            let _ = self.world.insert_one(copy, mesh);
+        }
+        if let Some(light) = light {
+            let _ = self.world.insert_one(copy, light);
+        }
+        // already spawned, undo removes it again
+        if let Some(tree) = crate::ecs::copy_tree(&self.world, &self.scene, copy) {
+            self.commands.push(crate::command::SceneEdit::Spawn(tree));
+        }
+        let node = self.world.get::<crate::ecs::SceneNode>(copy).map(|node| node.0).ok();
+        self.select(node);
+    }
+
+    /// Entity, world transform and world transform of the parent of the selected object
+    fn selected_object(&self) -> Option<(hecs::Entity, cgmath::Matrix4<f32>, cgmath::Matrix4<f32>)> {
+        use cgmath::SquareMatrix;
+        let id = self.selected?;
+        let node = self.scene.node(id)?;
+        let entity = crate::ecs::entity_of(&self.world, id)?;
+        let parent = node.parent()
+            .and_then(|parent| self.scene.node(parent))
+            .map_or_else(cgmath::Matrix4::identity, |parent| *parent.world_transform());
+        Some((entity, *node.world_transform(), parent))
+    }
+
+    fn gizmo_size(&self, world: &cgmath::Matrix4<f32>) -> f32 {
+        crate::gizmo::Gizmo::size(self.camera.eye, cgmath::Point3::from_homogeneous(world.w))
+    }
+
+    /// Start dragging the gizmo handle under the pixel, false if there is none
+    fn begin_gizmo_drag(&mut self, position: [f32; 2]) -> bool {
+        let (entity, world, parent) = match self.selected_object() {
+            Some(object) => object,
+            None => return false,
+        };
+        let ray = match self.cursor_ray(position) {
+            Some(ray) => ray,
+            None => return false,
+        };
+        let constraint = match self.gizmo.hit(&ray, &world, self.gizmo_size(&world)) {
+            Some(constraint) => constraint,
+            None => return false,
+        };
+        let local = match self.world.get::<crate::ecs::Transform>(entity) {
+            Ok(transform) => *transform,
+            Err(_) => return false,
+        };
+        if !self.gizmo.begin_drag(&ray, constraint, local, &world, &parent) {
+            return false;
+        }
+        self.dragged = Some(entity);
+        self.commands.begin_transaction("Drag gizmo");
+        true
+    }
+
+    /// Move the dragged object to where the cursor is now
+    fn update_gizmo_drag(&mut self) {
+        let entity = match self.dragged {
+            Some(entity) => entity,
+            None => return,
+        };
+        let after = match self.cursor_ray(self.pick_position()).and_then(|ray| self.gizmo.drag(&ray)) {
+            Some(after) => after,
+            None => return,
+        };
+        let before = match self.world.get::<crate::ecs::Transform>(entity) {
+            Ok(transform) => *transform,
+            Err(_) => return,
+        };
+        // the edits of every frame get merged, a drag, which didn't move, leaves no undo step
+        if after != before {
+            self.edit(crate::command::SceneEdit::Transform { entity, before, after });
+        }
+    }
+
+    /// Let go of the handle, the whole drag becomes one undo step
+    fn end_gizmo_drag(&mut self) {
+        if self.dragged.take().is_some() {
+            self.gizmo.end_drag();
+            self.commands.commit_transaction();
+        }
+    }
+
+    /// Put the dragged object back to where the drag started
+    fn cancel_gizmo_drag(&mut self) {
+        if self.dragged.take().is_some() {
+            self.gizmo.end_drag();
+            let mut target = crate::command::EditTarget {
+                world: &mut self.world,
+                scene: &mut self.scene,
+                materials: &mut self.materials,
+                ambient: &mut self.lights.ambient,
+            };
+            self.commands.cancel_transaction(&mut target);
+        }
+    }
+
+    /// Stop grabbing the cursor, for good if the platform can't do it at all
+    /// otherwise the next update tries again, e.g. X11 refuses while the window isn't viewable yet
+    /// the camera still turns with the free cursor until it leaves the window
+    fn release_cursor(&mut self, window: &winit::window::Window, error: winit::error::ExternalError) {
+        match error {
+            winit::error::ExternalError::NotSupported(_) => {
+                log::warn!("Running without cursor grab, the platform doesn't support it: {}", error);
+                self.cursor_grab_supported = false;
+            },
+            winit::error::ExternalError::Os(_) => {
+                log::debug!("Could not grab the cursor, retrying next frame: {}", error);
+            },
+        }
+        self.cursor_grabbed = false;
+        // it may have been grabbed before moving it failed
+        let _ = window.set_cursor_grab(false);
+        window.set_cursor_visible(true);
+    }
+
+    /// Where a click picks, the middle of the window while the cursor is grabbed
+    fn pick_position(&self) -> [f32; 2] {
+        if self.cursor_grabbed {
+            [self.size.width as f32 / 2.0, self.size.height as f32 / 2.0]
+        } else {
+            [self.cursor_position.x as f32, self.cursor_position.y as f32]
         }
     }
 
@@ -300,13 +1206,112 @@ impl State {
             self.size = new_size;
             self.sc_desc.width = new_size.width;
             self.sc_desc.height = new_size.height;
-            self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+            self.output.resize(&self.device, &self.sc_desc);
+            // attachments always have to match the size of the swap_chain
+            self.multisampled_framebuffer = Self::create_multisampled_framebuffer(&self.device, &self.sc_desc, self.sample_count);
+            self.depth_texture = crate::texture::Texture::create_depth_texture(&self.device, &self.sc_desc, self.sample_count, "depth_texture");
+            self.post_processor.resize(&self.device, &self.sc_desc);
+            self.ssao.resize(&self.device, &self.sc_desc);
+            self.sprites.resize(new_size.width, new_size.height);
+            self.picker.resize(&self.device, &self.sc_desc);
+            self.capture.resize(&self.device, &self.sc_desc);
+            if let Some(deferred) = &mut self.deferred {
+                deferred.resize(&self.device, &self.sc_desc);
+            }
         }
     }
 
+    /// Create the MSAA color target, if multisampling is enabled at all
+    fn create_multisampled_framebuffer(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        sample_count: u32,
+    ) -> Option<crate::texture::Texture> {
+        if sample_count > 1 {
+            Some(crate::texture::Texture::create_render_target(
+                device,
+                sc_desc.width,
+                sc_desc.height,
+                crate::post::HDR_FORMAT,
+                sample_count,
+                "multisampled_framebuffer",
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<crate::instance::InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Bloom parameters, changes are picked up in the next update()
+    pub fn bloom_settings_mut(&mut self) -> &mut crate::bloom::BloomSettings {
+        self.post_processor.bloom_settings_mut()
+    }
+
+    /// SSAO parameters, changes are picked up in the next update()
+    #[allow(dead_code)]
+    pub fn ssao_settings_mut(&mut self) -> &mut crate::ssao::SsaoSettings {
+        &mut self.ssao.settings
+    }
+
+    /// Draw text in this frame, has to be called every frame before render()
+    /// does nothing if no font was loaded
+    #[allow(dead_code)]
+    pub fn queue_text(
+        &mut self,
+        text: &str,
+        space: crate::text::TextSpace,
+        options: crate::text::LayoutOptions,
+        color: [f32; 4],
+    ) {
+        if let Some(renderer) = &mut self.text {
+            renderer.queue(text, space, options, color);
+        }
+    }
+
+    /// Lines for this frame, they have to be drawn again every frame before render()
+    #[allow(dead_code)]
+    pub fn debug_draw_mut(&mut self) -> &mut crate::debug_draw::DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// 2D sprites on top of the scene, they have to be drawn again every frame before render()
+    #[allow(dead_code)]
+    pub fn sprite_batch_mut(&mut self) -> &mut crate::sprite::SpriteBatch {
+        &mut self.sprites
+    }
+
+    /// Let the debug UI look at an event first, true if it was used up by the UI
+    /// and shouldn't reach input()
+    pub fn ui_event<T>(&mut self, event: &Event<T>) -> bool {
+        self.ui.handle_event(event)
+    }
+
     /// Process input of the WindowEvent
     pub fn input(&mut self, event: &WindowEvent, control_flow: &mut ControlFlow) {
+        // the controllers need these as well
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor_position = *position,
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            _ => {},
+        }
         match event {
+            // cancel a gizmo drag instead of closing
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                    ..
+                },
+                ..
+            } if self.dragged.is_some() => self.cancel_gizmo_drag(),
             WindowEvent::CloseRequested |
             WindowEvent::KeyboardInput {
                 input: KeyboardInput {
@@ -324,71 +1329,712 @@ impl State {
             WindowEvent::ScaleFactorChanged {new_inner_size, ..} => {
                 self.resize(**new_inner_size);
             },
-            _ => self.camera_controller.input(event)
+            // toggle bloom
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::B),
+                    ..
+                },
+                ..
+            } => {
+                let bloom = self.post_processor.bloom_settings_mut();
+                bloom.enabled = !bloom.enabled;
+            },
+            // toggle the frame stats overlay
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F3),
+                    ..
+                },
+                ..
+            } => self.show_overlay = !self.show_overlay,
+            // toggle the debug shapes
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F2),
+                    ..
+                },
+                ..
+            } => self.show_debug_shapes = !self.show_debug_shapes,
+            // cycle through the debug views
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F4),
+                    ..
+                },
+                ..
+            } => {
+                self.debug_view = self.debug_view.next();
+                log::info!("Debug view: {}", self.debug_view.name());
+            },
+            // toggle the debug ui
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F1),
+                    ..
+                },
+                ..
+            } => {
+                self.ui.visible = !self.ui.visible;
+                // releases happening while the ui is open never reach the camera
+                for (_, controller) in self.world.query_mut::<&mut crate::ecs::CameraController>() {
+                    controller.release();
+                }
+            },
+            // save a screenshot
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F12),
+                    ..
+                },
+                ..
+            } => self.screenshot(),
+            // start or stop recording
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F11),
+                    ..
+                },
+                ..
+            } => if self.is_recording() {
+                self.stop_recording();
+            } else {
+                self.start_recording();
+            },
+            // save the scene
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F5),
+                    ..
+                },
+                ..
+            } => match self.save_scene() {
+                Ok(()) => log::info!("Saved scene to {:?}", self.scene_path),
+                Err(error) => log::error!("Could not save scene to {:?}: {}", self.scene_path, error),
+            },
+            // grab a gizmo handle or select what's under the cursor
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let position = self.pick_position();
+                if !self.begin_gizmo_drag(position) {
+                    self.pick(position);
+                }
+            },
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => self.end_gizmo_drag(),
+            // gizmo mode, space and snapping
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key @ VirtualKeyCode::Key1)
+                        | Some(key @ VirtualKeyCode::Key2)
+                        | Some(key @ VirtualKeyCode::Key3),
+                    ..
+                },
+                ..
+            } => {
+                self.gizmo.mode = match key {
+                    VirtualKeyCode::Key1 => crate::gizmo::GizmoMode::Translate,
+                    VirtualKeyCode::Key2 => crate::gizmo::GizmoMode::Rotate,
+                    _ => crate::gizmo::GizmoMode::Scale,
+                };
+            },
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::X),
+                    ..
+                },
+                ..
+            } => self.gizmo.space = self.gizmo.space.toggled(),
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::N),
+                    ..
+                },
+                ..
+            } => self.gizmo.snapping.enabled = !self.gizmo.snapping.enabled,
+            // undo and redo edits
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Z),
+                    ..
+                },
+                ..
+            } if self.modifiers.ctrl() => {
+                self.undo();
+            },
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Y),
+                    ..
+                },
+                ..
+            } if self.modifiers.ctrl() => {
+                self.redo();
+            },
+            // remove or copy the selected object
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Delete),
+                    ..
+                },
+                ..
+            } => self.delete_selected(),
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::D),
+                    ..
+                },
+                ..
+            } if self.modifiers.ctrl() => self.duplicate_selected(),
+            // the camera stays put while the ui is open
+            _ if self.ui.visible => {},
+            _ => crate::ecs::input_system(&mut self.world, event)
         }
     }
 
-    /// Update State before render()
-    pub fn update(&mut self, window: &mut winit::window::Window) {
-        // reposition camera
-        self.camera_controller.update_camera(&mut self.camera);
-        // always move cursor back to center after controller update,
-        // so we don't loose it
-        window.set_cursor_position(PhysicalPosition::new(
-            self.size.width / 2,
-            self.size.height / 2,
-        )).unwrap();
+    /// Advance the game logic by one step, runs at the fixed simulation rate
+    pub fn fixed_update(&mut self) {
+        crate::ecs::store_previous_system(&mut self.world);
+        crate::ecs::camera_system(&mut self.world);
+    }
+
+    /// Update State before render(), once per frame after the fixed updates
+    /// tick.alpha blends between the previous and the current fixed update
+    pub fn update(&mut self, window: &mut winit::window::Window, tick: &crate::game_loop::Tick) {
+        // free the cursor for the ui, grab it again once it's closed
+        let grab = !self.ui.visible && self.cursor_grab_supported;
+        if self.cursor_grabbed != grab {
+            self.cursor_grabbed = grab;
+            window.set_cursor_visible(!grab);
+            if let Err(error) = window.set_cursor_grab(grab) {
+                self.release_cursor(window, error);
+            }
+        }
+        if self.cursor_grabbed {
+            // always move cursor back to center after controller update,
+            // so we don't loose it
+            let center = PhysicalPosition::new(self.size.width / 2, self.size.height / 2);
+            if let Err(error) = window.set_cursor_position(center) {
+                self.release_cursor(window, error);
+            }
+        }
+        self.update_frame(tick);
+    }
+
+    /// Everything update does, which doesn't need a window, e.g. for offscreen rendering
+    pub fn update_frame(&mut self, tick: &crate::game_loop::Tick) {
+        self.frame_stats.record_frame(tick.frame_time);
+        let alpha = tick.alpha;
+        // before the propagation, so the scene shows where the object was dragged to
+        self.update_gizmo_drag();
+        // blend between the last two fixed updates, ends with the scene and camera the renderer uses
+        crate::ecs::transform_propagation_system(&mut self.world, &mut self.scene, alpha);
+        if let Some(camera) = crate::ecs::render_extraction_system(&self.world, &mut self.scene, alpha) {
+            self.camera = camera;
+        }
+        // tweaks of the panels, most of them get uploaded right below
+        if let Some(ctx) = self.ui.begin_frame() {
+            // a slider drag is one undo step, every frame of it gets merged into it
+            let dragging = ctx.is_using_pointer();
+            if dragging && !self.commands.in_transaction() {
+                self.commands.begin_transaction("Edit in UI");
+            }
+            let snapshot = crate::command::SceneSnapshot::take(&self.world, &self.materials, self.lights.ambient);
+            crate::ui::camera_panel(&ctx, &mut self.world);
+            crate::ui::lights_panel(&ctx, &mut self.world, &mut self.lights.ambient);
+            crate::ui::materials_panel(&ctx, &mut self.materials);
+            crate::ui::render_panel(
+                &ctx,
+                &mut self.post_processor,
+                &mut self.ssao.settings,
+                &mut self.clear_color,
+                &mut self.show_overlay,
+                &mut self.show_debug_shapes,
+                &mut self.debug_view,
+            );
+            crate::ui::gizmo_panel(&ctx, &mut self.gizmo);
+            // the panels changed the values directly, record what they did
+            for edit in snapshot.changes(&self.world, &self.materials, self.lights.ambient) {
+                self.commands.push(edit);
+            }
+            // a gizmo drag has a transaction of its own
+            if !dragging && self.commands.in_transaction() && self.dragged.is_none() {
+                self.commands.commit_transaction();
+            }
+            match crate::ui::history_panel(&ctx, &self.commands) {
+                Some(crate::ui::HistoryAction::Undo) => {
+                    self.undo();
+                },
+                Some(crate::ui::HistoryAction::Redo) => {
+                    self.redo();
+                },
+                Some(crate::ui::HistoryAction::Clear) => {
+                    self.end_gizmo_drag();
+                    self.commands.clear();
+                },
+                Some(crate::ui::HistoryAction::SetLimit(limit)) => self.commands.set_limit(limit),
+                None => {},
+            }
+            self.ui.end_frame(&self.device, &self.queue);
+        }
         // update projection for uniform buffer
         self.uniform.update_view_proj(&self.camera);
         // write uniform buffer to queue
         self.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+        // effect parameters might have changed
+        self.post_processor.update(&self.queue);
+        self.ssao.update(&self.queue, &self.camera);
+        self.lights.point_lights = self.scene.point_lights().collect();
+        self.lights.update(&self.queue);
+        for material in &self.materials {
+            material.update(&self.queue);
+        }
+        // only upload what the camera can see
+        self.prepare_draws();
+
+        // pick up gpu timings of earlier frames
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.poll(&self.device);
+        }
+
+        if self.show_overlay {
+            let mut text = self.frame_stats.summary();
+            for (name, duration) in self.gpu_timings().unwrap_or_default() {
+                text += &format!("GPU {} {:.2} MS\n", name, duration.as_secs_f32() * 1000.0);
+            }
+            text += &format!("DRAWN {}/{}", self.culling_stats.visible, self.culling_stats.total);
+            self.overlay.set_text(&self.queue, self.sc_desc.width, self.sc_desc.height, &text);
+        }
+
+        if let Some(renderer) = &mut self.text {
+            // name the lights while they can be edited
+            if self.ui.visible {
+                let options = crate::text::LayoutOptions {
+                    align: crate::text::Align::Center,
+                    ..Default::default()
+                };
+                for (_, (name, light, global)) in self.world
+                    .query::<(&crate::ecs::Name, &crate::ecs::Light, &crate::ecs::GlobalTransform)>()
+                    .iter()
+                {
+                    let position = cgmath::Point3::from_homogeneous(global.0.w);
+                    let [r, g, b] = light.color;
+                    renderer.queue(&name.0, crate::text::TextSpace::World(position), options, [r, g, b, 1.0]);
+                }
+            }
+            // name of the selected object in the top right corner
+            let scene = &self.scene;
+            if let Some(node) = self.selected.and_then(|selected| scene.node(selected)) {
+                let options = crate::text::LayoutOptions {
+                    max_width: Some(SELECTION_LABEL_WIDTH),
+                    align: crate::text::Align::Right,
+                    ..Default::default()
+                };
+                let position = [
+                    self.sc_desc.width as f32 - SELECTION_LABEL_WIDTH - SELECTION_LABEL_MARGIN,
+                    SELECTION_LABEL_MARGIN,
+                ];
+                renderer.queue(&node.name, crate::text::TextSpace::Screen(position), options, [1.0; 4]);
+            }
+            renderer.prepare(&self.queue, &self.camera, self.sc_desc.width, self.sc_desc.height);
+        }
+        if let Some(picked) = self.picker.poll(&self.device) {
+            self.select(picked);
+        }
+        let frames = self.capture.poll(&self.device);
+        self.handle_captures(frames);
+        if self.recorder.is_some() {
+            // a skipped frame would be a jump in the video, wait for the gpu instead
+            if self.capture.is_full() {
+                let frames = self.capture.finish(&self.device);
+                self.handle_captures(frames);
+            }
+            if let Some(recorder) = &mut self.recorder {
+                self.capture.request(crate::capture::CaptureKind::Recording { frame: recorder.next_frame() });
+            }
+        }
+        if self.show_debug_shapes {
+            self.draw_debug_shapes();
+        }
+        self.draw_selection();
+        self.debug_draw.prepare(&self.device, &self.queue);
+        // after the selection, which finds the hovered gizmo handle
+        if let Some(crosshair) = self.crosshair.as_ref().filter(|_| self.cursor_grabbed) {
+            crosshair.draw(&mut self.sprites, self.hovered_handle.is_some());
+        }
+        self.sprites.prepare(&self.device, &self.queue);
+    }
+
+    /// Bounds and normals of every mesh instance, lights with their radius and the other cameras
+    fn draw_debug_shapes(&mut self) {
+        use cgmath::SquareMatrix;
+        use crate::debug_draw::{GREEN, WHITE, YELLOW};
+        let lines = &mut self.debug_draw;
+
+        lines.depth_tested.grid(cgmath::Point3::new(0.0, -0.5, 0.0), 10.0, 10, [0.5, 0.5, 0.5, 1.0]);
+        lines.on_top.axes(&cgmath::Matrix4::identity(), 0.5);
+
+        for (_, attachment, model) in self.scene.mesh_instances() {
+            if let Some(mesh) = self.meshes.get(attachment.mesh) {
+                lines.depth_tested.oriented_box(&mesh.aabb, &model, YELLOW);
+                lines.depth_tested.normals(mesh, &model, 0.1, GREEN);
+            }
+        }
+
+        for light in self.scene.point_lights() {
+            let [r, g, b] = light.color;
+            let position: cgmath::Point3<f32> = light.position.into();
+            lines.on_top.sphere(position, 0.05, [r, g, b, 1.0]);
+            lines.depth_tested.sphere(position, light.radius, [r, g, b, 0.3]);
+        }
+
+        // the one rendered with isn't visible anyway
+        for (_, camera) in self.world.query::<&crate::ecs::Camera>().iter() {
+            lines.depth_tested.frustum(&camera.build_view_projection_matrix(), WHITE);
+        }
+    }
+
+    /// Box and gizmo around the selected object, visible through everything else
+    fn draw_selection(&mut self) {
+        // only a drawn gizmo has handles to hover
+        self.hovered_handle = None;
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => return,
+        };
+        // separate fields, the scene stays borrowed while drawing into debug_draw
+        let (scene, meshes, lines) = (&self.scene, &self.meshes, &mut self.debug_draw);
+        let node = match scene.node(selected) {
+            Some(node) => node,
+            None => return,
+        };
+        if let Some(mesh) = node.mesh.and_then(|attachment| meshes.get(attachment.mesh)) {
+            lines.on_top.oriented_box(&mesh.aabb, node.world_transform(), crate::debug_draw::WHITE);
+        }
+
+        let (_, world, _) = match self.selected_object() {
+            Some(object) => object,
+            None => return,
+        };
+        let size = self.gizmo_size(&world);
+        self.hovered_handle = if self.gizmo.is_dragging() {
+            None
+        } else {
+            self.cursor_ray(self.pick_position()).and_then(|ray| self.gizmo.hit(&ray, &world, size))
+        };
+        self.gizmo.draw(&mut self.debug_draw.on_top, &world, size, self.hovered_handle);
+    }
+
+    /// Frustum cull all mesh nodes of the scene, group the visible ones by mesh and material
+    /// and write them to the instance buffer, one contiguous range per batch
+    fn prepare_draws(&mut self) {
+        let frustum = crate::frustum::Frustum::from_matrix(&self.camera.build_view_projection_matrix());
+
+        let mut total = 0;
+        // sorted, so batches are drawn in a stable order
+        let mut groups: std::collections::BTreeMap<(usize, usize), Vec<(crate::scene::NodeId, crate::instance::InstanceRaw)>> =
+            std::collections::BTreeMap::new();
+        for (node, attachment, model) in self.scene.mesh_instances() {
+            total += 1;
+            // nodes referencing missing meshes or materials are never drawn
+            let mesh = match self.meshes.get(attachment.mesh) {
+                Some(mesh) if attachment.material < self.materials.len() => mesh,
+                _ => continue,
+            };
+            // cheap sphere test first, the box is tighter
+            if frustum.intersects_sphere(&mesh.sphere.transformed(&model))
+                && frustum.intersects_aabb(&mesh.aabb.transformed(&model))
+            {
+                groups
+                    .entry((attachment.mesh, attachment.material))
+                    .or_default()
+                    .push((node, crate::instance::InstanceRaw::from_matrix(&model)));
+            }
+        }
+
+        let mut visible = Vec::new();
+        self.batches.clear();
+        self.visible_nodes.clear();
+        for ((mesh, material), instances) in groups {
+            let start = visible.len() as u32;
+            for (node, instance) in instances {
+                self.visible_nodes.push(node);
+                visible.push(instance);
+            }
+            self.batches.push(DrawBatch {
+                mesh,
+                material,
+                instances: start..visible.len() as u32,
+            });
+        }
+
+        self.culling_stats = crate::frustum::CullingStats {
+            total,
+            visible: visible.len(),
+        };
+
+        if visible.len() > self.instance_capacity {
+            self.instance_capacity = visible.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(&self.device, self.instance_capacity);
+        }
+        self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&visible));
+    }
+
+    /// Bind instance data and draw every batch, the other bind groups have to be set already
+    /// material_group is the bind group index of the material, None if the pipeline doesn't use it
+    fn draw_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, material_group: Option<u32>) {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for batch in &self.batches {
+            if let Some(index) = material_group {
+                render_pass.set_bind_group(index, &self.materials[batch.material].bind_group, &[]);
+            }
+            self.meshes[batch.mesh].draw(render_pass, batch.instances.clone());
+        }
     }
 
     /// Generate commands for gpu to render to frame
     pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
         // current screen
-        let frame = self.swap_chain
-            .get_current_frame()?
-            .output;
+        let frame = self.output.next_frame()?;
         // encoder to talk to the gpu
         let mut encoder = self.device
             .create_command_encoder(
                 &wgpu::CommandEncoderDescriptor {
                     label: Some("Render Encoder"),
                 });
+        // CPU time of recording every pass
+        let mut timer = crate::frame_stats::PassTimer::start();
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.begin_frame();
+        }
+
+        // depth + normals for the ambient occlusion
+        self.begin_gpu_scope(&mut encoder, "SSAO");
+        if let Some(mut prepass) = self.ssao.begin_prepass(&mut encoder) {
+            self.draw_scene(&mut prepass, None);
+        }
+        self.ssao.render(&mut encoder);
+        self.end_gpu_scope(&mut encoder);
+        self.frame_stats.record_pass("SSAO", timer.lap());
+
+        self.begin_gpu_scope(&mut encoder, "SCENE");
+        if self.debug_view != crate::debug_view::DebugView::Lit {
+            self.render_debug_view(&mut encoder);
+        } else {
+            match &self.deferred {
+                Some(deferred) => self.render_deferred(&mut encoder, deferred),
+                None => self.render_forward(&mut encoder),
+            }
+        }
+        self.end_gpu_scope(&mut encoder);
+        self.frame_stats.record_pass("SCENE", timer.lap());
+
+        // ids of the visible instances, for a pick requested since the last frame
+        if let Some(position) = self.pick_request.take() {
+            self.begin_gpu_scope(&mut encoder, "PICK");
+            let nodes = self.visible_nodes.clone();
+            if !self.picker.request(position, nodes) {
+                log::info!("Ignoring pick, the last one is still in flight");
+            }
+            if let Some(mut pick_pass) = self.picker.begin_pass(&mut encoder) {
+                pick_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                self.draw_scene(&mut pick_pass, None);
+            }
+            self.picker.end_pass(&mut encoder);
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("PICK", timer.lap());
+        }
+
+        // debug lines go into the scene, with the depth it was drawn with
+        self.begin_gpu_scope(&mut encoder, "DEBUG");
+        let (view, resolve_target, depth) = self.scene_attachments();
+        self.debug_draw.render(&mut encoder, view, resolve_target, depth, &self.uniform_bind_group);
+        self.end_gpu_scope(&mut encoder);
+        self.frame_stats.record_pass("DEBUG", timer.lap());
+
+        // a captured frame is drawn offscreen and copied to the screen at the end
+        let capture_view = self.capture.begin_frame(&self.device);
+        let output = capture_view.as_ref().unwrap_or_else(|| frame.view());
+
+        // post-processing and final blit to the current screen
+        self.begin_gpu_scope(&mut encoder, "POST");
+        self.post_processor.render(&mut encoder, output);
+        self.end_gpu_scope(&mut encoder);
+        self.frame_stats.record_pass("POST", timer.lap());
+
+        if self.show_overlay {
+            self.begin_gpu_scope(&mut encoder, "OVERLAY");
+            self.overlay.render(&mut encoder, output);
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("OVERLAY", timer.lap());
+        }
+
+        if self.text.is_some() {
+            self.begin_gpu_scope(&mut encoder, "TEXT");
+            if let Some(renderer) = &self.text {
+                renderer.render(&mut encoder, output);
+            }
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("TEXT", timer.lap());
+        }
+
+        if self.sprites.draw_calls() > 0 {
+            self.begin_gpu_scope(&mut encoder, "SPRITES");
+            self.sprites.render(&mut encoder, output);
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("SPRITES", timer.lap());
+        }
+
+        if self.ui.visible {
+            self.begin_gpu_scope(&mut encoder, "UI");
+            self.ui.render(&self.device, &self.queue, &mut encoder, output, &self.sc_desc);
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("UI", timer.lap());
+        }
+
+        if capture_view.is_some() {
+            self.begin_gpu_scope(&mut encoder, "CAPTURE");
+            self.capture.end_frame(&mut encoder, frame.view());
+            self.end_gpu_scope(&mut encoder);
+            self.frame_stats.record_pass("CAPTURE", timer.lap());
+        }
+
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.end_frame(&mut encoder);
+        }
+
+        // submit finished command buffers
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.after_submit();
+        }
+        self.picker.after_submit();
+        self.capture.after_submit();
+        self.frame_stats.record_pass("SUBMIT", timer.lap());
+
+        Ok(())
+    }
+
+    fn begin_gpu_scope(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.begin_scope(encoder, name);
+        }
+    }
+
+    fn end_gpu_scope(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.end_scope(encoder);
+        }
+    }
+
+    /// Color, resolve and depth attachment to draw on top of the scene with
+    fn scene_attachments(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>, &wgpu::TextureView) {
+        let scene_view = self.post_processor.scene_view();
+        match (&self.deferred, &self.multisampled_framebuffer) {
+            (Some(deferred), _) => (scene_view, None, deferred.depth_view()),
+            (None, Some(framebuffer)) => (&framebuffer.view, Some(scene_view), &self.depth_texture.view),
+            (None, None) => (scene_view, None, &self.depth_texture.view),
+        }
+    }
+
+    /// Draw the scene with the shader of the current debug view instead of shading it
+    fn render_debug_view(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (view, resolve_target, depth) = self.scene_attachments();
+        let mut render_pass = match self.debug_views.begin_pass(encoder, view, resolve_target, depth, self.debug_view) {
+            Some(render_pass) => render_pass,
+            None => return,
+        };
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        if !self.debug_views.uses_expanded_meshes(self.debug_view) {
+            self.draw_scene(&mut render_pass, None);
+            return;
+        }
+        // same batches, but every triangle has its own vertices
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for batch in &self.batches {
+            if let Some((vertex_buffer, vertex_count)) = self.debug_views.expanded_mesh(batch.mesh) {
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.draw(0..vertex_count, batch.instances.clone());
+            }
+        }
+    }
+
+    /// Draw and shade the scene in one pass into the scene target
+    fn render_forward(&self, encoder: &mut wgpu::CommandEncoder) {
+        // with MSAA draw to the multisampled framebuffer and resolve into the scene target,
+        // otherwise draw to the scene target directly
+        let scene_view = self.post_processor.scene_view();
+        let (view, resolve_target) = match &self.multisampled_framebuffer {
+            Some(framebuffer) => (&framebuffer.view, Some(scene_view)),
+            None => (scene_view, None),
+        };
         // create a render_pass
         let mut render_pass = encoder.begin_render_pass(
             &wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[
                     wgpu::RenderPassColorAttachment {
-                        view: &frame.view, // draw to current screen
-                        resolve_target: None, // no multisampling yet
+                        view,
+                        resolve_target,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(
-                                wgpu::Color { r: 0.2, g: 0.5, b: 0.5, a: 1.0 }),
+                            load: wgpu::LoadOp::Clear(self.clear_color),
                             store: true,
                         }
                     }
                 ],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
             });
 
         render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.set_bind_group(0, &self.aqua_bind_group, &[]);
         render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-        // draw triangle
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
-        //render_pass.draw(0..self.num_vertices, 0..1);
+        render_pass.set_bind_group(2, self.ssao.output_bind_group(), &[]);
+        render_pass.set_bind_group(3, self.lights.bind_group(), &[]);
+        // draw everything visible, material by material
+        self.draw_scene(&mut render_pass, Some(0));
+    }
 
-        // drop so encoder isn't borrowed mutually anymore
-        drop(render_pass);
+    /// Fill the G-buffer, then shade it into the scene target
+    fn render_deferred(&self, encoder: &mut wgpu::CommandEncoder, deferred: &crate::deferred::DeferredRenderer) {
+        let mut geometry_pass = deferred.begin_geometry_pass(encoder);
+        geometry_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        self.draw_scene(&mut geometry_pass, Some(0));
+        drop(geometry_pass);
 
-        // submit finished command buffers
-        self.queue.submit(std::iter::once(encoder.finish()));
-
-        Ok(())
+        deferred.render_lighting(
+            encoder,
+            self.post_processor.scene_view(),
+            self.clear_color,
+            &self.uniform_bind_group,
+            self.ssao.output_bind_group(),
+            self.lights.bind_group(),
+        );
     }
 }
@@ -0,0 +1,727 @@
+use ab_glyph::{Font, ScaleFont};
+use wgpu::util::DeviceExt;
+
+/// Edge length of the glyph atlas, it's cleared once it runs full
+const ATLAS_SIZE: u32 = 1024;
+/// Glyphs that fit into the vertex buffer per frame, the rest gets cut off
+const MAX_GLYPHS: usize = 8192;
+/// Two triangles per glyph
+const VERTICES_PER_GLYPH: usize = 6;
+
+/// Horizontal alignment of every line within the text box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutOptions {
+    /// height of the font in pixels
+    pub size: f32,
+    /// lines get wrapped at whitespace (or within words that don't fit at all) to stay below it
+    pub max_width: Option<f32>,
+    pub align: Align,
+    /// multiple of the font's line height
+    pub line_spacing: f32,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            max_width: None,
+            align: Align::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// Glyph placed by layout()
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub id: ab_glyph::GlyphId,
+    /// pen position on the baseline, in pixels from the top left corner of the text box
+    pub position: [f32; 2],
+}
+
+/// Result of layout(), whitespace isn't part of glyphs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// size of the text box in pixels, the width is max_width if there is one
+    pub width: f32,
+    pub height: f32,
+    pub lines: usize,
+}
+
+struct LineGlyph {
+    id: ab_glyph::GlyphId,
+    x: f32,
+    advance: f32,
+    whitespace: bool,
+}
+
+/// Place every character of text, lines are separated by '\n'
+/// characters are mapped to glyphs one by one, there is no shaping of combined characters
+pub fn layout<F: Font>(font: &F, text: &str, options: &LayoutOptions) -> TextLayout {
+    let scaled = font.as_scaled(options.size);
+    let line_height = (scaled.height() + scaled.line_gap()) * options.line_spacing;
+
+    let mut lines: Vec<Vec<LineGlyph>> = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<LineGlyph> = Vec::new();
+        let mut pen = 0.0;
+        let mut previous: Option<ab_glyph::GlyphId> = None;
+        for c in paragraph.chars().filter(|c| !c.is_control()) {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                pen += scaled.kern(previous, id);
+            }
+            let advance = scaled.h_advance(id);
+            let whitespace = c.is_whitespace();
+
+            // whitespace may hang over the edge, it's trimmed anyway
+            let overflows = options.max_width.is_some_and(|max_width| pen + advance > max_width);
+            if overflows && !whitespace && !line.is_empty() {
+                // break after the last whitespace, or in the middle of a word that doesn't fit at all
+                let split = line.iter().rposition(|glyph| glyph.whitespace).map_or(line.len(), |index| index + 1);
+                let rest = line.split_off(split);
+                lines.push(line);
+                let shift = rest.first().map_or(pen, |glyph| glyph.x);
+                line = rest.into_iter().map(|glyph| LineGlyph { x: glyph.x - shift, ..glyph }).collect();
+                pen -= shift;
+            }
+
+            line.push(LineGlyph { id, x: pen, advance, whitespace });
+            pen += advance;
+            previous = Some(id);
+        }
+        lines.push(line);
+    }
+
+    // trailing whitespace doesn't count towards the width
+    let line_width = |line: &[LineGlyph]| {
+        line.iter()
+            .rev()
+            .find(|glyph| !glyph.whitespace)
+            .map_or(0.0, |glyph| glyph.x + glyph.advance)
+    };
+    let width = options.max_width.unwrap_or_else(|| {
+        lines.iter().map(|line| line_width(line)).fold(0.0, f32::max)
+    });
+
+    let mut glyphs = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let offset = match options.align {
+            Align::Left => 0.0,
+            Align::Center => (width - line_width(line)) / 2.0,
+            Align::Right => width - line_width(line),
+        };
+        let baseline = scaled.ascent() + index as f32 * line_height;
+        glyphs.extend(line.iter().filter(|glyph| !glyph.whitespace).map(|glyph| PositionedGlyph {
+            id: glyph.id,
+            position: [offset + glyph.x, baseline],
+        }));
+    }
+
+    TextLayout {
+        glyphs,
+        width,
+        height: (lines.len() - 1) as f32 * line_height + scaled.height(),
+        lines: lines.len(),
+    }
+}
+
+/// Glyph rasterized into the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachedGlyph {
+    pub rect: crate::atlas::AtlasRect,
+    /// pixels from the pen position to the top left corner of the bitmap
+    pub offset: [f32; 2],
+}
+
+/// There is no room left for a new glyph, the atlas has to be cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasFull;
+
+/// Single channel coverage of every glyph used so far, rasterized on the cpu
+pub struct GlyphAtlas {
+    packer: crate::atlas::ShelfPacker,
+    /// one byte per texel
+    pub pixels: Vec<u8>,
+    // by glyph and size in quarter pixels, None for glyphs without outline like spaces
+    glyphs: std::collections::HashMap<(ab_glyph::GlyphId, u32), Option<CachedGlyph>>,
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    pub fn new(size: u32) -> Self {
+        Self {
+            // one texel between glyphs, so they don't bleed into each other
+            packer: crate::atlas::ShelfPacker::new(size, size, 1),
+            pixels: vec![0; (size * size) as usize],
+            glyphs: std::collections::HashMap::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.packer.size()
+    }
+
+    /// Look up a glyph, rasterize it first if it isn't in the atlas yet
+    /// None if the glyph has nothing to draw
+    pub fn glyph<F: Font>(
+        &mut self,
+        font: &F,
+        id: ab_glyph::GlyphId,
+        size: f32,
+    ) -> Result<Option<CachedGlyph>, AtlasFull> {
+        let key = (id, (size * 4.0).round() as u32);
+        if let Some(cached) = self.glyphs.get(&key) {
+            return Ok(*cached);
+        }
+
+        let outlined = match font.outline_glyph(id.with_scale(size)) {
+            Some(outlined) => outlined,
+            None => {
+                self.glyphs.insert(key, None);
+                return Ok(None);
+            },
+        };
+        // already on whole pixels
+        let bounds = outlined.px_bounds();
+        let rect = self.packer
+            .pack(bounds.width() as u32, bounds.height() as u32)
+            .ok_or(AtlasFull)?;
+
+        let atlas_width = self.packer.size().0;
+        let pixels = &mut self.pixels;
+        outlined.draw(|x, y, coverage| {
+            if x < rect.width && y < rect.height {
+                let index = ((rect.y + y) * atlas_width + rect.x + x) as usize;
+                pixels[index] = (coverage.min(1.0) * 255.0).round() as u8;
+            }
+        });
+
+        let cached = CachedGlyph {
+            rect,
+            offset: [bounds.min.x, bounds.min.y],
+        };
+        self.glyphs.insert(key, Some(cached));
+        self.dirty = true;
+        Ok(Some(cached))
+    }
+
+    /// Forget every glyph, they get rasterized again when they're used next
+    pub fn clear(&mut self) {
+        self.packer.clear();
+        self.glyphs.clear();
+        self.pixels.iter_mut().for_each(|pixel| *pixel = 0);
+        self.dirty = true;
+    }
+
+    /// True once after the pixels changed, they have to be uploaded then
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
+    }
+}
+
+/// Where a text gets drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextSpace {
+    /// top left corner of the text box in pixels
+    Screen([f32; 2]),
+    /// text box centered above a point, the size stays the same in pixels
+    World(cgmath::Point3<f32>),
+}
+
+/// Read a TTF or OTF font file
+pub fn load_font(path: &std::path::Path) -> anyhow::Result<ab_glyph::FontArc> {
+    let bytes = std::fs::read(path)?;
+    Ok(ab_glyph::FontArc::try_from_vec(bytes)?)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextVertex {
+    // w is 1 in world space and 0 in screen space
+    anchor: [f32; 4],
+    // pixels from the anchor
+    offset: [f32; 2],
+    texture_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl TextVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniform {
+    view_projection: [[f32; 4]; 4],
+    // zw unused
+    screen_size: [f32; 4],
+}
+
+struct QueuedText {
+    text: String,
+    space: TextSpace,
+    options: LayoutOptions,
+    color: [f32; 4],
+}
+
+/// Draws all text queued during a frame in a single call, on top of everything
+pub struct TextRenderer {
+    font: ab_glyph::FontArc,
+    atlas: GlyphAtlas,
+    atlas_texture: wgpu::Texture,
+    atlas_bind_group: wgpu::BindGroup,
+
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    queued: Vec<QueuedText>,
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, font: ab_glyph::FontArc) -> Self {
+        let atlas = GlyphAtlas::new(ATLAS_SIZE);
+        let (width, height) = atlas.size();
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph_atlas"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // coverage only
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let atlas_bind_group_layout = crate::post::create_input_bind_group_layout(device);
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("glyph_atlas_bind_group"),
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[TextUniform {
+                view_projection: [[0.0; 4]; 4],
+                screen_size: [1.0; 4],
+            }]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("text_uniform_bind_group_layout"),
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("text_uniform_bind_group"),
+        });
+
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("Text Shader"),
+                flags: wgpu::ShaderFlags::all(),
+                source: wgpu::ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+            }
+        );
+
+        let layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Text Pipeline Layout"),
+                bind_group_layouts: &[&atlas_bind_group_layout, &uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Text Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "main",
+                    buffers: &[TextVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    clamp_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                }
+            }
+        );
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (MAX_GLYPHS * VERTICES_PER_GLYPH * std::mem::size_of::<TextVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            font,
+            atlas,
+            atlas_texture,
+            atlas_bind_group,
+
+            uniform_buffer,
+            uniform_bind_group,
+            pipeline,
+
+            vertex_buffer,
+            num_vertices: 0,
+            queued: Vec::new(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn font(&self) -> &ab_glyph::FontArc {
+        &self.font
+    }
+
+    /// Draw text this frame, has to be called again every frame
+    pub fn queue(&mut self, text: &str, space: TextSpace, options: LayoutOptions, color: [f32; 4]) {
+        self.queued.push(QueuedText {
+            text: text.to_string(),
+            space,
+            options,
+            color,
+        });
+    }
+
+    /// Lay out everything queued since the last call and upload it, together with new glyphs
+    pub fn prepare(
+        &mut self,
+        queue: &wgpu::Queue,
+        camera: &crate::camera::Camera,
+        screen_width: u32,
+        screen_height: u32,
+    ) {
+        let queued = std::mem::take(&mut self.queued);
+        let mut vertices = Vec::new();
+        if self.build_vertices(&queued, &mut vertices).is_err() {
+            // start over, only the glyphs of this frame come back
+            self.atlas.clear();
+            vertices.clear();
+            if self.build_vertices(&queued, &mut vertices).is_err() {
+                log::warn!("The text of this frame doesn't fit into the glyph atlas");
+            }
+        }
+
+        if self.atlas.take_dirty() {
+            let (width, height) = self.atlas.size();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.atlas_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                &self.atlas.pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(width),
+                    rows_per_image: std::num::NonZeroU32::new(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let uniform = TextUniform {
+            view_projection: camera.build_view_projection_matrix().into(),
+            screen_size: [screen_width as f32, screen_height as f32, 0.0, 0.0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    /// Draw the prepared text on top of whatever is in target
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        if self.num_vertices == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Text Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.atlas_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+
+    /// Two triangles per glyph, fails if a glyph didn't fit into the atlas anymore
+    fn build_vertices(&mut self, queued: &[QueuedText], vertices: &mut Vec<TextVertex>) -> Result<(), AtlasFull> {
+        let (atlas_width, atlas_height) = self.atlas.size();
+        for text in queued {
+            let layout = layout(&self.font, &text.text, &text.options);
+            let (anchor, origin) = match text.space {
+                TextSpace::Screen(position) => ([0.0; 4], position),
+                // bottom center of the box on the point
+                TextSpace::World(point) => ([point.x, point.y, point.z, 1.0], [-layout.width / 2.0, -layout.height]),
+            };
+
+            for glyph in &layout.glyphs {
+                if vertices.len() + VERTICES_PER_GLYPH > MAX_GLYPHS * VERTICES_PER_GLYPH {
+                    return Ok(());
+                }
+                let cached = match self.atlas.glyph(&self.font, glyph.id, text.options.size)? {
+                    Some(cached) => cached,
+                    None => continue,
+                };
+
+                let left = origin[0] + glyph.position[0] + cached.offset[0];
+                let top = origin[1] + glyph.position[1] + cached.offset[1];
+                let right = left + cached.rect.width as f32;
+                let bottom = top + cached.rect.height as f32;
+                let [u_min, v_min, u_max, v_max] = cached.rect.uv(atlas_width, atlas_height);
+                let vertex = |x: f32, y: f32, u: f32, v: f32| TextVertex {
+                    anchor,
+                    offset: [x, y],
+                    texture_coords: [u, v],
+                    color: text.color,
+                };
+                let left_top = vertex(left, top, u_min, v_min);
+                let right_top = vertex(right, top, u_max, v_min);
+                let left_bottom = vertex(left, bottom, u_min, v_max);
+                let right_bottom = vertex(right, bottom, u_max, v_max);
+                vertices.extend_from_slice(&[left_top, left_bottom, right_bottom, left_top, right_bottom, right_top]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monospaced font that comes with egui, so the tests don't need a font file
+    fn font() -> ab_glyph::FontArc {
+        let data = egui::FontDefinitions::default().font_data["ProggyClean"].to_vec();
+        ab_glyph::FontArc::try_from_vec(data).unwrap()
+    }
+
+    fn options(max_width: Option<f32>, align: Align) -> LayoutOptions {
+        LayoutOptions {
+            size: 16.0,
+            max_width,
+            align,
+            line_spacing: 1.0,
+        }
+    }
+
+    /// Width of every character at the test size
+    fn advance(font: &ab_glyph::FontArc) -> f32 {
+        font.as_scaled(16.0).h_advance(font.glyph_id('a'))
+    }
+
+    /// x of every glyph per line
+    fn columns(layout: &TextLayout) -> Vec<Vec<f32>> {
+        let mut lines: Vec<(f32, Vec<f32>)> = Vec::new();
+        for glyph in &layout.glyphs {
+            match lines.last_mut() {
+                Some((baseline, line)) if *baseline == glyph.position[1] => line.push(glyph.position[0]),
+                _ => lines.push((glyph.position[1], vec![glyph.position[0]])),
+            }
+        }
+        lines.into_iter().map(|(_, line)| line).collect()
+    }
+
+    fn approx(values: &[f32], expected: &[f32]) -> bool {
+        values.len() == expected.len() && values.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    #[test]
+    fn single_line_skips_whitespace() {
+        let font = font();
+        let a = advance(&font);
+        let layout = layout(&font, "ab cd ", &options(None, Align::Left));
+        assert_eq!(layout.lines, 1);
+        assert_eq!(layout.glyphs.len(), 4);
+        assert!(approx(&columns(&layout)[0], &[0.0, a, 3.0 * a, 4.0 * a]));
+        // the trailing space doesn't count
+        assert!((layout.width - 5.0 * a).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lines_from_newlines() {
+        let font = font();
+        let a = advance(&font);
+        let scaled = font.as_scaled(16.0);
+        let line_height = scaled.height() + scaled.line_gap();
+
+        let layout = layout(&font, "abc\nd\n\nef", &options(None, Align::Left));
+        assert_eq!(layout.lines, 4);
+        assert_eq!(columns(&layout), vec![vec![0.0, a, 2.0 * a], vec![0.0], vec![0.0, a]]);
+        let baselines: Vec<_> = layout.glyphs.iter().map(|glyph| glyph.position[1]).collect();
+        assert!((baselines[3] - baselines[0] - line_height).abs() < 1e-3);
+        assert!((baselines[4] - baselines[0] - 3.0 * line_height).abs() < 1e-3);
+        assert!((layout.height - (3.0 * line_height + scaled.height())).abs() < 1e-3);
+        assert!((layout.width - 3.0 * a).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wraps_at_whitespace() {
+        let font = font();
+        let a = advance(&font);
+        let layout = layout(&font, "abc def ghi", &options(Some(7.5 * a), Align::Left));
+        assert_eq!(layout.lines, 2);
+        let columns = columns(&layout);
+        assert!(approx(&columns[0], &[0.0, a, 2.0 * a, 4.0 * a, 5.0 * a, 6.0 * a]));
+        assert!(approx(&columns[1], &[0.0, a, 2.0 * a]));
+        assert_eq!(layout.width, 7.5 * a);
+    }
+
+    #[test]
+    fn wraps_inside_overlong_words() {
+        let font = font();
+        let a = advance(&font);
+        let layout = layout(&font, "abcdefgh", &options(Some(3.5 * a), Align::Left));
+        assert_eq!(layout.lines, 3);
+        let columns = columns(&layout);
+        assert!(approx(&columns[0], &[0.0, a, 2.0 * a]));
+        assert!(approx(&columns[1], &[0.0, a, 2.0 * a]));
+        assert!(approx(&columns[2], &[0.0, a]));
+    }
+
+    #[test]
+    fn center_and_right_alignment() {
+        let font = font();
+        let a = advance(&font);
+        let centered = layout(&font, "ab\nabcd", &options(Some(10.0 * a), Align::Center));
+        let lines = columns(&centered);
+        assert!(approx(&lines[0], &[4.0 * a, 5.0 * a]));
+        assert!(approx(&lines[1], &[3.0 * a, 4.0 * a, 5.0 * a, 6.0 * a]));
+
+        let right = layout(&font, "ab \nabcd", &options(Some(10.0 * a), Align::Right));
+        let lines = columns(&right);
+        // trailing whitespace doesn't push the line to the left
+        assert!(approx(&lines[0], &[8.0 * a, 9.0 * a]));
+        assert!(approx(&lines[1], &[6.0 * a, 7.0 * a, 8.0 * a, 9.0 * a]));
+    }
+
+    #[test]
+    fn glyph_atlas_caches_glyphs() {
+        let font = font();
+        let mut atlas = GlyphAtlas::new(64);
+        assert_eq!(atlas.glyph(&font, font.glyph_id(' '), 16.0), Ok(None));
+
+        let glyph = atlas.glyph(&font, font.glyph_id('a'), 16.0).unwrap().unwrap();
+        assert!(atlas.take_dirty());
+        assert!(!atlas.take_dirty());
+        assert_eq!(atlas.glyph(&font, font.glyph_id('a'), 16.0), Ok(Some(glyph)));
+        assert!(!atlas.take_dirty());
+        assert!(atlas.pixels.iter().any(|&pixel| pixel > 0));
+
+        atlas.clear();
+        assert!(atlas.pixels.iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn glyph_atlas_runs_full() {
+        let font = font();
+        let mut atlas = GlyphAtlas::new(16);
+        let result = "abcdefghijklmnopqrstuvwxyz"
+            .chars()
+            .map(|c| atlas.glyph(&font, font.glyph_id(c), 16.0))
+            .find(|result| result.is_err());
+        assert_eq!(result, Some(Err(AtlasFull)));
+    }
+}
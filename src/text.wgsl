@@ -0,0 +1,56 @@
+// Vertex shader
+[[block]]
+struct TextUniform {
+	view_projection: mat4x4<f32>;
+	// x, y in pixels, zw unused
+	screen_size: vec4<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> text: TextUniform;
+
+struct VertexInput {
+	// w is 1 for world space text, 0 for text placed in pixels from the top left corner
+	[[location(0)]] anchor: vec4<f32>;
+	// pixels from the anchor, y points down
+	[[location(1)]] offset: vec2<f32>;
+	[[location(2)]] texture_coords: vec2<f32>;
+	[[location(3)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] clip_coordinate: vec4<f32>;
+	[[location(0)]] texture_coords: vec2<f32>;
+	[[location(1)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn main(in: VertexInput) -> VertexOutput {
+	var out: VertexOutput;
+	var clip: vec4<f32> = vec4<f32>(-1.0, 1.0, 0.0, 1.0);
+	if (in.anchor.w > 0.5) {
+		clip = text.view_projection * vec4<f32>(in.anchor.xyz, 1.0);
+	}
+	// multiplied by w, so labels keep their size in pixels
+	let pixel = vec2<f32>(2.0, -2.0) / text.screen_size.xy;
+	out.clip_coordinate = vec4<f32>(clip.xy + in.offset * pixel * clip.w, clip.z, clip.w);
+	if (clip.w <= 0.0) {
+		// behind the camera
+		out.clip_coordinate = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+	}
+	out.texture_coords = in.texture_coords;
+	out.color = in.color;
+	return out;
+}
+
+// Fragment shader
+// single channel glyph coverage
+[[group(0), binding(0)]]
+var t_atlas: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_atlas: sampler;
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let coverage = textureSample(t_atlas, s_atlas, in.texture_coords).r;
+	return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,299 @@
+use winit::event::{Event, WindowEvent};
+
+/// Immediate-mode debug UI on top of the final image, toggled with F1
+/// while it's open the cursor is free and the camera doesn't get any input
+pub struct DebugUi {
+    platform: egui_winit_platform::Platform,
+    render_pass: egui_wgpu_backend::RenderPass,
+    // egui animations need the time since startup
+    start: std::time::Instant,
+    // kept up to date by ScaleFactorChanged events
+    scale_factor: f32,
+    // tessellated in end_frame, drawn in render
+    paint_jobs: Vec<egui::paint::ClippedMesh>,
+    pub visible: bool,
+}
+
+impl DebugUi {
+    /// size and scale_factor of the window, or of the offscreen target
+    pub fn new(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let platform = egui_winit_platform::Platform::new(egui_winit_platform::PlatformDescriptor {
+            physical_width: size.width,
+            physical_height: size.height,
+            scale_factor,
+            font_definitions: egui::FontDefinitions::default(),
+            style: egui::Style::default(),
+        });
+        // drawn after the blit, no MSAA
+        let render_pass = egui_wgpu_backend::RenderPass::new(device, format, 1);
+
+        Self {
+            platform,
+            render_pass,
+            start: std::time::Instant::now(),
+            scale_factor: scale_factor as f32,
+            paint_jobs: Vec::new(),
+            visible: false,
+        }
+    }
+
+    /// Feed an event to egui, true if the UI uses it and nobody else should see it
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> bool {
+        if let Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { scale_factor, .. }, .. } = event {
+            self.scale_factor = *scale_factor as f32;
+        }
+        if !self.visible {
+            // the screen size still has to be known, once the UI opens
+            if let Event::WindowEvent {
+                event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
+                ..
+            } = event {
+                self.platform.handle_event(event);
+            }
+            return false;
+        }
+
+        self.platform.handle_event(event);
+        self.platform.captures_event(event)
+    }
+
+    /// Start building the UI of this frame, None if it's hidden
+    pub fn begin_frame(&mut self) -> Option<egui::CtxRef> {
+        if !self.visible {
+            self.paint_jobs.clear();
+            return None;
+        }
+        self.platform.update_time(self.start.elapsed().as_secs_f64());
+        self.platform.begin_frame();
+        Some(self.platform.context())
+    }
+
+    /// Tessellate the UI and upload its font texture, has to follow begin_frame
+    pub fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let (_output, shapes) = self.platform.end_frame();
+        let context = self.platform.context();
+        self.paint_jobs = context.tessellate(shapes);
+
+        self.render_pass.update_texture(device, queue, &context.texture());
+        self.render_pass.update_user_textures(device, queue);
+    }
+
+    /// Draw the UI on top of whatever is in target
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) {
+        if self.paint_jobs.is_empty() {
+            return;
+        }
+
+        let screen_descriptor = egui_wgpu_backend::ScreenDescriptor {
+            physical_width: sc_desc.width,
+            physical_height: sc_desc.height,
+            scale_factor: self.scale_factor,
+        };
+        self.render_pass.update_buffers(device, queue, &self.paint_jobs, &screen_descriptor);
+        // None keeps the frame below
+        self.render_pass.execute(encoder, target, &self.paint_jobs, &screen_descriptor, None);
+    }
+}
+
+/// Movement of every controlled camera
+pub fn camera_panel(ctx: &egui::CtxRef, world: &mut hecs::World) {
+    egui::Window::new("Camera").show(ctx, |ui| {
+        for (_, (camera, controller)) in world.query_mut::<(&crate::ecs::Camera, &mut crate::ecs::CameraController)>() {
+            ui.label(format!("eye: {:.2} {:.2} {:.2}", camera.eye.x, camera.eye.y, camera.eye.z));
+            ui.label(format!("target: {:.2} {:.2} {:.2}", camera.target.x, camera.target.y, camera.target.z));
+            ui.add(egui::Slider::new(&mut controller.speed, 0.001..=1.0).text("speed"));
+            ui.add(egui::Slider::new(&mut controller.mouse_slowdown, 10.0..=1000.0).text("mouse slowdown"));
+            ui.separator();
+        }
+    });
+}
+
+/// Every light entity and the ambient term
+pub fn lights_panel(ctx: &egui::CtxRef, world: &mut hecs::World, ambient: &mut f32) {
+    egui::Window::new("Lights").show(ctx, |ui| {
+        ui.add(egui::Slider::new(ambient, 0.0..=1.0).text("ambient"));
+        for (entity, (name, light)) in world.query_mut::<(Option<&crate::ecs::Name>, &mut crate::ecs::Light)>() {
+            let title = name.map_or_else(|| format!("{:?}", entity), |name| name.0.clone());
+            egui::CollapsingHeader::new(title).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("color");
+                    ui.color_edit_button_rgb(&mut light.color);
+                });
+                ui.add(egui::Slider::new(&mut light.intensity, 0.0..=10.0).text("intensity"));
+                ui.add(egui::Slider::new(&mut light.radius, 0.1..=20.0).text("radius"));
+            });
+        }
+    });
+}
+
+/// Parameters of every loaded material
+pub fn materials_panel(ctx: &egui::CtxRef, materials: &mut [crate::material::Material]) {
+    egui::Window::new("Materials").show(ctx, |ui| {
+        for material in materials {
+            egui::CollapsingHeader::new(&material.name).show(ui, |ui| {
+                let params = &mut material.params;
+                ui.add(egui::Slider::new(&mut params.specular, 0.0..=1.0).text("specular"));
+                ui.add(egui::Slider::new(&mut params.shininess, 1.0..=256.0).text("shininess"));
+                ui.add(egui::Slider::new(&mut params.emissive, 0.0..=10.0).text("emissive"));
+            });
+        }
+    });
+}
+
+/// Everything the renderer can change without being rebuilt
+pub fn render_panel(
+    ctx: &egui::CtxRef,
+    post_processor: &mut crate::post::PostProcessor,
+    ssao: &mut crate::ssao::SsaoSettings,
+    clear_color: &mut wgpu::Color,
+    show_overlay: &mut bool,
+    show_debug_shapes: &mut bool,
+    debug_view: &mut crate::debug_view::DebugView,
+) {
+    egui::Window::new("Render").show(ctx, |ui| {
+        let mut color = [clear_color.r as f32, clear_color.g as f32, clear_color.b as f32];
+        ui.horizontal(|ui| {
+            ui.label("clear color");
+            ui.color_edit_button_rgb(&mut color);
+        });
+        *clear_color = wgpu::Color {
+            r: color[0] as f64,
+            g: color[1] as f64,
+            b: color[2] as f64,
+            a: 1.0,
+        };
+        ui.checkbox(show_overlay, "frame stats (F3)");
+        ui.checkbox(show_debug_shapes, "debug shapes (F2)");
+
+        egui::CollapsingHeader::new("Debug view (F4)").show(ui, |ui| {
+            for view in crate::debug_view::DebugView::ALL.iter() {
+                ui.radio_value(debug_view, *view, view.name());
+            }
+        });
+
+        egui::CollapsingHeader::new("Bloom").show(ui, |ui| {
+            let bloom = post_processor.bloom_settings_mut();
+            ui.checkbox(&mut bloom.enabled, "enabled (B)");
+            ui.add(egui::Slider::new(&mut bloom.threshold, 0.0..=5.0).text("threshold"));
+            ui.add(egui::Slider::new(&mut bloom.intensity, 0.0..=2.0).text("intensity"));
+            ui.add(egui::Slider::new(&mut bloom.radius, 0.1..=4.0).text("radius"));
+        });
+
+        egui::CollapsingHeader::new("SSAO").show(ui, |ui| {
+            ui.checkbox(&mut ssao.enabled, "enabled");
+            ui.add(egui::Slider::new(&mut ssao.radius, 0.05..=2.0).text("radius"));
+            ui.add(egui::Slider::new(&mut ssao.bias, 0.0..=0.1).text("bias"));
+            ui.add(egui::Slider::new(&mut ssao.intensity, 0.1..=4.0).text("intensity"));
+        });
+
+        egui::CollapsingHeader::new("Post effects").show(ui, |ui| {
+            // copied, so the chain can be changed while going through it
+            let effects: Vec<(crate::post::PostEffect, bool)> = post_processor
+                .effects()
+                .map(|(effect, enabled)| (*effect, enabled))
+                .collect();
+            for (index, (mut effect, mut enabled)) in effects.into_iter().enumerate() {
+                let before = (effect, enabled);
+                ui.checkbox(&mut enabled, effect.name());
+                match &mut effect {
+                    crate::post::PostEffect::ToneMapping { exposure } => {
+                        ui.add(egui::Slider::new(exposure, 0.1..=5.0).text("exposure"));
+                    },
+                    crate::post::PostEffect::Gamma { gamma } => {
+                        ui.add(egui::Slider::new(gamma, 1.0..=3.0).text("gamma"));
+                    },
+                    crate::post::PostEffect::Vignette { intensity, radius, softness } => {
+                        ui.add(egui::Slider::new(intensity, 0.0..=1.0).text("intensity"));
+                        ui.add(egui::Slider::new(radius, 0.0..=1.5).text("radius"));
+                        ui.add(egui::Slider::new(softness, 0.01..=1.0).text("softness"));
+                    },
+                    crate::post::PostEffect::ColorGrading { intensity } => {
+                        ui.add(egui::Slider::new(intensity, 0.0..=1.0).text("intensity"));
+                    },
+                    crate::post::PostEffect::Fxaa => {},
+                }
+                if (effect, enabled) != before {
+                    post_processor.set_effect(index, effect);
+                    post_processor.set_enabled(index, enabled);
+                }
+            }
+        });
+    });
+}
+
+/// Mode, space and snapping of the gizmo on the selected object
+pub fn gizmo_panel(ctx: &egui::CtxRef, gizmo: &mut crate::gizmo::Gizmo) {
+    egui::Window::new("Gizmo").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            for mode in crate::gizmo::GizmoMode::ALL.iter() {
+                ui.radio_value(&mut gizmo.mode, *mode, mode.name());
+            }
+        });
+        ui.horizontal(|ui| {
+            for space in [crate::gizmo::GizmoSpace::World, crate::gizmo::GizmoSpace::Local].iter() {
+                ui.radio_value(&mut gizmo.space, *space, space.name());
+            }
+        });
+        let snapping = &mut gizmo.snapping;
+        ui.checkbox(&mut snapping.enabled, "snapping (N)");
+        ui.add(egui::Slider::new(&mut snapping.translation, 0.05..=2.0).text("translation"));
+        ui.add(egui::Slider::new(&mut snapping.rotation, 1.0..=90.0).text("rotation (degrees)"));
+        ui.add(egui::Slider::new(&mut snapping.scale, 0.01..=1.0).text("scale"));
+    });
+}
+
+/// Button clicked in the history panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+    Clear,
+    /// keep this many undo steps
+    SetLimit(usize),
+}
+
+/// Undo, redo and clear buttons and the number of steps kept above the edits, newest first,
+/// undone ones are grayed out
+pub fn history_panel(
+    ctx: &egui::CtxRef,
+    commands: &crate::command::CommandStack<crate::command::SceneEdit>,
+) -> Option<HistoryAction> {
+    let mut action = None;
+    egui::Window::new("History").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            if ui.add(egui::Button::new("Undo (Ctrl+Z)").enabled(commands.can_undo())).clicked() {
+                action = Some(HistoryAction::Undo);
+            }
+            if ui.add(egui::Button::new("Redo (Ctrl+Y)").enabled(commands.can_redo())).clicked() {
+                action = Some(HistoryAction::Redo);
+            }
+            if ui.add(egui::Button::new("Clear").enabled(commands.can_undo() || commands.can_redo())).clicked() {
+                action = Some(HistoryAction::Clear);
+            }
+        });
+        let mut limit = commands.limit();
+        if ui.add(egui::Slider::new(&mut limit, 1..=1000).text("steps kept")).changed() {
+            action = Some(HistoryAction::SetLimit(limit));
+        }
+        ui.separator();
+        for name in commands.redo_names() {
+            ui.colored_label(egui::Color32::GRAY, name);
+        }
+        for name in commands.undo_names().rev() {
+            ui.label(name);
+        }
+    });
+    action
+}
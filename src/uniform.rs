@@ -2,6 +2,12 @@
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniform {
     view_projection: [[f32; 4]; 4],
+    // needed for view space normals
+    view: [[f32; 4]; 4],
+    // needed to reconstruct world positions from depth
+    inverse_view_projection: [[f32; 4]; 4],
+    // camera position for specular lighting, w is unused
+    eye: [f32; 4],
 }
 
 impl Uniform {
@@ -9,10 +15,21 @@ impl Uniform {
         use cgmath::SquareMatrix;
         Self {
             view_projection: cgmath::Matrix4::identity().into(),
+            view: cgmath::Matrix4::identity().into(),
+            inverse_view_projection: cgmath::Matrix4::identity().into(),
+            eye: [0.0; 4],
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &crate::camera::Camera) {
-        self.view_projection = camera.build_view_projection_matrix().into();
+        use cgmath::SquareMatrix;
+        let view_projection = camera.build_view_projection_matrix();
+        self.view_projection = view_projection.into();
+        self.view = camera.build_view_matrix().into();
+        self.inverse_view_projection = view_projection
+            .invert()
+            .unwrap_or_else(cgmath::Matrix4::identity)
+            .into();
+        self.eye = camera.eye.to_homogeneous().into();
     }
 }